@@ -0,0 +1,479 @@
+use super::filters;
+use super::flags;
+use super::handlers;
+use super::metrics::Progress;
+use super::models::LoggedAction;
+use super::packs::{self, SharedPacks};
+use super::rooms::{Room, Rooms};
+use super::shutdown::Shutdown;
+use common::game::{GamePhase, Notice, PackInfo, PastRound, Player, NEARLY_EXHAUSTED};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use tracing::info;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+fn default_redact() -> bool {
+    true
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RedactQuery {
+    /// Blank out the word, the hints and the guess, on unless asked otherwise
+    /// so the state can be looked at while playing.
+    #[serde(default = "default_redact")]
+    pub redact: bool,
+}
+
+/// A room in the list of rooms.
+#[derive(Serialize, Debug)]
+pub struct RoomSummary {
+    pub name: String,
+    pub connections: usize,
+    pub phase: &'static str,
+    pub players: Vec<Player>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StateReport {
+    pub room: String,
+    pub connections: usize,
+    pub players: Vec<Player>,
+    pub active_index: Option<usize>,
+    pub phase: GamePhase,
+    pub past_rounds: Vec<PastRound>,
+    pub dictionary_size: usize,
+    /// With the number of words the room hasn't played yet.
+    pub packs: Vec<PackInfo>,
+    pub selected_packs: Vec<String>,
+    /// Words played in the room since its history was last reset.
+    pub history_size: usize,
+    /// Unused words of the selected packs are running out.
+    pub nearly_exhausted: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NoticeRequest {
+    pub message: String,
+}
+
+/// The admin API, `<room>` is the name of an open room. Names in the path
+/// are percent-encoded, `Bot%201` for `Bot 1`:
+///
+/// - `GET /admin/rooms`: every room with its phase, players and connections
+/// - `GET /admin/rooms/<room>`: players, phase and history of the room's game
+/// - `GET /admin/rooms/<room>/log`: the most recent actions in the room and
+///   whether they were accepted
+/// - `POST /admin/rooms/<room>/players/<name>/kick`: remove a player,
+///   connected or not
+/// - `POST /admin/rooms/<room>/advance`: force the game into the next phase
+/// - `POST /admin/rooms/<room>/notice`: show `{"message": "..."}` to everyone in the room
+/// - `POST /admin/rooms/<room>/close`: disconnect everyone and close the room
+/// - `POST /admin/rooms/<room>/history/reset`: forget which words the room played
+/// - `POST /admin/notice`: show `{"message": "..."}` to every connection of every room
+/// - `POST /admin/packs/reload`: read the word files again, games in progress
+///   keep their words
+/// - `GET /admin/flags`: the flagged words, most flagged first, 404 without a flag file
+/// - `POST /admin/shutdown`: drain and stop the server, like SIGTERM
+///
+/// The GET endpoints take `?redact=false` to show words, hints and guesses.
+pub fn routes(
+    token: Option<String>,
+    rooms: Rooms,
+    shutdown: Shutdown,
+    packs: SharedPacks,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let with_rooms = filters::with_rooms(rooms.clone());
+    let room = warp::path("rooms").and(filters::open_room(rooms));
+
+    let list_rooms = warp::path!("rooms")
+        .and(warp::get())
+        .and(with_rooms.clone())
+        .and_then(list_rooms);
+    let get_state = room
+        .clone()
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query::<RedactQuery>())
+        .and_then(get_state);
+    let get_log = room
+        .clone()
+        .and(warp::path!("log"))
+        .and(warp::get())
+        .and(warp::query::<RedactQuery>())
+        .and_then(get_log);
+    let kick = room
+        .clone()
+        .and(warp::path("players"))
+        .and(filters::name())
+        .and(warp::path!("kick"))
+        .and(warp::post())
+        .and_then(kick);
+    let advance = room
+        .clone()
+        .and(warp::path!("advance"))
+        .and(warp::post())
+        .and_then(advance);
+    let room_notice = room
+        .clone()
+        .and(warp::path!("notice"))
+        .and(warp::post())
+        .and(warp::body::json::<NoticeRequest>())
+        .and_then(room_notice);
+    let close = room
+        .clone()
+        .and(warp::path!("close"))
+        .and(warp::post())
+        .and(with_rooms.clone())
+        .and_then(close);
+    let reset_history = room
+        .and(warp::path!("history" / "reset"))
+        .and(warp::post())
+        .and_then(reset_history);
+    let notice = warp::path!("notice")
+        .and(warp::post())
+        .and(warp::body::json::<NoticeRequest>())
+        .and(with_rooms.clone())
+        .and_then(notice);
+    let reload_packs = warp::path!("packs" / "reload")
+        .and(warp::post())
+        .and(filters::with_packs(packs))
+        .and(with_rooms.clone())
+        .and_then(reload_packs);
+    let get_flags = warp::path!("flags")
+        .and(warp::get())
+        .and(with_rooms)
+        .and_then(get_flags);
+    let shutdown = warp::path!("shutdown")
+        .and(warp::post())
+        .and(filters::with_shutdown(shutdown))
+        .map(|shutdown: Shutdown| {
+            shutdown.request();
+            StatusCode::ACCEPTED
+        });
+
+    warp::path("admin").and(filters::admin(token)).and(
+        list_rooms
+            .or(get_state)
+            .or(get_log)
+            .or(kick)
+            .or(advance)
+            .or(room_notice)
+            .or(close)
+            .or(reset_history)
+            .or(notice)
+            .or(reload_packs)
+            .or(get_flags)
+            .or(shutdown),
+    )
+}
+
+pub async fn list_rooms(rooms: Rooms) -> Result<impl Reply, Infallible> {
+    let mut summaries = vec![];
+    for room in rooms.all().await {
+        let connections = room.users.read().await.len();
+        let state = room.state.lock().await;
+        summaries.push(RoomSummary {
+            name: room.name.clone(),
+            connections,
+            phase: state.phase.name(),
+            players: state.players.clone(),
+        });
+    }
+    Ok(warp::reply::json(&summaries))
+}
+
+pub async fn get_state(room: Room, query: RedactQuery) -> Result<impl Reply, Infallible> {
+    let connections = room.users.read().await.len();
+    let state = room.state.lock().await;
+    let phase = if query.redact {
+        state.phase.redacted()
+    } else {
+        state.phase.clone()
+    };
+    Ok(warp::reply::json(&StateReport {
+        room: room.name.clone(),
+        connections,
+        players: state.players.clone(),
+        active_index: state.active_index,
+        phase,
+        past_rounds: state.past_rounds.clone(),
+        dictionary_size: state.dictionary.len(),
+        packs: state.library.infos_for(&state.history),
+        selected_packs: state.selected_packs.clone(),
+        history_size: state.history.len(),
+        nearly_exhausted: state.dictionary.unused(&state.history) <= NEARLY_EXHAUSTED,
+    }))
+}
+
+pub async fn get_log(room: Room, query: RedactQuery) -> Result<impl Reply, Infallible> {
+    let log = room.log.lock().await;
+    let entries: Vec<LoggedAction> = log
+        .iter()
+        .map(|entry| LoggedAction {
+            time: entry.time,
+            action: if query.redact {
+                entry.action.redacted()
+            } else {
+                entry.action.clone()
+            },
+            accepted: entry.accepted,
+        })
+        .collect();
+    Ok(warp::reply::json(&entries))
+}
+
+pub async fn kick(room: Room, name: String) -> Result<impl Reply, Infallible> {
+    let kicked_id = {
+        let mut state = room.state.lock().await;
+        let progress = Progress::of(&state);
+        let id = state
+            .players
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.id);
+        let result = state.remove_player(&name).map(|_| id);
+        progress.record(&state);
+        result
+    };
+    match kicked_id {
+        Some(id) => {
+            info!(room = %room.name, player = %name, "admin kicked player");
+            if let Some(id) = id {
+                handlers::notice(&room.users, id, Notice::Kicked).await;
+            }
+            handlers::notify_all(&room.users, &room.state).await;
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn advance(room: Room) -> Result<impl Reply, Infallible> {
+    let result = {
+        let mut state = room.state.lock().await;
+        let progress = Progress::of(&state);
+        let result = state.force_advance();
+        progress.record(&state);
+        result
+    };
+    match result {
+        Some(()) => {
+            info!(room = %room.name, "admin advanced the game");
+            handlers::notify_all(&room.users, &room.state).await;
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Ok(StatusCode::CONFLICT),
+    }
+}
+
+pub async fn room_notice(room: Room, request: NoticeRequest) -> Result<impl Reply, Infallible> {
+    handlers::broadcast_notice(&room.users, Notice::Message(request.message)).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn notice(request: NoticeRequest, rooms: Rooms) -> Result<impl Reply, Infallible> {
+    for room in rooms.all().await {
+        handlers::broadcast_notice(&room.users, Notice::Message(request.message.clone())).await;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn close(room: Room, rooms: Rooms) -> Result<impl Reply, Infallible> {
+    info!(room = %room.name, "admin closed the room");
+    rooms.close(&room.name).await;
+    handlers::broadcast_notice(&room.users, Notice::GameClosed).await;
+    handlers::close_all(&room.users, 1000, "room closed").await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn reset_history(room: Room) -> Result<impl Reply, Infallible> {
+    let forgotten = {
+        let mut state = room.state.lock().await;
+        let forgotten = state.history.len();
+        state.history.clear();
+        forgotten
+    };
+    info!(room = %room.name, forgotten, "admin reset the word history");
+    handlers::notify_all(&room.users, &room.state).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Answers with the number of packs and words and the problems in the files,
+/// or 500 if nothing could be loaded, in which case the old packs stay.
+pub async fn reload_packs(packs: SharedPacks, rooms: Rooms) -> Result<impl Reply, Infallible> {
+    info!("admin reloads the word packs");
+    match packs::reload(&packs, &rooms).await {
+        Ok(report) => Ok(warp::reply::json(&report).into_response()),
+        Err(e) => Ok(
+            warp::reply::with_status(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR)
+                .into_response(),
+        ),
+    }
+}
+
+pub async fn get_flags(rooms: Rooms) -> Result<impl Reply, Infallible> {
+    let path = match rooms.flag_file() {
+        Some(path) => path,
+        None => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+    match flags::load(path) {
+        Ok(stored) => Ok(warp::reply::json(&flags::summarize(&stored)).into_response()),
+        Err(e) => Ok(
+            warp::reply::with_status(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR)
+                .into_response(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::{Action, Library};
+
+    fn no_packs() -> SharedPacks {
+        let path = std::path::Path::new("/nonexistent/words");
+        std::sync::Arc::new(std::sync::Mutex::new(packs::Packs::new(path)))
+    }
+
+    fn api(rooms: &Rooms) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        routes(
+            Some("token".to_string()),
+            rooms.clone(),
+            Shutdown::default(),
+            no_packs(),
+        )
+    }
+
+    fn request(method: &str, path: &str) -> warp::test::RequestBuilder {
+        warp::test::request()
+            .method(method)
+            .path(path)
+            .header("authorization", "Bearer token")
+    }
+
+    /// `rooms` with a room called `name` and a connection to it.
+    async fn open(
+        rooms: &Rooms,
+        name: &str,
+        id: usize,
+    ) -> (
+        Room,
+        tokio::sync::mpsc::UnboundedReceiver<Result<warp::ws::Message, warp::Error>>,
+    ) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        (rooms.enter(name, id, tx).await.unwrap(), rx)
+    }
+
+    #[tokio::test]
+    async fn kick_removes_player() {
+        let rooms = Rooms::new(Library::default(), None, None, None, 10);
+        let (room, _rx) = open(&rooms, "stammtisch", 1).await;
+        room.state.lock().await.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        let api = api(&rooms);
+        let kick = "/admin/rooms/stammtisch/players/a/kick";
+        let response = request("POST", kick).reply(&api).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(room.state.lock().await.players.is_empty());
+        let response = request("POST", kick).reply(&api).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = request("POST", "/admin/rooms/elsewhere/players/a/kick")
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn kick_takes_percent_encoded_names() {
+        let rooms = Rooms::new(Library::default(), None, None, None, 10);
+        let (room, _rx) = open(&rooms, "stammtisch", 1).await;
+        {
+            let mut state = room.state.lock().await;
+            state.action(&Action::Join {
+                id: 1,
+                name: "Jürgen".to_string(),
+            });
+            state.action(&Action::AddBot {
+                id: 1,
+                difficulty: common::game::Difficulty::Easy,
+            });
+        }
+        let api = api(&rooms);
+        for path in [
+            "/admin/rooms/stammtisch/players/Bot%201/kick",
+            "/admin/rooms/stammtisch/players/J%C3%BCrgen/kick",
+        ]
+        .iter()
+        {
+            let response = request("POST", path).reply(&api).await;
+            assert_eq!(response.status(), StatusCode::NO_CONTENT, "{}", path);
+        }
+        assert!(room.state.lock().await.players.is_empty());
+    }
+
+    #[tokio::test]
+    async fn log_is_redacted_by_default() {
+        let rooms = Rooms::new(Library::default(), None, None, None, 10);
+        let (room, _rx) = open(&rooms, "stammtisch", 1).await;
+        handlers::apply(
+            Action::GiveHint {
+                id: 1,
+                hint: Some("Versteck".to_string()),
+            },
+            &room.state,
+            &room.log,
+        )
+        .await;
+        let api = api(&rooms);
+        let redacted = request("GET", "/admin/rooms/stammtisch/log")
+            .reply(&api)
+            .await;
+        assert!(!String::from_utf8_lossy(redacted.body()).contains("Versteck"));
+        let full = request("GET", "/admin/rooms/stammtisch/log?redact=false")
+            .reply(&api)
+            .await;
+        assert!(String::from_utf8_lossy(full.body()).contains("Versteck"));
+    }
+
+    #[tokio::test]
+    async fn rooms_are_listed_inspected_and_closed() {
+        let rooms = Rooms::new(Library::default(), None, None, None, 10);
+        let (first, mut rx) = open(&rooms, "erster", 1).await;
+        open(&rooms, "zweiter", 2).await;
+        first.state.lock().await.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        let api = api(&rooms);
+
+        let list = request("GET", "/admin/rooms").reply(&api).await;
+        let list: serde_json::Value = serde_json::from_slice(list.body()).unwrap();
+        assert_eq!(list[0]["name"], "erster");
+        assert_eq!(list[0]["phase"], "GatherPlayers");
+        assert_eq!(list[0]["players"][0]["name"], "a");
+        assert_eq!(list[1]["name"], "zweiter");
+        assert_eq!(list[1]["connections"], 1);
+
+        let state = request("GET", "/admin/rooms/erster").reply(&api).await;
+        let state: serde_json::Value = serde_json::from_slice(state.body()).unwrap();
+        assert_eq!(state["room"], "erster");
+        assert_eq!(state["connections"], 1);
+
+        let response = request("POST", "/admin/rooms/erster/close")
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(!rooms.contains("erster").await);
+        let notice = rx.recv().await.unwrap().unwrap();
+        assert!(notice.to_str().unwrap().contains("GameClosed"));
+        assert!(rx.recv().await.unwrap().unwrap().is_close());
+        let response = request("GET", "/admin/rooms/erster").reply(&api).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let list = request("GET", "/admin/rooms").reply(&api).await;
+        let list: serde_json::Value = serde_json::from_slice(list.body()).unwrap();
+        assert_eq!(list.as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,514 @@
+use super::handlers;
+use super::rooms::{Room, Rooms};
+use common::game::{
+    Action, ActiveGuessing, Difficulty, GamePhase, HintCollection, HintFiltering, Player,
+    VisibleGamePhase, VisibleGuessing, Word,
+};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Where the bots take their hints and guesses from and how fast they play.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotConfig {
+    /// Lines like `Schwan: Vogel, See, weiß`, the strongest association first.
+    pub associations: PathBuf,
+    /// How long the bots wait before they act.
+    pub think_seconds: u64,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            associations: PathBuf::from("associations.txt"),
+            think_seconds: 2,
+        }
+    }
+}
+
+/// Hint given when the bot knows nothing about the word, the other players
+/// can filter it out.
+const NO_IDEA: &str = "?";
+
+/// Words people think of for a word, the strongest first. Keys are lowercase.
+#[derive(Debug, Default)]
+pub struct Associations {
+    words: HashMap<String, Vec<String>>,
+}
+
+impl Associations {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Lines without a `:` and comments starting with `#` are ignored. Every
+    /// association is added to the other word as well, after its own ones.
+    pub fn parse(content: &str) -> Self {
+        let mut words: HashMap<String, Vec<String>> = HashMap::new();
+        let mut reverse = vec![];
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('#') {
+                continue;
+            }
+            let (word, associated) = match line.split_once(':') {
+                Some((word, associated)) => (word.trim(), associated),
+                None => continue,
+            };
+            let associated: Vec<String> = associated
+                .split(',')
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .map(str::to_string)
+                .collect();
+            for a in associated.iter() {
+                reverse.push((a.to_lowercase(), word.to_string()));
+            }
+            words
+                .entry(word.to_lowercase())
+                .or_default()
+                .extend(associated);
+        }
+        for (word, associated) in reverse {
+            let entry = words.entry(word).or_default();
+            if !entry
+                .iter()
+                .any(|a| a.to_lowercase() == associated.to_lowercase())
+            {
+                entry.push(associated);
+            }
+        }
+        Self { words }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// The associations of `word` and its other spellings.
+    pub fn of(&self, word: &Word) -> Vec<&str> {
+        std::iter::once(&word.word)
+            .chain(word.also.iter())
+            .filter_map(|spelling| self.words.get(&spelling.to_lowercase()))
+            .flatten()
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Lowercase without umlauts, so "Schwäne" and "Schwan" compare alike.
+fn normalize(word: &str) -> String {
+    word.to_lowercase()
+        .replace('ä', "a")
+        .replace('ö', "o")
+        .replace('ü', "u")
+        .replace('ß', "ss")
+}
+
+/// Whether the two words share a stem: one contains the other or they start
+/// with the same five letters.
+fn same_stem(a: &str, b: &str) -> bool {
+    let (a, b) = (normalize(a), normalize(b));
+    let common = a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count();
+    a.contains(&b) || b.contains(&a) || common >= 5
+}
+
+/// A hint has to be a single word and mustn't be a form of the word or one
+/// of its other spellings.
+pub fn is_valid_hint(hint: &str, word: &Word) -> bool {
+    !hint.is_empty()
+        && !hint.chars().any(char::is_whitespace)
+        && !std::iter::once(&word.word)
+            .chain(word.also.iter())
+            .any(|spelling| same_stem(hint, spelling))
+}
+
+/// A hint for `word`. Easy bots give one of the strongest associations, hard
+/// ones one of the weakest. Hints in `taken` are avoided if possible.
+pub fn hint<R: Rng>(
+    associations: &Associations,
+    word: &Word,
+    difficulty: Difficulty,
+    taken: &[String],
+    rng: &mut R,
+) -> String {
+    let mut candidates: Vec<&str> = vec![];
+    for a in associations.of(word) {
+        if is_valid_hint(a, word)
+            && !candidates
+                .iter()
+                .any(|c| c.to_lowercase() == a.to_lowercase())
+        {
+            candidates.push(a);
+        }
+    }
+    let free: Vec<&str> = candidates
+        .iter()
+        .copied()
+        .filter(|c| !taken.iter().any(|t| t.to_lowercase() == c.to_lowercase()))
+        .collect();
+    let candidates = if free.is_empty() { candidates } else { free };
+    let third = candidates.len().div_ceil(3);
+    let range = match difficulty {
+        Difficulty::Easy => 0..third,
+        Difficulty::Medium => third..(2 * third).min(candidates.len()),
+        Difficulty::Hard => (2 * third).min(candidates.len())..candidates.len(),
+    };
+    let range = if range.is_empty() {
+        0..candidates.len()
+    } else {
+        range
+    };
+    candidates[range]
+        .choose(rng)
+        .map(|hint| hint.to_string())
+        .unwrap_or_else(|| NO_IDEA.to_string())
+}
+
+/// How strongly `candidate` is associated with the hints: every hint among
+/// its associations counts, the stronger the association the more.
+fn score(associations: &Associations, candidate: &Word, hints: &[String]) -> f64 {
+    let associated: Vec<String> = associations
+        .of(candidate)
+        .into_iter()
+        .map(normalize)
+        .collect();
+    hints
+        .iter()
+        .map(|hint| normalize(hint))
+        .filter_map(|hint| associated.iter().position(|a| *a == hint))
+        .map(|position| 1.0 / (1.0 + position as f64))
+        .sum()
+}
+
+/// The word of `words` which fits the hints best, none if no word has
+/// anything to do with them. Words in `played` don't come up again that soon
+/// and are left out.
+pub fn guess<R: Rng>(
+    associations: &Associations,
+    words: &[Word],
+    hints: &[String],
+    played: &[String],
+    rng: &mut R,
+) -> Option<String> {
+    let scored: Vec<(&Word, f64)> = words
+        .iter()
+        .filter(|word| !played.contains(&word.word))
+        .map(|word| (word, score(associations, word, hints)))
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+    let best = scored.iter().map(|(_, score)| *score).fold(0.0, f64::max);
+    let best: Vec<&Word> = scored
+        .into_iter()
+        .filter(|(_, score)| *score == best)
+        .map(|(word, _)| word)
+        .collect();
+    best.choose(rng).map(|word| word.word.clone())
+}
+
+/// What `bot` does next. Bots give hints and guess, and if no connected
+/// person is around to do it they also filter the hints and end the judging.
+/// They filter like `is_valid_hint`: hints which give the word away go, and so
+/// do hints given twice, one per action before filtering is finished.
+///
+/// A guessing bot only uses what its view shows: the allowed hints and the
+/// past rounds. The candidates are the words of the packs the game uses.
+pub fn next_action<R: Rng>(
+    state: &common::game::State,
+    bot: &Player,
+    associations: &Associations,
+    rng: &mut R,
+) -> Option<Action> {
+    let id = bot.id?;
+    let difficulty = bot.bot?;
+    let active = state.active_index.map(|index| &state.players[index]) == Some(bot);
+    let others_absent = || {
+        !state.players.iter().enumerate().any(|(index, p)| {
+            p.bot.is_none() && p.id.is_some() && Some(index) != state.active_index
+        })
+    };
+    let entry = |word: &str| {
+        state
+            .dictionary
+            .find(word)
+            .cloned()
+            .unwrap_or_else(|| Word::from(word))
+    };
+    match &state.phase {
+        GamePhase::HintCollection(HintCollection { word, hints }) => {
+            if active || hints.contains_key(&bot.name) {
+                return None;
+            }
+            let entry = entry(word);
+            let taken: Vec<String> = state
+                .bots()
+                .filter_map(|other| hints.get(&other.name))
+                .map(|hint| hint.content.clone())
+                .collect();
+            let hint = hint(associations, &entry, difficulty, &taken, rng);
+            Some(Action::GiveHint {
+                id,
+                hint: Some(hint),
+            })
+        }
+        GamePhase::HintFiltering(HintFiltering { word, hints }) if !active && others_absent() => {
+            let entry = entry(word);
+            let given: Vec<&str> = hints
+                .values()
+                .map(|hint| hint.content.as_str())
+                .filter(|content| !content.is_empty())
+                .collect();
+            let rejected = hints
+                .values()
+                .filter(|hint| hint.allowed && !hint.content.is_empty())
+                .find(|hint| {
+                    !is_valid_hint(&hint.content, &entry)
+                        || given
+                            .iter()
+                            .filter(|other| same_stem(other, &hint.content))
+                            .count()
+                            > 1
+                });
+            Some(match rejected {
+                Some(hint) => Action::FilterHint {
+                    id,
+                    hint: hint.content.clone(),
+                    allowed: false,
+                },
+                None => Action::FinishHintFiltering { id },
+            })
+        }
+        GamePhase::Guessing(_) if active => {
+            let view = state.get_view(id);
+            let hints: Vec<String> = match view.phase {
+                VisibleGamePhase::Guessing(VisibleGuessing::Active(ActiveGuessing {
+                    hints,
+                    ..
+                })) => hints.values().map(|hint| hint.to_string()).collect(),
+                _ => return None,
+            };
+            let played: Vec<String> = view.past_rounds.into_iter().map(|r| r.word).collect();
+            let words = state.dictionary.words();
+            Some(Action::Guess {
+                id,
+                guess: guess(associations, words, &hints, &played, rng),
+            })
+        }
+        GamePhase::Judging(_) if !active && others_absent() => Some(Action::FinishJudging { id }),
+        _ => None,
+    }
+}
+
+/// Let the bots of every room play, one action each per `think` interval, as
+/// long as someone is connected to the room.
+pub async fn run(rooms: Rooms, associations: Arc<Associations>, think: Duration) {
+    let mut ticks = tokio::time::interval(think);
+    loop {
+        ticks.tick().await;
+        for room in rooms.all().await {
+            play(&room, &associations).await;
+        }
+    }
+}
+
+async fn play(room: &Room, associations: &Associations) {
+    let bots: Vec<Player> = {
+        let state = room.state.lock().await;
+        if !state
+            .players
+            .iter()
+            .any(|p| p.bot.is_none() && p.id.is_some())
+        {
+            return;
+        }
+        state.bots().cloned().collect()
+    };
+    let mut changed = false;
+    for bot in bots {
+        let action = {
+            let state = room.state.lock().await;
+            next_action(&state, &bot, associations, &mut rand::thread_rng())
+        };
+        if let Some(action) = action {
+            debug!(room = %room.name, bot = %bot.name, action = action.name(), "bot acts");
+            changed |= handlers::apply(action, &room.state, &room.log)
+                .await
+                .is_some();
+        }
+    }
+    if changed {
+        handlers::notify_all(&room.users, &room.state).await;
+    }
+}
+
+/// The associations at `path`, none if they can't be read.
+pub fn load_or_warn(path: &Path) -> Associations {
+    match Associations::load(path) {
+        Ok(associations) => associations,
+        Err(e) => {
+            warn!(?path, error = %e, "couldn't read associations, bots will only give \"?\"");
+            Associations::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::{Library, WordPack};
+
+    #[test]
+    fn hints_follow_the_rules_and_the_difficulty() {
+        let associations = Associations::parse(
+            "# comment\nSchwan: Vogel, See, Schwäne, weiße Feder, Hals, Ballett\nTeich: Schwan\n",
+        );
+        let schwan = Word::from("Schwan");
+        assert!(!is_valid_hint("Schwäne", &schwan));
+        assert!(!is_valid_hint("weiße Feder", &schwan));
+        assert!(is_valid_hint("Vogel", &schwan));
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let easy = hint(&associations, &schwan, Difficulty::Easy, &[], &mut rng);
+            assert!(["Vogel", "See"].contains(&easy.as_str()), "{}", easy);
+            let hard = hint(&associations, &schwan, Difficulty::Hard, &[], &mut rng);
+            assert!(["Teich"].contains(&hard.as_str()), "{}", hard);
+        }
+        let taken = vec!["vogel".to_string()];
+        for _ in 0..20 {
+            let easy = hint(&associations, &schwan, Difficulty::Easy, &taken, &mut rng);
+            assert_ne!(easy, "Vogel");
+        }
+        let unknown = Word::from("Quatsch");
+        assert_eq!(
+            hint(&associations, &unknown, Difficulty::Easy, &[], &mut rng),
+            NO_IDEA
+        );
+    }
+
+    #[test]
+    fn duplicate_hints_are_filtered_before_guessing() {
+        let library = Library::new(vec![WordPack::new("test", "de", vec!["Schwan".into()])]);
+        let mut state = common::game::State::new(library);
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        for _ in 0..4 {
+            state.action(&Action::AddBot {
+                id: 1,
+                difficulty: Difficulty::Easy,
+            });
+        }
+        state.action(&Action::Start { id: 1 });
+        let bots: Vec<Player> = state.bots().cloned().collect();
+        for (bot, hint) in bots.iter().zip(["Vogel", "vogel", "See", "Schwäne"].iter()) {
+            state
+                .action(&Action::GiveHint {
+                    id: bot.id.unwrap(),
+                    hint: Some(hint.to_string()),
+                })
+                .unwrap();
+        }
+        assert!(matches!(state.phase, GamePhase::HintFiltering(_)));
+
+        let associations = Associations::parse("");
+        let mut rng = rand::thread_rng();
+        for _ in 0..5 {
+            let action = next_action(&state, &bots[0], &associations, &mut rng).unwrap();
+            state.action(&action).unwrap();
+            if matches!(action, Action::FinishHintFiltering { .. }) {
+                break;
+            }
+        }
+        assert!(matches!(state.phase, GamePhase::Guessing(_)));
+        let mut allowed: Vec<&str> = state
+            .phase
+            .hints()
+            .into_iter()
+            .flatten()
+            .filter(|(_, hint)| hint.allowed)
+            .map(|(_, hint)| hint.content.as_str())
+            .collect();
+        allowed.sort_unstable();
+        assert_eq!(allowed, ["See"]);
+    }
+
+    #[test]
+    fn guesses_rank_the_words_by_the_hints() {
+        let associations = Associations::parse(
+            "Schwan: Vogel, See, Hals\nGans: Vogel, Braten, Martin\nSee: Wasser, Ufer\n",
+        );
+        let words: Vec<Word> = ["Schwan", "Gans", "Steinbruch"]
+            .iter()
+            .map(|&w| Word::from(w))
+            .collect();
+        let mut rng = rand::thread_rng();
+        let hints = |hints: &[&str]| hints.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+        let guess_for = |h: &[&str], played: &[String], rng: &mut _| {
+            guess(&associations, &words, &hints(h), played, rng)
+        };
+        assert_eq!(
+            guess_for(&["Vögel", "Hals"], &[], &mut rng).as_deref(),
+            Some("Schwan")
+        );
+        assert_eq!(
+            guess_for(&["Vogel", "Martin"], &[], &mut rng).as_deref(),
+            Some("Gans")
+        );
+        assert_eq!(
+            guess_for(&["Vogel", "Hals"], &["Schwan".to_string()], &mut rng).as_deref(),
+            Some("Gans")
+        );
+        assert_eq!(guess_for(&["Granit"], &[], &mut rng), None);
+    }
+
+    #[test]
+    fn bots_fill_in_for_the_missing_players() {
+        let associations = Associations::parse("Steinbruch: Granit\nSchwan: Vogel\n");
+        let mut state = common::game::State::new(Library::default());
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::AddBot {
+            id: 1,
+            difficulty: Difficulty::Easy,
+        });
+        state.action(&Action::AddBot {
+            id: 1,
+            difficulty: Difficulty::Easy,
+        });
+        state.action(&Action::Start { id: 1 });
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let bots: Vec<Player> = state.bots().cloned().collect();
+            for bot in bots {
+                if let Some(action) = next_action(&state, &bot, &associations, &mut rng) {
+                    assert!(state.action(&action).is_some(), "{:?}", action);
+                }
+            }
+            let active = state.active_index == Some(0);
+            let person = match state.phase {
+                GamePhase::HintCollection(_) if !active => Action::GiveHint {
+                    id: 1,
+                    hint: Some("Stein".to_string()),
+                },
+                GamePhase::HintFiltering(_) if !active => Action::FinishHintFiltering { id: 1 },
+                GamePhase::Guessing(_) if active => Action::Guess { id: 1, guess: None },
+                GamePhase::Judging(_) if !active => Action::FinishJudging { id: 1 },
+                _ => continue,
+            };
+            state.action(&person);
+        }
+        let guessers: Vec<&str> = state.past_rounds.iter().map(|r| r.name.as_str()).collect();
+        assert!(guessers.len() >= 3);
+        assert_eq!(guessers[..3], ["a", "Bot 1", "Bot 2"]);
+        // the second word is new and Bot 2 gave its only association
+        assert!(state.past_rounds[1].success);
+    }
+}
@@ -0,0 +1,209 @@
+use super::bots::BotConfig;
+use super::connections::ConnectionConfig;
+use super::limits::Limits;
+use super::logging::LogFormat;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Server configuration, read from a TOML file.
+///
+/// Every field has a default, so an empty file (or no file at all) gives the
+/// same behaviour as the hard-coded values we used to have.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Address of the websocket listener, which also serves the metrics and
+    /// the admin API.
+    pub websocket_address: SocketAddr,
+    /// Address of the listener serving the frontend. It accepts websocket
+    /// connections as well, the frontend uses the address the page came from.
+    pub static_address: SocketAddr,
+    /// Directory containing the compiled frontend.
+    pub static_dir: PathBuf,
+    /// A word file or a directory of them, every file is a word pack.
+    pub words: PathBuf,
+    /// How often the word files are checked for changes, 0 to only reload on
+    /// SIGHUP or through the admin API.
+    pub reload_seconds: u64,
+    /// If present, both listeners are served over TLS.
+    pub tls: Option<TlsConfig>,
+    /// If present, the admin endpoints are enabled.
+    pub admin: Option<AdminConfig>,
+    /// Serve Prometheus metrics on `/metrics` of the websocket listener. They
+    /// aren't protected, so it's off unless the listener is kept private.
+    pub metrics: bool,
+    /// Format of the log output, the level is set with `RUST_LOG`.
+    pub log_format: LogFormat,
+    /// How long a running round may continue after SIGTERM.
+    pub shutdown_grace_seconds: u64,
+    /// Where the games of all rooms are saved on shutdown and restored from on startup.
+    pub snapshot: Option<PathBuf>,
+    /// Directory where the words already played in each room are kept, a
+    /// file per room, so they don't come up again after a restart.
+    pub history: Option<PathBuf>,
+    /// Seed of the word draw of every room, random per room if not set.
+    pub seed: Option<u64>,
+    /// Where the words flagged by players are collected, one JSON object per
+    /// line with the name of the room.
+    pub flags: Option<PathBuf>,
+    /// Room of the connections to `/websocket`, `/websocket/<room>` plays in
+    /// any other room.
+    pub room: String,
+    /// How many rooms may be open at once, connections which would open
+    /// another one are refused with 503.
+    pub max_rooms: usize,
+    pub limits: Limits,
+    pub connections: ConnectionConfig,
+    pub bots: BotConfig,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM encoded certificate chain.
+    pub cert: PathBuf,
+    /// PEM encoded private key (PKCS#8 or RSA).
+    pub key: PathBuf,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AdminConfig {
+    /// Expected in an `Authorization: Bearer <token>` header.
+    pub token: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            websocket_address: ([127, 0, 0, 1], 9001).into(),
+            static_address: ([127, 0, 0, 1], 8080).into(),
+            static_dir: PathBuf::from("static"),
+            words: PathBuf::from("../words.txt"),
+            reload_seconds: 5,
+            tls: None,
+            admin: None,
+            metrics: false,
+            log_format: LogFormat::default(),
+            shutdown_grace_seconds: 120,
+            snapshot: None,
+            history: None,
+            seed: None,
+            flags: None,
+            room: "default".to_string(),
+            max_rooms: 100,
+            limits: Limits::default(),
+            connections: ConnectionConfig::default(),
+            bots: BotConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    MissingFile(PathBuf),
+    EmptyAdminToken,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "couldn't read {:?}: {}", path, e),
+            ConfigError::Parse(path, e) => write!(f, "couldn't parse {:?}: {}", path, e),
+            ConfigError::MissingFile(path) => write!(f, "{:?} does not exist", path),
+            ConfigError::EmptyAdminToken => write!(f, "the admin token must not be empty"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn from_toml(path: &Path, content: &str) -> Result<Self, ConfigError> {
+        let config: Config =
+            toml::from_str(content).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+        config.check()?;
+        Ok(config)
+    }
+
+    /// Read the configuration from `path`, or use the defaults if there is no path.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        match path {
+            Some(path) => {
+                let content = std::fs::read_to_string(path)
+                    .map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+                Self::from_toml(path, &content)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Catch missing certificate files at startup instead of at the first handshake,
+    /// and refuse an empty admin token which would make the admin endpoints public.
+    fn check(&self) -> Result<(), ConfigError> {
+        if let Some(admin) = &self.admin {
+            if admin.token.trim().is_empty() {
+                return Err(ConfigError::EmptyAdminToken);
+            }
+        }
+        if let Some(tls) = &self.tls {
+            for path in [&tls.cert, &tls.key].iter() {
+                if !path.exists() {
+                    return Err(ConfigError::MissingFile(path.to_path_buf()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_file_gives_defaults() {
+        let config = Config::from_toml(Path::new("empty.toml"), "").unwrap();
+        assert_eq!(
+            config.websocket_address,
+            Config::default().websocket_address
+        );
+        assert!(config.tls.is_none());
+        assert!(!config.metrics);
+    }
+
+    #[test]
+    fn tls_requires_existing_files() {
+        let content = r#"
+            [tls]
+            cert = "/nonexistent/cert.pem"
+            key = "/nonexistent/key.pem"
+        "#;
+        match Config::from_toml(Path::new("tls.toml"), content) {
+            Err(ConfigError::MissingFile(path)) => {
+                assert_eq!(path, PathBuf::from("/nonexistent/cert.pem"))
+            }
+            other => panic!("expected missing file, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn admin_is_disabled_by_default() {
+        assert!(Config::default().admin.is_none());
+        assert!(matches!(
+            Config::from_toml(Path::new("admin.toml"), "[admin]\ntoken = \" \""),
+            Err(ConfigError::EmptyAdminToken)
+        ));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(matches!(
+            Config::from_toml(Path::new("typo.toml"), "websocket_adress = \"0.0.0.0:1\""),
+            Err(ConfigError::Parse(_, _))
+        ));
+    }
+}
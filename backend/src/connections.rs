@@ -0,0 +1,177 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use warp::http::StatusCode;
+
+/// Who may open a websocket connection.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectionConfig {
+    /// Values of the `Origin` header which may connect, e.g. `"https://example.org:8080"`.
+    /// Empty means the page has to come from the host the websocket is
+    /// requested from, which is the case for the static site served by us.
+    /// Requests without the header don't come from a browser and are always
+    /// allowed.
+    pub allowed_origins: Vec<String>,
+    pub max_per_ip: usize,
+    pub max_total: usize,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec![],
+            max_per_ip: 16,
+            max_total: 512,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    /// Whether a request with `origin` to `host`, the `Host` header, may connect.
+    pub fn origin_allowed(&self, origin: Option<&str>, host: Option<&str>) -> bool {
+        match origin {
+            Some(origin) if self.allowed_origins.is_empty() => {
+                let origin_host = origin.split_once("://").map(|(_, host)| host);
+                matches!((origin_host, host), (Some(a), Some(b)) if a.eq_ignore_ascii_case(b))
+            }
+            Some(origin) => self
+                .allowed_origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin)),
+            None => true,
+        }
+    }
+}
+
+/// Why a websocket upgrade was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refusal {
+    Origin,
+    TooManyFromAddress,
+    ServerFull,
+    /// The connection would open a new room, but there are `max_rooms` already.
+    TooManyRooms,
+}
+
+impl Refusal {
+    pub fn status(self) -> StatusCode {
+        match self {
+            Refusal::Origin => StatusCode::FORBIDDEN,
+            Refusal::TooManyFromAddress => StatusCode::TOO_MANY_REQUESTS,
+            Refusal::ServerFull | Refusal::TooManyRooms => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Refusal::Origin => "origin not allowed",
+            Refusal::TooManyFromAddress => "too many connections from this address",
+            Refusal::ServerFull => "server is full",
+            Refusal::TooManyRooms => "no more rooms can be opened",
+        }
+    }
+}
+
+impl warp::reject::Reject for Refusal {}
+
+#[derive(Debug, Default)]
+struct Counts {
+    total: usize,
+    per_ip: HashMap<IpAddr, usize>,
+}
+
+/// Counts open connections per address and in total.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionCounter {
+    counts: Arc<Mutex<Counts>>,
+}
+
+/// One open connection, released when dropped.
+#[derive(Debug)]
+pub struct Slot {
+    ip: Option<IpAddr>,
+    counts: Arc<Mutex<Counts>>,
+}
+
+impl ConnectionCounter {
+    pub fn acquire(&self, ip: Option<IpAddr>, config: &ConnectionConfig) -> Result<Slot, Refusal> {
+        let mut counts = self.counts.lock().unwrap();
+        if counts.total >= config.max_total {
+            return Err(Refusal::ServerFull);
+        }
+        if let Some(ip) = ip {
+            let from_ip = counts.per_ip.entry(ip).or_insert(0);
+            if *from_ip >= config.max_per_ip {
+                return Err(Refusal::TooManyFromAddress);
+            }
+            *from_ip += 1;
+        }
+        counts.total += 1;
+        Ok(Slot {
+            ip,
+            counts: self.counts.clone(),
+        })
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.total -= 1;
+        if let Some(ip) = self.ip {
+            if let Some(from_ip) = counts.per_ip.get_mut(&ip) {
+                *from_ip -= 1;
+                if *from_ip == 0 {
+                    counts.per_ip.remove(&ip);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_are_limited_and_released() {
+        let config = ConnectionConfig {
+            max_per_ip: 2,
+            max_total: 3,
+            ..ConnectionConfig::default()
+        };
+        let counter = ConnectionCounter::default();
+        let a: IpAddr = [10, 0, 0, 1].into();
+        let b: IpAddr = [10, 0, 0, 2].into();
+        let first = counter.acquire(Some(a), &config).unwrap();
+        let _second = counter.acquire(Some(a), &config).unwrap();
+        assert_eq!(
+            counter.acquire(Some(a), &config).unwrap_err(),
+            Refusal::TooManyFromAddress
+        );
+        let _third = counter.acquire(Some(b), &config).unwrap();
+        assert_eq!(
+            counter.acquire(Some(b), &config).unwrap_err(),
+            Refusal::ServerFull
+        );
+        drop(first);
+        assert!(counter.acquire(Some(a), &config).is_ok());
+    }
+
+    #[test]
+    fn origins_are_the_own_site_unless_configured() {
+        let mut config = ConnectionConfig::default();
+        let host = Some("game.example:8080");
+        assert!(config.origin_allowed(Some("https://game.example:8080"), host));
+        assert!(!config.origin_allowed(Some("https://evil.example"), host));
+        assert!(!config.origin_allowed(Some("https://game.example:8080"), None));
+        assert!(!config.origin_allowed(Some("null"), host));
+        config.allowed_origins = vec!["https://game.example".to_string()];
+        assert!(config.origin_allowed(Some("https://GAME.example"), host));
+        assert!(!config.origin_allowed(Some("https://evil.example"), host));
+        assert!(!config.origin_allowed(Some("https://game.example:8080"), host));
+        assert!(config.origin_allowed(None, None));
+    }
+}
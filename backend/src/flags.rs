@@ -0,0 +1,189 @@
+use super::rooms::{Room, Rooms};
+use common::game::{FlagReason, WordFlag};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// How often new flags are picked up from the game.
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A flag as it is kept in the flag file, one JSON object per line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StoredFlag {
+    /// Seconds since the epoch.
+    pub time: u64,
+    pub room: String,
+    #[serde(flatten)]
+    pub flag: WordFlag,
+}
+
+/// Everything known about one flagged word, for the curators.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct FlaggedWord {
+    pub word: String,
+    pub pack: Option<String>,
+    pub flags: usize,
+    pub reasons: BTreeMap<FlagReason, usize>,
+    pub rooms: BTreeSet<String>,
+    /// Seconds since the epoch of the latest flag.
+    pub last: u64,
+}
+
+/// The flags stored at `path`, none if there is no file yet. Lines which
+/// can't be parsed are skipped.
+pub fn load(path: &Path) -> std::io::Result<Vec<StoredFlag>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(number, line)| match serde_json::from_str(line) {
+            Ok(flag) => Some(flag),
+            Err(e) => {
+                warn!(?path, line = number + 1, error = %e, "skipping broken flag");
+                None
+            }
+        })
+        .collect())
+}
+
+pub fn append(flags: &[StoredFlag], path: &Path) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut lines = String::new();
+    for flag in flags {
+        lines += &serde_json::to_string(flag)?;
+        lines.push('\n');
+    }
+    file.write_all(lines.as_bytes())
+}
+
+/// Flagged words, the most flagged first.
+pub fn summarize(flags: &[StoredFlag]) -> Vec<FlaggedWord> {
+    let mut words: BTreeMap<(&str, Option<&str>), FlaggedWord> = BTreeMap::new();
+    for stored in flags {
+        let flag = &stored.flag;
+        let summary = words
+            .entry((&flag.word, flag.pack.as_deref()))
+            .or_insert_with(|| FlaggedWord {
+                word: flag.word.clone(),
+                pack: flag.pack.clone(),
+                flags: 0,
+                reasons: BTreeMap::new(),
+                rooms: BTreeSet::new(),
+                last: 0,
+            });
+        summary.flags += 1;
+        *summary.reasons.entry(flag.reason).or_insert(0) += 1;
+        summary.rooms.insert(stored.room.clone());
+        summary.last = summary.last.max(stored.time);
+    }
+    let mut words: Vec<FlaggedWord> = words.into_values().collect();
+    words.sort_by_key(|summary| std::cmp::Reverse(summary.flags));
+    words
+}
+
+/// Take the new flags out of the game of `room` and append them to `path`.
+/// Without a path they are only logged.
+pub async fn save_new(room: &Room, path: Option<&Path>) {
+    let flags = std::mem::take(&mut room.state.lock().await.flags);
+    if flags.is_empty() {
+        return;
+    }
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let stored: Vec<StoredFlag> = flags
+        .into_iter()
+        .map(|flag| StoredFlag {
+            time,
+            room: room.name.clone(),
+            flag,
+        })
+        .collect();
+    for flag in stored.iter() {
+        info!(room = %flag.room, pack = ?flag.flag.pack, reason = ?flag.flag.reason, "word flagged");
+    }
+    if let Some(path) = path {
+        if let Err(e) = append(&stored, path) {
+            warn!(?path, error = %e, "couldn't store flagged words");
+        }
+    }
+}
+
+/// Store the flags of every room as they come in, until the process ends.
+pub async fn keep_saved(rooms: Rooms) {
+    let mut interval = tokio::time::interval(SAVE_INTERVAL);
+    loop {
+        interval.tick().await;
+        for room in rooms.all().await {
+            save_new(&room, rooms.flag_file()).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::{Action, Library};
+
+    #[tokio::test]
+    async fn flags_are_stored_and_summarized() {
+        let path =
+            std::env::temp_dir().join(format!("beunique-flags-{}.jsonl", std::process::id()));
+        let room = Room::new("stammtisch", common::game::State::new(Library::default()));
+        let word = {
+            let mut state = room.state.lock().await;
+            state.action(&Action::Join {
+                id: 1,
+                name: "a".to_string(),
+            });
+            state.action(&Action::Join {
+                id: 2,
+                name: "b".to_string(),
+            });
+            state.action(&Action::Join {
+                id: 3,
+                name: "c".to_string(),
+            });
+            state.action(&Action::Start { id: 1 });
+            state.action(&Action::FlagWord {
+                id: 2,
+                reason: FlagReason::Unknown,
+            });
+            state.action(&Action::FlagWord {
+                id: 3,
+                reason: FlagReason::Unguessable,
+            });
+            state.phase.word().unwrap().to_string()
+        };
+        save_new(&room, Some(&path)).await;
+        assert!(room.state.lock().await.flags.is_empty());
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"not a flag\n")
+            .unwrap();
+        let flags = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(flags.len(), 2);
+        let summary = summarize(&flags);
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].word, word);
+        assert_eq!(summary[0].pack.as_deref(), Some("Standard"));
+        assert_eq!(summary[0].flags, 2);
+        assert_eq!(summary[0].reasons[&FlagReason::Unknown], 1);
+        assert!(summary[0].rooms.contains("stammtisch"));
+    }
+}
@@ -0,0 +1,88 @@
+use super::rooms::Rooms;
+use common::game::WordHistory;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often the history is written if it changed.
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The file in `directory` with the history of the room `room`.
+pub fn path(directory: &Path, room: &str) -> PathBuf {
+    directory.join(format!("{}.json", room))
+}
+
+/// The history stored at `path`, empty if there is none yet.
+pub fn load(path: &Path) -> std::io::Result<WordHistory> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => Ok(serde_json::from_str(&json)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(WordHistory::default()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn save(history: &WordHistory, path: &Path) -> std::io::Result<()> {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    let json = serde_json::to_string_pretty(history)?;
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, json)?;
+    std::fs::rename(&temporary, path)
+}
+
+/// Write the history of every room whenever it changes, until the process
+/// ends. Rooms without a history file are left alone.
+pub async fn keep_saved(rooms: Rooms) {
+    let mut written: BTreeMap<String, WordHistory> = BTreeMap::new();
+    let mut interval = tokio::time::interval(SAVE_INTERVAL);
+    loop {
+        interval.tick().await;
+        for room in rooms.all().await {
+            let path = match rooms.history_path(&room.name) {
+                Some(path) => path,
+                None => continue,
+            };
+            let history = room.state.lock().await.history.clone();
+            if written.get(&room.name) == Some(&history) {
+                continue;
+            }
+            match save(&history, &path) {
+                Ok(()) => {
+                    info!(?path, words = history.len(), "saved word history");
+                    written.insert(room.name, history);
+                }
+                Err(e) => warn!(?path, error = %e, "couldn't save word history"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::{Action, Library};
+
+    #[test]
+    fn history_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("beunique-history-{}.json", std::process::id()));
+        assert!(load(&path).unwrap().is_empty());
+        let mut state = common::game::State::new(Library::default());
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        state.action(&Action::Start { id: 1 });
+        save(&state.history, &path).unwrap();
+        let loaded = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, state.history);
+        assert_eq!(loaded.len(), 1);
+    }
+}
@@ -0,0 +1,482 @@
+//! Whole games over real websocket connections: the routes `main` serves run
+//! on an ephemeral port and every client checks the views it receives.
+
+use super::config::TlsConfig;
+use super::connections::{ConnectionConfig, ConnectionCounter};
+use super::filters;
+use super::limits::Limits;
+use super::rooms::{Room, Rooms};
+use super::shutdown::Shutdown;
+use common::game::{Action, GamePhase, Library, WordPack};
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use warp::{Filter, Rejection, Reply};
+
+/// The only word, so every round is about it.
+const WORD: &str = "Geheimnis";
+
+/// How long a client waits for the view it expects.
+const PATIENCE: Duration = Duration::from_secs(5);
+
+/// Room of the connections without a room name.
+const DEFAULT_ROOM: &str = "default";
+
+struct Server {
+    address: SocketAddr,
+    rooms: Rooms,
+    limits: Limits,
+    shutdown: Shutdown,
+}
+
+impl Server {
+    /// Serve until the test's runtime goes away.
+    fn start() -> Self {
+        Self::start_with(Limits::default())
+    }
+
+    fn start_with(limits: Limits) -> Self {
+        let server = Self {
+            limits,
+            ..Self::new(([127, 0, 0, 1], 0).into())
+        };
+        let (address, running) = warp::serve(server.routes()).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(running);
+        Self { address, ..server }
+    }
+
+    fn new(address: SocketAddr) -> Self {
+        let library = Library::new(vec![WordPack::new("test", "de", vec![WORD.into()])]);
+        Self {
+            address,
+            rooms: Rooms::new(library, None, None, None, 10),
+            limits: Limits::default(),
+            shutdown: Shutdown::default(),
+        }
+    }
+
+    fn routes(&self) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        filters::websocket(
+            ConnectionConfig::default(),
+            ConnectionCounter::default(),
+            self.rooms.clone(),
+            DEFAULT_ROOM.to_string(),
+            self.shutdown.clone(),
+            self.limits.clone(),
+        )
+    }
+
+    async fn room(&self, name: &str) -> Room {
+        self.rooms.get(name).await.expect("the room is open")
+    }
+
+    /// Connect to the default room and take a seat as `name`.
+    async fn join(&self, name: &str) -> Client {
+        self.join_room("", name).await
+    }
+
+    /// Connect to `room` and take a seat as `name`.
+    async fn join_room(&self, room: &str, name: &str) -> Client {
+        let room = percent_encoding::utf8_percent_encode(room, percent_encoding::NON_ALPHANUMERIC);
+        let url = format!("ws://{}/websocket/{}", self.address, room);
+        let (socket, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .expect("connecting");
+        Client::join(socket, name).await
+    }
+}
+
+struct Client {
+    id: usize,
+    name: String,
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    /// The last view received.
+    view: Value,
+}
+
+impl Client {
+    /// Take a seat as `name` with the id the first view offers.
+    async fn join(socket: WebSocketStream<MaybeTlsStream<TcpStream>>, name: &str) -> Client {
+        let mut client = Client {
+            id: 0,
+            name: name.to_string(),
+            socket,
+            view: Value::Null,
+        };
+        let view = client.until(|view| !view["actions"].is_null()).await;
+        client.id = view["actions"]
+            .as_array()
+            .and_then(|actions| {
+                actions
+                    .iter()
+                    .find(|a| a["type"] == "Join")
+                    .and_then(|a| a["id"].as_u64())
+            })
+            .expect("joining is offered") as usize;
+        client
+            .send(Action::Join {
+                id: client.id,
+                name: name.to_string(),
+            })
+            .await;
+        client.until(|view| view["me"]["name"] == name).await;
+        client
+    }
+
+    async fn send(&mut self, action: Action) {
+        let json = serde_json::to_string(&action).unwrap();
+        self.socket
+            .send(Message::text(json))
+            .await
+            .expect("sending");
+    }
+
+    /// The last view if it matches, otherwise read views until one does.
+    /// Other messages are skipped.
+    async fn until(&mut self, matches: impl Fn(&Value) -> bool) -> Value {
+        if matches(&self.view) {
+            return self.view.clone();
+        }
+        let name = self.name.clone();
+        let last = self.view.clone();
+        let wait = async {
+            while let Some(message) = self.socket.next().await {
+                let text = match message.expect("receiving") {
+                    Message::Text(text) => text,
+                    _ => continue,
+                };
+                let message: Value = serde_json::from_str(&text).expect("json");
+                if message["type"] == "View" {
+                    let view = &message["view"];
+                    self.view = view.clone();
+                    if matches(view) {
+                        return view.clone();
+                    }
+                }
+            }
+            panic!("{} was disconnected", name)
+        };
+        match tokio::time::timeout(PATIENCE, wait).await {
+            Ok(view) => view,
+            Err(_) => panic!("{} is still waiting, last view {}", name, last),
+        }
+    }
+
+    /// Read until a message of type `kind` arrives, views on the way are kept.
+    async fn until_message(&mut self, kind: &str) -> Value {
+        let name = self.name.clone();
+        let wait = async {
+            while let Some(message) = self.socket.next().await {
+                let text = match message.expect("receiving") {
+                    Message::Text(text) => text,
+                    _ => continue,
+                };
+                let message: Value = serde_json::from_str(&text).expect("json");
+                if message["type"] == kind {
+                    return message;
+                }
+                if message["type"] == "View" {
+                    self.view = message["view"].clone();
+                }
+            }
+            panic!("{} was disconnected", name)
+        };
+        match tokio::time::timeout(PATIENCE, wait).await {
+            Ok(message) => message,
+            Err(_) => panic!("{} is still waiting for {}", name, kind),
+        }
+    }
+
+    /// Read views until the game is in `phase`, `Active` or `Inactive` as given.
+    async fn until_phase(&mut self, phase: &str, role: &str) -> Value {
+        self.until(|view| !view["phase"][phase][role].is_null())
+            .await
+    }
+
+    /// Close the websocket properly, with a close frame.
+    async fn close(mut self) {
+        self.socket.close(None).await.expect("closing");
+    }
+}
+
+fn players(view: &Value) -> Vec<(String, Option<u64>)> {
+    view["players"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| (p["name"].as_str().unwrap().to_string(), p["id"].as_u64()))
+        .collect()
+}
+
+fn contains(view: &Value, text: &str) -> bool {
+    view.to_string().contains(&format!("\"{}\"", text))
+}
+
+#[tokio::test]
+async fn a_round_is_played_and_a_player_leaves() {
+    let server = Server::start();
+    let mut a = server.join("a").await;
+    let mut b = server.join("b").await;
+    let mut c = server.join("c").await;
+    for client in [&mut a, &mut b, &mut c].iter_mut() {
+        let view = client.until(|view| players(view).len() == 3).await;
+        assert_eq!(view["phase"], "GatherPlayers");
+        assert_eq!(view["me"]["id"].as_u64(), Some(client.id as u64));
+    }
+
+    a.send(Action::Start { id: a.id }).await;
+    let view = a.until_phase("HintCollection", "Active").await;
+    assert!(!contains(&view, WORD));
+    let view = b.until_phase("HintCollection", "Inactive").await;
+    assert_eq!(view["phase"]["HintCollection"]["Inactive"]["word"], WORD);
+    assert_eq!(
+        view["phase"]["HintCollection"]["Inactive"]["active_player"],
+        "a"
+    );
+
+    b.send(Action::GiveHint {
+        id: b.id,
+        hint: Some("Versteck".to_string()),
+    })
+    .await;
+    c.send(Action::GiveHint {
+        id: c.id,
+        hint: Some("Rätsel".to_string()),
+    })
+    .await;
+    let view = a.until_phase("HintFiltering", "Active").await;
+    assert!(!contains(&view, "Versteck") && !contains(&view, WORD));
+    let view = c.until_phase("HintFiltering", "Inactive").await;
+    let hints = &view["phase"]["HintFiltering"]["Inactive"]["hint_filtering"]["hints"];
+    assert_eq!(hints["b"]["content"], "Versteck");
+
+    c.send(Action::FilterHint {
+        id: c.id,
+        hint: "Versteck".to_string(),
+        allowed: false,
+    })
+    .await;
+    c.send(Action::FinishHintFiltering { id: c.id }).await;
+    let view = a.until_phase("Guessing", "Active").await;
+    let hints = &view["phase"]["Guessing"]["Active"]["hints"];
+    assert_eq!(hints["c"], "Rätsel");
+    assert!(hints.get("b").is_none());
+    assert!(!contains(&view, WORD));
+
+    a.send(Action::Guess {
+        id: a.id,
+        guess: Some(WORD.to_string()),
+    })
+    .await;
+    let view = b.until_phase("Judging", "Inactive").await;
+    assert_eq!(
+        view["phase"]["Judging"]["Inactive"]["judging"]["success"],
+        true
+    );
+    b.send(Action::FinishJudging { id: b.id }).await;
+    let view = b.until_phase("HintCollection", "Active").await;
+    assert_eq!(
+        view["past_rounds"],
+        serde_json::json!([{"name": "a", "word": WORD, "success": true}])
+    );
+
+    c.send(Action::Leave { id: c.id }).await;
+    for client in [&mut a, &mut b].iter_mut() {
+        let view = client.until(|view| players(view).len() == 2).await;
+        assert_eq!(
+            view["phase"]["HintCollection"]["Inactive"]["active_player"],
+            if client.name == "a" {
+                serde_json::json!("b")
+            } else {
+                Value::Null
+            }
+        );
+    }
+    let view = c.until(|view| view["me"].is_null()).await;
+    assert_eq!(view["actions"][0]["type"], "Join");
+}
+
+#[tokio::test]
+async fn players_come_back_after_losing_the_connection() {
+    let server = Server::start();
+    let mut a = server.join("a").await;
+    let b = server.join("b").await;
+    let c = server.join("c").await;
+    a.send(Action::Start { id: a.id }).await;
+    a.until_phase("HintCollection", "Active").await;
+
+    // a close frame
+    let b_id = b.id;
+    b.close().await;
+    let view = a
+        .until(|view| players(view).contains(&("b".to_string(), None)))
+        .await;
+    assert!(!view["phase"]["HintCollection"].is_null());
+
+    // the connection just goes away, `user_disconnected` cleans up
+    drop(c);
+    a.until(|view| players(view).contains(&("c".to_string(), None)))
+        .await;
+    assert_eq!(server.room(DEFAULT_ROOM).await.users.read().await.len(), 1);
+
+    let mut b = server.join("b").await;
+    assert_ne!(b.id, b_id);
+    let view = b.until_phase("HintCollection", "Inactive").await;
+    assert_eq!(view["phase"]["HintCollection"]["Inactive"]["word"], WORD);
+    let view = a
+        .until(|view| players(view).contains(&("b".to_string(), Some(b.id as u64))))
+        .await;
+    assert_eq!(players(&view).len(), 3);
+
+    // the round goes on where it was
+    b.send(Action::GiveHint {
+        id: b.id,
+        hint: Some("Versteck".to_string()),
+    })
+    .await;
+    let view = a
+        .until(|view| {
+            view["phase"]["HintCollection"]["Active"]["players_done"] == serde_json::json!(["b"])
+        })
+        .await;
+    assert!(!contains(&view, "Versteck"));
+    let room = server.room(DEFAULT_ROOM).await;
+    let state = room.state.lock().await;
+    assert_eq!(state.players.iter().filter(|p| p.id.is_some()).count(), 2);
+}
+
+#[tokio::test]
+async fn no_game_is_started_while_draining() {
+    let server = Server::start();
+    let mut a = server.join_room("erster", "a").await;
+    let _b = server.join_room("erster", "b").await;
+    a.send(Action::Start { id: a.id }).await;
+    a.until_phase("HintCollection", "Active").await;
+    let mut c = server.join_room("zweiter", "c").await;
+    let _d = server.join_room("zweiter", "d").await;
+
+    // the round in the first room keeps the server draining
+    let (shutdown, rooms) = (server.shutdown.clone(), server.rooms.clone());
+    tokio::spawn(async move { shutdown.drain(&rooms, Duration::from_secs(60), None).await });
+    c.until_message("ShuttingDown").await;
+    c.send(Action::Start { id: c.id }).await;
+    let rejected = c.until_message("Rejected").await;
+    assert_eq!(rejected["reason"], "ShuttingDown");
+    let room = server.room("zweiter").await;
+    assert!(matches!(
+        room.state.lock().await.phase,
+        GamePhase::GatherPlayers
+    ));
+}
+
+#[tokio::test]
+async fn connections_which_stop_answering_are_closed() {
+    let server = Server::start_with(Limits {
+        ping_seconds: 1,
+        ..Limits::default()
+    });
+    // nobody reads from a, so it doesn't answer the pings
+    let a = server.join("a").await;
+    let mut b = server.join("b").await;
+    b.until(|view| players(view).contains(&("a".to_string(), None)))
+        .await;
+    assert_eq!(server.room(DEFAULT_ROOM).await.users.read().await.len(), 1);
+    drop(a);
+}
+
+#[tokio::test]
+async fn every_room_plays_its_own_game() {
+    let server = Server::start();
+    let mut a = server.join_room("erster", "a").await;
+    let mut b = server.join_room("erster", "b").await;
+    let mut c = server.join_room("Küche", "c").await;
+    let mut d = server.join("d").await;
+    a.until(|view| players(view).len() == 2).await;
+    a.send(Action::Start { id: a.id }).await;
+    b.until_phase("HintCollection", "Inactive").await;
+
+    for client in [&mut c, &mut d].iter_mut() {
+        let view = client.until(|view| !view["me"].is_null()).await;
+        assert_eq!(
+            players(&view),
+            [(client.name.clone(), Some(client.id as u64))]
+        );
+        assert_eq!(view["phase"], "GatherPlayers");
+    }
+    let names: Vec<String> = server
+        .rooms
+        .all()
+        .await
+        .into_iter()
+        .map(|r| r.name)
+        .collect();
+    assert_eq!(names, ["Küche", "default", "erster"]);
+}
+
+#[tokio::test]
+async fn the_websocket_is_served_over_tls() {
+    let certificate = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let directory = std::env::temp_dir().join(format!("beunique-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let tls = TlsConfig {
+        cert: directory.join("cert.pem"),
+        key: directory.join("key.pem"),
+    };
+    let cert_pem = certificate.serialize_pem().unwrap();
+    std::fs::write(&tls.cert, &cert_pem).unwrap();
+    std::fs::write(&tls.key, certificate.serialize_private_key_pem()).unwrap();
+
+    // `serve` binds the address itself, so find a free port first
+    let port = std::net::TcpListener::bind(("127.0.0.1", 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let server = Server::new(([127, 0, 0, 1], port).into());
+    let (stop, stopped) = tokio::sync::watch::channel(false);
+    let running = tokio::spawn(super::serve(
+        server.routes(),
+        server.address,
+        Some(tls),
+        stopped,
+    ));
+
+    let connector = native_tls::TlsConnector::builder()
+        .add_root_certificate(native_tls::Certificate::from_pem(cert_pem.as_bytes()).unwrap())
+        .build()
+        .unwrap();
+    let stream = loop {
+        match TcpStream::connect(server.address).await {
+            Ok(stream) => break stream,
+            Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+        }
+    };
+    let url = format!("wss://localhost:{}/websocket", port);
+    let (socket, _) = tokio_tungstenite::client_async_tls_with_config(
+        url.as_str(),
+        stream,
+        None,
+        Some(connector),
+    )
+    .await
+    .expect("handshake");
+    assert!(matches!(socket.get_ref(), MaybeTlsStream::Tls(_)));
+    let client = Client::join(socket, "a").await;
+    let room = server.room(DEFAULT_ROOM).await;
+    assert_eq!(room.state.lock().await.players[0].id, Some(client.id));
+
+    // plain websocket connections are refused by the TLS listener
+    let plain = format!("ws://localhost:{}/websocket", port);
+    assert!(tokio_tungstenite::connect_async(plain.as_str())
+        .await
+        .is_err());
+
+    client.close().await;
+    stop.send(true).unwrap();
+    running.await.unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+}
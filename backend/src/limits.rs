@@ -0,0 +1,224 @@
+use common::game::{Action, RejectReason};
+use serde::Deserialize;
+use std::time::Instant;
+
+/// Limits on what a single connection may send.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// Sustained rate of frames per connection.
+    pub messages_per_second: f64,
+    /// Frames a connection may send in a burst before the rate applies.
+    pub message_burst: u32,
+    /// Larger frames are rejected without being parsed.
+    pub max_message_bytes: usize,
+    pub max_name_chars: usize,
+    /// Applies to hints, guesses and custom words.
+    pub max_hint_chars: usize,
+    /// The connection is closed after this many rejected frames.
+    pub max_rejections: u32,
+    /// A connection which sent nothing for this long is pinged, and closed
+    /// if it doesn't answer within as long again.
+    pub ping_seconds: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            messages_per_second: 5.0,
+            message_burst: 20,
+            max_message_bytes: 1024,
+            max_name_chars: 30,
+            max_hint_chars: 40,
+            max_rejections: 20,
+            ping_seconds: 15,
+        }
+    }
+}
+
+impl Limits {
+    pub fn ping_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.ping_seconds)
+    }
+
+    /// Frames above this size make the websocket library drop the connection
+    /// before we get to see them, smaller ones we reject ourselves.
+    pub fn hard_message_bytes(&self) -> usize {
+        self.max_message_bytes.saturating_mul(4)
+    }
+
+    pub fn check_action(&self, action: &Action) -> Result<(), RejectReason> {
+        let too_long = |text: &str, max: usize| text.chars().count() > max;
+        let ok = match action {
+            Action::Join { name, .. } => !too_long(name, self.max_name_chars),
+            Action::GiveHint {
+                hint: Some(text), ..
+            }
+            | Action::Guess {
+                guess: Some(text), ..
+            } => !too_long(text, self.max_hint_chars),
+            Action::FilterHint { hint, .. } => !too_long(hint, self.max_hint_chars),
+            Action::SubmitWords { words, .. } => words
+                .iter()
+                .all(|word| !too_long(word, self.max_hint_chars)),
+            _ => true,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(RejectReason::TooLong)
+        }
+    }
+}
+
+/// Classic token bucket: holds up to `capacity` tokens, refills at `rate` per second
+/// and every frame takes one.
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, rate: f64, now: Instant) -> Self {
+        Self {
+            capacity: capacity as f64,
+            rate,
+            tokens: capacity as f64,
+            last: now,
+        }
+    }
+
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Per connection bookkeeping of rate and rejections.
+#[derive(Debug)]
+pub struct Limiter {
+    limits: Limits,
+    bucket: TokenBucket,
+    rejections: u32,
+}
+
+impl Limiter {
+    pub fn new(limits: Limits) -> Self {
+        let bucket = TokenBucket::new(
+            limits.message_burst,
+            limits.messages_per_second,
+            Instant::now(),
+        );
+        Self {
+            limits,
+            bucket,
+            rejections: 0,
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Checks rate and size of an incoming frame.
+    pub fn admit(&mut self, bytes: usize, now: Instant) -> Result<(), RejectReason> {
+        if !self.bucket.try_take(now) {
+            Err(RejectReason::RateLimited)
+        } else if bytes > self.limits.max_message_bytes {
+            Err(RejectReason::TooLarge)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Count a rejected frame, true if the connection has used up its allowance.
+    pub fn reject(&mut self) -> bool {
+        self.rejections += 1;
+        self.rejections >= self.limits.max_rejections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn bucket_allows_burst_then_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(3, 2.0, start);
+        assert!((0..3).all(|_| bucket.try_take(start)));
+        assert!(!bucket.try_take(start));
+        assert!(bucket.try_take(start + Duration::from_millis(500)));
+        assert!(!bucket.try_take(start + Duration::from_millis(600)));
+        // refilling never goes beyond the capacity
+        let later = start + Duration::from_secs(60);
+        assert!((0..3).all(|_| bucket.try_take(later)));
+        assert!(!bucket.try_take(later));
+    }
+
+    #[test]
+    fn limiter_rejects_large_frames_and_counts_offences() {
+        let mut limiter = Limiter::new(Limits {
+            max_message_bytes: 10,
+            max_rejections: 2,
+            ..Limits::default()
+        });
+        let now = Instant::now();
+        assert_eq!(limiter.admit(10, now), Ok(()));
+        assert_eq!(limiter.admit(11, now), Err(RejectReason::TooLarge));
+        assert!(!limiter.reject());
+        assert!(limiter.reject());
+    }
+
+    #[test]
+    fn names_and_hints_are_limited_in_characters() {
+        let limits = Limits {
+            max_name_chars: 3,
+            max_hint_chars: 4,
+            ..Limits::default()
+        };
+        assert_eq!(
+            limits.check_action(&Action::Join {
+                id: 1,
+                name: "äöü".to_string()
+            }),
+            Ok(())
+        );
+        assert_eq!(
+            limits.check_action(&Action::Join {
+                id: 1,
+                name: "abcd".to_string()
+            }),
+            Err(RejectReason::TooLong)
+        );
+        assert_eq!(
+            limits.check_action(&Action::GiveHint {
+                id: 1,
+                hint: Some("abcde".to_string())
+            }),
+            Err(RejectReason::TooLong)
+        );
+        assert_eq!(
+            limits.check_action(&Action::Guess { id: 1, guess: None }),
+            Ok(())
+        );
+        assert_eq!(
+            limits.check_action(&Action::SubmitWords {
+                id: 1,
+                words: vec!["a".into(), "abcde".into()]
+            }),
+            Err(RejectReason::TooLong)
+        );
+    }
+}
@@ -0,0 +1,24 @@
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines with the span context.
+    #[default]
+    Pretty,
+    /// One JSON object per event, for log collectors.
+    Json,
+}
+
+/// Install the global subscriber. The level is taken from `RUST_LOG` and defaults to `info`.
+///
+/// Words, hints and guesses are only ever logged at `debug` or below.
+pub fn init(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        LogFormat::Pretty => builder.init(),
+        LogFormat::Json => builder.json().with_current_span(true).init(),
+    }
+}
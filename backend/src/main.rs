@@ -1,111 +1,224 @@
 use futures::join;
-use rand::thread_rng;
-use std::io::BufRead;
-use std::io::BufReader;
-use std::path::Path;
+use std::net::SocketAddr;
 use std::sync::atomic::AtomicUsize;
+use tracing::{info, warn};
 use warp::Filter;
 
 extern crate common;
 
+mod admin;
+mod bots;
+mod config;
+mod connections;
+mod flags;
+mod history;
+#[cfg(test)]
+mod integration;
+mod limits;
+mod logging;
+mod metrics;
+mod packs;
+mod rooms;
+mod shutdown;
+
 /// Our global unique user id counter.
 static NEXT_USER_ID: AtomicUsize = AtomicUsize::new(1);
 
-fn read_words(filename: &Path) -> Result<Vec<String>, std::io::Error> {
-    std::fs::File::open(filename).and_then(|f| {
-        Ok(BufReader::new(f)
-            .lines()
-            .filter_map(|l| match l {
-                Ok(line) => {
-                    if line.find(|c: char| !c.is_whitespace()).is_some() {
-                        Some(line)
-                    } else {
-                        None
-                    }
-                }
-                Err(_) => None,
-            })
-            .collect())
-    })
+/// How long a room stays open without connections.
+const EMPTY_ROOM_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Serve `filter` on `address` until `stop` fires, over TLS if it is configured.
+async fn serve<F>(
+    filter: F,
+    address: SocketAddr,
+    tls: Option<config::TlsConfig>,
+    mut stop: tokio::sync::watch::Receiver<bool>,
+) where
+    F: Filter<Error = warp::Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: warp::Reply,
+{
+    let signal = async move {
+        let _ = stop.changed().await;
+    };
+    match tls {
+        Some(tls) => {
+            let (_, server) = warp::serve(filter)
+                .tls()
+                .cert_path(tls.cert)
+                .key_path(tls.key)
+                .bind_with_graceful_shutdown(address, signal);
+            server.await
+        }
+        None => {
+            let (_, server) = warp::serve(filter).bind_with_graceful_shutdown(address, signal);
+            server.await
+        }
+    }
 }
+
 #[tokio::main]
 async fn main() {
-    pretty_env_logger::init();
-    let users = models::Users::default();
-
-    use common::game::Dictionary;
-    use rand::seq::SliceRandom;
-    let filename = Path::new("../words.txt");
-    let dictionary = match read_words(filename) {
-        Ok(mut words) => {
-            if words.len() > 0 {
-                let mut rng = thread_rng();
-                words.shuffle(&mut rng);
-                Dictionary::new(words)
-            } else {
-                eprintln!(
-                    "read 0 words from {:?}, using default dictionary",
-                    filename.canonicalize()
-                );
-                Dictionary::default()
-            }
+    let config_path = std::env::args().nth(1).map(std::path::PathBuf::from);
+    let config = match config::Config::load(config_path.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("invalid configuration: {}", e);
+            std::process::exit(1);
         }
+    };
+    logging::init(config.log_format);
+
+    let mut word_packs = packs::Packs::new(&config.words);
+    let library = match word_packs.load() {
+        Ok(_) => word_packs.library(),
         Err(e) => {
-            eprintln!(
-                "couldn't read words from {:?} because of {:?}, using default dictionary",
-                filename, e
+            warn!(
+                path = ?config.words,
+                error = %e,
+                "couldn't read words, using default dictionary"
             );
-            Dictionary::default()
+            common::game::Library::default()
         }
     };
 
-    let state = models::State::new(tokio::sync::Mutex::new(common::game::State::new(
-        dictionary,
-    )));
-
-    let routes = warp::path("websocket")
-        // The `ws()` filter will prepare the Websocket handshake.
-        .and(warp::ws())
-        .and(filters::with_users(users))
-        .and(filters::with_state(state.clone()))
-        .map(|ws: warp::ws::Ws, users, state| {
-            // And then our closure will be called when it completes...
-            ws.on_upgrade(move |websocket| {
-                handlers::user_connected(websocket, users, state)
-                //// Just echo all messages back...
-                //let (tx, rx) = websocket.split();
-                //rx.forward(tx).map(|result| {
-                //    if let Err(e) = result {
-                //        eprintln!("websocket error: {:?}", e);
-                //    }
-                //})
-            })
-        })
-        .or(warp::path("debug")
-            .and(filters::with_state(state.clone()))
-            .map(|state: models::State| format!("{:#?}", state)));
+    let rooms = rooms::Rooms::new(
+        library,
+        config.seed,
+        config.history.clone(),
+        config.flags.clone(),
+        config.max_rooms,
+    );
+    if let Some(path) = config.snapshot.as_deref().filter(|path| path.exists()) {
+        match shutdown::read_snapshot(path) {
+            Ok(games) => {
+                info!(?path, rooms = games.len(), "restored snapshot");
+                rooms.restore(games).await;
+                if let Err(e) = std::fs::remove_file(path) {
+                    warn!(?path, error = %e, "couldn't remove snapshot");
+                }
+            }
+            Err(e) => warn!(?path, error = %e, "couldn't read snapshot, starting without rooms"),
+        }
+    }
+    let word_packs: packs::SharedPacks = std::sync::Arc::new(std::sync::Mutex::new(word_packs));
+    let shutdown = shutdown::Shutdown::default();
+    let connection_counter = connections::ConnectionCounter::default();
+
+    let websocket = filters::websocket(
+        config.connections.clone(),
+        connection_counter,
+        rooms.clone(),
+        config.room.clone(),
+        shutdown.clone(),
+        config.limits.clone(),
+    );
+    let routes = websocket
+        .clone()
+        .or(warp::path!("metrics")
+            .and(warp::get())
+            .and(filters::enabled(config.metrics))
+            .and(filters::with_rooms(rooms.clone()))
+            .and_then(metrics::render))
+        .or(admin::routes(
+            config.admin.as_ref().map(|admin| admin.token.clone()),
+            rooms.clone(),
+            shutdown.clone(),
+            word_packs.clone(),
+        ))
+        .recover(filters::handle_rejection);
 
-    let fut_ws = warp::serve(routes).bind(([127, 0, 0, 1], 9001));
+    tokio::spawn(packs::watch(
+        word_packs,
+        rooms.clone(),
+        std::time::Duration::from_secs(config.reload_seconds),
+    ));
+    tokio::spawn(rooms::sweep(rooms.clone(), EMPTY_ROOM_TIMEOUT));
+    tokio::spawn(history::keep_saved(rooms.clone()));
+    let associations = bots::load_or_warn(&config.bots.associations);
+    info!(words = associations.len(), "read bot associations");
+    tokio::spawn(bots::run(
+        rooms.clone(),
+        std::sync::Arc::new(associations),
+        std::time::Duration::from_secs(config.bots.think_seconds.max(1)),
+    ));
+    tokio::spawn(flags::keep_saved(rooms.clone()));
 
-    let static_route = warp::fs::dir("static");
-    let fut_static = warp::serve(static_route).bind(([127, 0, 0, 1], 8080));
+    let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
+    let fut_ws = serve(
+        routes,
+        config.websocket_address,
+        config.tls.clone(),
+        stop_rx.clone(),
+    );
 
-    join!(fut_ws, fut_static);
+    // the frontend connects to the origin it was loaded from
+    let static_route = websocket
+        .or(warp::fs::dir(config.static_dir.clone()))
+        .recover(filters::handle_rejection);
+    let fut_static = serve(
+        static_route,
+        config.static_address,
+        config.tls.clone(),
+        stop_rx,
+    );
+
+    let fut_shutdown = async move {
+        shutdown.requested().await;
+        shutdown
+            .drain(
+                &rooms,
+                std::time::Duration::from_secs(config.shutdown_grace_seconds),
+                config.snapshot.as_deref(),
+            )
+            .await;
+        let _ = stop_tx.send(true);
+    };
+
+    join!(fut_ws, fut_static, fut_shutdown);
+    info!("shut down");
 }
 
 mod handlers {
-    use super::models::{State, Users};
+    use super::limits::{Limiter, Limits};
+    use super::metrics::Progress;
+    use super::models::{ActionLog, LoggedAction, State, Users, ACTION_LOG_LENGTH};
+    use super::rooms::{Room, Rooms};
+    use super::shutdown::Shutdown;
+    use common::game::{Action, Notice, RejectReason, ServerMessage};
     use futures::{FutureExt, StreamExt};
     use std::sync::atomic::Ordering;
     use tokio::sync::mpsc;
+    use tracing::{debug, info, info_span, trace, warn, Instrument};
     use warp::ws::Message;
     use warp::ws::WebSocket;
 
-    pub async fn user_connected(websocket: WebSocket, users: Users, state: State) {
+    pub async fn user_connected(
+        websocket: WebSocket,
+        rooms: Rooms,
+        room: String,
+        shutdown: Shutdown,
+        limits: Limits,
+    ) {
         // Use a counter to assign a new unique ID for this user.
         let my_id = super::NEXT_USER_ID.fetch_add(1, Ordering::Relaxed);
 
-        eprintln!("new connected user: {}", my_id);
+        // Everything logged for this connection carries the user id and the room.
+        let span = info_span!("connection", user_id = my_id, room = %room);
+        connection(my_id, websocket, rooms, room, shutdown, limits)
+            .instrument(span)
+            .await
+    }
+
+    async fn connection(
+        my_id: usize,
+        websocket: WebSocket,
+        rooms: Rooms,
+        room: String,
+        shutdown: Shutdown,
+        limits: Limits,
+    ) {
+        info!("new connected user");
+        super::metrics::CONNECTED_WEBSOCKETS.inc();
 
         let (user_ws_tx, mut user_ws_rx) = websocket.split();
 
@@ -117,13 +230,31 @@ mod handlers {
                 .forward(user_ws_tx)
                 .map(|result| {
                     if let Err(e) = result {
-                        eprintln!("websocket send error: {}", e);
+                        warn!(error = %e, "websocket send error");
                     }
-                }),
+                })
+                .in_current_span(),
         );
 
-        // Save the sender in our list of connected users.
-        users.write().await.insert(my_id, tx);
+        // Save the sender in the list of users connected to the room.
+        let my_tx = tx.clone();
+        let Room {
+            users, state, log, ..
+        } = match rooms.enter(&room, my_id, tx).await {
+            Ok(room) => room,
+            Err(refusal) => {
+                // the room filled up after the upgrade was accepted
+                super::metrics::record_refused_connection(refusal);
+                let _ = my_tx.send(Ok(Message::close_with(
+                    CLOSE_TRY_AGAIN_LATER,
+                    refusal.message(),
+                )));
+                super::metrics::CONNECTED_WEBSOCKETS.dec();
+                return;
+            }
+        };
+        let ping_interval = limits.ping_interval();
+        let mut limiter = Limiter::new(limits);
 
         notify_all(&users, &state).await;
 
@@ -133,99 +264,624 @@ mod handlers {
         // Make an extra clone to give to our disconnection handler...
         let users2 = users.clone();
 
-        // Every time the user sends a message, process it
-        while let Some(result) = user_ws_rx.next().await {
+        // Every time the user sends a message, process it. A connection which
+        // went away without a close frame is noticed by the missing pong.
+        let mut pinged = false;
+        loop {
+            let result = match tokio::time::timeout(ping_interval, user_ws_rx.next()).await {
+                Ok(Some(result)) => result,
+                Ok(None) => break,
+                Err(_elapsed) if pinged => {
+                    info!("no answer to the ping, disconnecting");
+                    break;
+                }
+                Err(_elapsed) => {
+                    pinged = true;
+                    let _ = my_tx.send(Ok(Message::ping(Vec::new())));
+                    continue;
+                }
+            };
+            pinged = false;
             let msg = match result {
                 Ok(msg) => msg,
                 Err(e) => {
-                    eprintln!("websocket error(uid={}): {}", my_id, e);
+                    warn!(error = %e, "websocket error");
                     break;
                 }
             };
-            user_message(my_id, msg, &users, &state).await;
+            let result =
+                user_message(my_id, msg, &users, &state, &log, &shutdown, &mut limiter).await;
+            if let Err(reason) = result {
+                debug!(?reason, "rejected frame");
+                super::metrics::record_rejected_frame(reason);
+                send(&my_tx, &ServerMessage::Rejected { reason });
+                if limiter.reject() {
+                    warn!("too many rejected frames, closing connection");
+                    let _ = my_tx.send(Ok(Message::close_with(
+                        CLOSE_POLICY_VIOLATION,
+                        "too many rejected messages",
+                    )));
+                    break;
+                }
+            }
         }
 
         // user_ws_rx stream will keep processing as long as the user stays
         // connected. Once they disconnect, then...
-        user_disconnected(my_id, &users2, &state).await;
+        user_disconnected(my_id, &users2, &state, &log).await;
     }
 
-    async fn notify_all(users: &Users, state: &State) {
+    fn send(tx: &super::models::UserSender, message: &ServerMessage) {
+        let json = serde_json::to_string(message).unwrap();
+        let kind = match message {
+            ServerMessage::View { .. } => "View",
+            ServerMessage::Notice { .. } => "Notice",
+            ServerMessage::ShuttingDown { .. } => "ShuttingDown",
+            ServerMessage::Rejected { .. } => "Rejected",
+        };
+        super::metrics::record_message_size(kind, json.len());
+        if let Err(_disconnected) = tx.send(Ok(Message::text(json))) {
+            // The tx is disconnected, our `user_disconnected` code
+            // should be happening in another task, nothing more to
+            // do here.
+        }
+    }
+
+    pub async fn notify_all(users: &Users, state: &State) {
+        let _timer = super::metrics::notify_all_timer();
         for (id, tx) in users.write().await.iter() {
             let view = state.lock().await.get_view(*id);
-            let view_json = serde_json::to_string(&view).unwrap();
-            if let Err(_disconnected) = tx.send(Ok(Message::text(view_json))) {
-                // The tx is disconnected, our `user_disconnected` code
-                // should be happening in another task, nothing more to
-                // do here.
-            }
+            send(
+                tx,
+                &ServerMessage::View {
+                    view: Box::new(view),
+                },
+            );
         }
     }
 
-    pub async fn user_message(my_id: usize, msg: Message, users: &Users, state: &State) {
-        eprintln!("user {} has sent message {:?}", my_id, msg);
+    pub async fn broadcast(users: &Users, message: &ServerMessage) {
+        for tx in users.read().await.values() {
+            send(tx, message);
+        }
+    }
 
-        match msg.to_str() {
-            Ok(msg_text) => match serde_json::from_str::<common::game::Action>(msg_text) {
-                Ok(action) => {
-                    state.lock().await.action(&action);
-                }
-                Err(e) => {
-                    eprintln!("error in deserializing: {:?}", e)
-                }
-            },
-            Err(e) => {
-                eprintln!("error in deserializing: {:?}", e)
-            }
+    pub async fn broadcast_notice(users: &Users, notice: Notice) {
+        broadcast(users, &ServerMessage::Notice { notice }).await
+    }
+
+    pub async fn notice(users: &Users, id: usize, notice: Notice) {
+        if let Some(tx) = users.read().await.get(&id) {
+            send(tx, &ServerMessage::Notice { notice });
         }
+    }
+
+    /// Ask every client to close its connection, `user_disconnected` takes care of the rest.
+    pub async fn close_all(users: &Users, code: u16, reason: &'static str) {
+        for tx in users.read().await.values() {
+            let _ = tx.send(Ok(Message::close_with(code, reason)));
+        }
+    }
+
+    /// Apply an action to the game and record it in the log.
+    pub async fn apply(action: Action, state: &State, log: &ActionLog) -> Option<()> {
+        let result = {
+            let mut state = state.lock().await;
+            let progress = Progress::of(&state);
+            let result = state.action(&action);
+            progress.record(&state);
+            info!(
+                action = action.name(),
+                accepted = result.is_some(),
+                phase = state.phase.name(),
+                "applied action"
+            );
+            result
+        };
+        super::metrics::record_action(&action, result.is_some());
+        let mut log = log.lock().await;
+        if log.len() == ACTION_LOG_LENGTH {
+            log.pop_front();
+        }
+        log.push_back(LoggedAction::new(action, result.is_some()));
+        result
+    }
+
+    /// Close code for connections to a room which can't be opened right now.
+    const CLOSE_TRY_AGAIN_LATER: u16 = 1013;
+
+    /// Close code for connections which keep sending frames we reject, see RFC 6455.
+    const CLOSE_POLICY_VIOLATION: u16 = 1008;
+
+    /// Process one frame. Everybody is only notified if the game changed, rejected
+    /// frames are reported to the sender alone.
+    pub async fn user_message(
+        my_id: usize,
+        msg: Message,
+        users: &Users,
+        state: &State,
+        log: &ActionLog,
+        shutdown: &Shutdown,
+        limiter: &mut Limiter,
+    ) -> Result<(), RejectReason> {
+        // the message may contain hints or guesses, so keep it out of the regular log
+        trace!(?msg, "received message");
+
         if msg.is_close() {
-            eprintln!("marking id {} as disconnected", my_id);
-            let disconnect = common::game::Action::DisconnectPlayer(my_id);
-            state.lock().await.action(&disconnect);
+            info!("marking as disconnected");
+            let disconnect = common::game::Action::DisconnectPlayer { id: my_id };
+            apply(disconnect, state, log).await;
+            notify_all(users, state).await;
+            return Ok(());
+        }
+        if msg.is_ping() || msg.is_pong() {
+            return Ok(());
         }
 
-        notify_all(users, state).await
+        limiter.admit(msg.as_bytes().len(), std::time::Instant::now())?;
+        let action = msg
+            .to_str()
+            .ok()
+            .and_then(|text| serde_json::from_str::<common::game::Action>(text).ok())
+            .ok_or(RejectReason::Malformed)?
+            // whatever id the client sent, it can only act for itself and not
+            // for other players or bots
+            .with_id(my_id);
+        limiter.limits().check_action(&action)?;
+
+        if shutdown.is_draining() && matches!(action, Action::Start { .. }) {
+            info!("not starting a game while draining");
+            super::metrics::record_action(&action, false);
+            return Err(RejectReason::ShuttingDown);
+        }
+        if apply(action, state, log).await.is_some() {
+            notify_all(users, state).await;
+        }
+        Ok(())
     }
 
-    async fn user_disconnected(my_id: usize, users: &Users, state: &State) {
-        eprintln!("good bye user: {}", my_id);
+    async fn user_disconnected(my_id: usize, users: &Users, state: &State, log: &ActionLog) {
+        info!("good bye user");
 
         // Stream closed up, so remove from the user list
-        eprintln!("marking id {} as disconnected", my_id);
-        let disconnect = common::game::Action::DisconnectPlayer(my_id);
-        state.lock().await.action(&disconnect);
+        let disconnect = common::game::Action::DisconnectPlayer { id: my_id };
+        let changed = apply(disconnect, state, log).await.is_some();
         users.write().await.remove(&my_id);
+        super::metrics::CONNECTED_WEBSOCKETS.dec();
+        // without a close frame nobody has been told yet
+        if changed {
+            notify_all(users, state).await;
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use common::game::{Difficulty, Library, WordPack};
+
+        async fn send(room: &Room, limiter: &mut Limiter, id: usize, action: Action) {
+            let msg = Message::text(serde_json::to_string(&action).unwrap());
+            let shutdown = Shutdown::default();
+            let result = user_message(
+                id,
+                msg,
+                &room.users,
+                &room.state,
+                &room.log,
+                &shutdown,
+                limiter,
+            );
+            assert_eq!(result.await, Ok(()));
+        }
+
+        #[tokio::test]
+        async fn actions_are_taken_for_the_sending_connection() {
+            let room = Room::new(
+                "test",
+                common::game::State::new(Library::new(vec![WordPack::new(
+                    "test",
+                    "de",
+                    vec!["Geheimnis".into()],
+                )])),
+            );
+            let mut limiter = Limiter::new(Limits::default());
+            send(
+                &room,
+                &mut limiter,
+                1,
+                Action::Join {
+                    id: 1,
+                    name: "a".to_string(),
+                },
+            )
+            .await;
+            send(
+                &room,
+                &mut limiter,
+                1,
+                Action::AddBot {
+                    id: 1,
+                    difficulty: Difficulty::Easy,
+                },
+            )
+            .await;
+            send(
+                &room,
+                &mut limiter,
+                2,
+                Action::Join {
+                    id: 2,
+                    name: "b".to_string(),
+                },
+            )
+            .await;
+            let bot = room.state.lock().await.players[1].id.unwrap();
+
+            // "b" tries to make the bot and "a" leave, but leaves itself
+            send(&room, &mut limiter, 2, Action::Leave { id: bot }).await;
+            send(&room, &mut limiter, 2, Action::Leave { id: 1 }).await;
+            let state = room.state.lock().await;
+            let names: Vec<&str> = state.players.iter().map(|p| p.name.as_str()).collect();
+            assert_eq!(names, ["a", "Bot 1"]);
+        }
     }
 }
 mod models {
-    use std::collections::HashMap;
+    use common::game::Action;
+    use serde::Serialize;
+    use std::collections::{HashMap, VecDeque};
     use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
     use tokio::sync::{mpsc, Mutex, RwLock};
     use warp::ws::Message;
 
     pub type State = Arc<Mutex<common::game::State>>;
 
+    pub type UserSender = mpsc::UnboundedSender<Result<Message, warp::Error>>;
+
     /// Our state of currently connected users.
     ///
     /// - Key is their id
     /// - Value is a sender of `warp::ws::Message`
-    pub type Users =
-        Arc<RwLock<HashMap<usize, mpsc::UnboundedSender<Result<Message, warp::Error>>>>>;
+    pub type Users = Arc<RwLock<HashMap<usize, UserSender>>>;
+
+    #[derive(Serialize, Debug)]
+    pub struct LoggedAction {
+        /// Seconds since the epoch.
+        pub time: u64,
+        pub action: Action,
+        /// Whether the game accepted the action.
+        pub accepted: bool,
+    }
+
+    impl LoggedAction {
+        pub fn new(action: Action, accepted: bool) -> Self {
+            Self {
+                time: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0),
+                action,
+                accepted,
+            }
+        }
+    }
+
+    /// How many actions the log keeps.
+    pub const ACTION_LOG_LENGTH: usize = 1000;
+
+    /// The most recent actions applied to the game, oldest first.
+    pub type ActionLog = Arc<Mutex<VecDeque<LoggedAction>>>;
 }
 
 mod filters {
-    use super::models::{State, Users};
-    use warp::Filter;
+    use super::connections::{ConnectionConfig, ConnectionCounter, Refusal, Slot};
+    use super::limits::Limits;
+    use super::packs::SharedPacks;
+    use super::rooms::{self, Room, Rooms};
+    use super::shutdown::Shutdown;
+    use tracing::info;
+    use warp::http::StatusCode;
+    use warp::{Filter, Rejection, Reply};
+
+    pub fn with_rooms(
+        rooms: Rooms,
+    ) -> impl Filter<Extract = (Rooms,), Error = std::convert::Infallible> + Clone {
+        warp::any().map(move || rooms.clone())
+    }
+
+    pub fn with_limits(
+        limits: Limits,
+    ) -> impl Filter<Extract = (Limits,), Error = std::convert::Infallible> + Clone {
+        warp::any().map(move || limits.clone())
+    }
+
+    pub fn with_shutdown(
+        shutdown: Shutdown,
+    ) -> impl Filter<Extract = (Shutdown,), Error = std::convert::Infallible> + Clone {
+        warp::any().map(move || shutdown.clone())
+    }
+
+    pub fn with_packs(
+        packs: SharedPacks,
+    ) -> impl Filter<Extract = (SharedPacks,), Error = std::convert::Infallible> + Clone {
+        warp::any().map(move || packs.clone())
+    }
+
+    /// A path segment, percent-decoded, so names with spaces or umlauts come
+    /// through as they are. Segments which aren't UTF-8 are not found.
+    pub fn name() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+        warp::path::param::<String>().and_then(|segment: String| async move {
+            percent_encoding::percent_decode_str(&segment)
+                .decode_utf8()
+                .map(|name| name.into_owned())
+                .map_err(|_| warp::reject::not_found())
+        })
+    }
+
+    /// A valid room name from the path.
+    fn room_name() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+        name().and_then(|name: String| async move {
+            if rooms::is_valid_name(&name) {
+                Ok(name)
+            } else {
+                Err(warp::reject::not_found())
+            }
+        })
+    }
+
+    /// The open room named by the path, not found if there is none.
+    pub fn open_room(rooms: Rooms) -> impl Filter<Extract = (Room,), Error = Rejection> + Clone {
+        room_name()
+            .and(with_rooms(rooms))
+            .and_then(|name: String, rooms: Rooms| async move {
+                rooms.get(&name).await.ok_or_else(warp::reject::not_found)
+            })
+    }
+
+    #[derive(Debug)]
+    struct Draining;
+
+    impl warp::reject::Reject for Draining {}
 
-    pub fn with_state(
-        state: State,
-    ) -> impl Filter<Extract = (State,), Error = std::convert::Infallible> + Clone {
-        warp::any().map(move || state.clone())
+    /// The room to play in, named by the rest of the path or `default` if the
+    /// path ends. While the server drains no new rooms are opened, the open
+    /// ones can still be entered to finish the round.
+    pub fn room(
+        default: String,
+        rooms: Rooms,
+        shutdown: Shutdown,
+    ) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+        warp::path::end()
+            .map(move || default.clone())
+            .or(room_name().and(warp::path::end()))
+            .unify()
+            .and(with_rooms(rooms))
+            .and(with_shutdown(shutdown))
+            .and_then(
+                |name: String, rooms: Rooms, shutdown: Shutdown| async move {
+                    if shutdown.is_draining() && !rooms.contains(&name).await {
+                        Err(warp::reject::custom(Draining))
+                    } else if !rooms.admits(&name).await {
+                        info!(room = %name, "refused connection to a new room");
+                        super::metrics::record_refused_connection(Refusal::TooManyRooms);
+                        Err(warp::reject::custom(Refusal::TooManyRooms))
+                    } else {
+                        Ok(name)
+                    }
+                },
+            )
+    }
+
+    /// The game itself: `/websocket` and `/websocket/<room>` upgrade to a
+    /// websocket and play in the room over it.
+    pub fn websocket(
+        connections: ConnectionConfig,
+        counter: ConnectionCounter,
+        rooms: Rooms,
+        default_room: String,
+        shutdown: Shutdown,
+        limits: Limits,
+    ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        warp::path("websocket")
+            .and(room(default_room, rooms.clone(), shutdown.clone()))
+            // The `ws()` filter will prepare the Websocket handshake.
+            .and(warp::ws())
+            .and(admission(connections, counter))
+            .and(with_rooms(rooms))
+            .and(with_shutdown(shutdown))
+            .and(with_limits(limits))
+            .map(
+                |room: String, ws: warp::ws::Ws, slot: Slot, rooms, shutdown, limits: Limits| {
+                    let hard_limit = limits.hard_message_bytes();
+                    // And then our closure will be called when it completes...
+                    ws.max_message_size(hard_limit)
+                        .max_frame_size(hard_limit)
+                        .on_upgrade(move |websocket| async move {
+                            super::handlers::user_connected(
+                                websocket, rooms, room, shutdown, limits,
+                            )
+                            .await;
+                            // the connection counts against the limits until it is closed
+                            drop(slot);
+                            //// Just echo all messages back...
+                            //let (tx, rx) = websocket.split();
+                            //rx.forward(tx).map(|result| {
+                            //    if let Err(e) = result {
+                            //        eprintln!("websocket error: {:?}", e);
+                            //    }
+                            //})
+                        })
+                },
+            )
+    }
+
+    /// Checks the origin and reserves a connection slot for a websocket upgrade.
+    pub fn admission(
+        config: ConnectionConfig,
+        counter: ConnectionCounter,
+    ) -> impl Filter<Extract = (Slot,), Error = Rejection> + Clone {
+        warp::header::optional::<String>("origin")
+            .and(warp::header::optional::<String>("host"))
+            .and(warp::addr::remote())
+            .and_then(
+                move |origin: Option<String>,
+                      host: Option<String>,
+                      remote: Option<std::net::SocketAddr>| {
+                    let result = if config.origin_allowed(origin.as_deref(), host.as_deref()) {
+                        counter.acquire(remote.map(|address| address.ip()), &config)
+                    } else {
+                        Err(Refusal::Origin)
+                    };
+                    async move {
+                        result.map_err(|refusal| {
+                            info!(?origin, ?remote, ?refusal, "refused connection");
+                            super::metrics::record_refused_connection(refusal);
+                            warp::reject::custom(refusal)
+                        })
+                    }
+                },
+            )
+    }
+
+    /// Passes if `enabled`, otherwise rejects as not found.
+    pub fn enabled(enabled: bool) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+        warp::any()
+            .and_then(move || async move {
+                if enabled {
+                    Ok(())
+                } else {
+                    Err(warp::reject::not_found())
+                }
+            })
+            .untuple_one()
     }
 
-    pub fn with_users(
-        users: Users,
-    ) -> impl Filter<Extract = (Users,), Error = std::convert::Infallible> + Clone {
-        warp::any().map(move || users.clone())
+    #[derive(Debug)]
+    struct Unauthorized;
+
+    impl warp::reject::Reject for Unauthorized {}
+
+    /// Compare without returning early so the response time doesn't reveal the matching prefix.
+    fn token_matches(expected: &str, given: &str) -> bool {
+        expected.len() == given.len()
+            && expected
+                .bytes()
+                .zip(given.bytes())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+
+    /// Only lets requests through which carry `Authorization: Bearer <token>`.
+    /// Without a configured token every request is rejected as not found,
+    /// so a server without admin configuration doesn't advertise the endpoints.
+    pub fn admin(token: Option<String>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+        warp::header::optional::<String>("authorization")
+            .and_then(move |header: Option<String>| {
+                let token = token.clone();
+                async move {
+                    let expected = token.ok_or_else(warp::reject::not_found)?;
+                    match header.as_deref().and_then(|h| h.strip_prefix("Bearer ")) {
+                        Some(given) if token_matches(&expected, given) => Ok(()),
+                        _ => Err(warp::reject::custom(Unauthorized)),
+                    }
+                }
+            })
+            .untuple_one()
+    }
+
+    /// Turns our own rejections into responses, everything else is left to warp.
+    pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+        if err.find::<Unauthorized>().is_some() {
+            Ok(warp::reply::with_status(
+                "missing or wrong admin token",
+                StatusCode::UNAUTHORIZED,
+            ))
+        } else if let Some(refusal) = err.find::<Refusal>() {
+            Ok(warp::reply::with_status(
+                refusal.message(),
+                refusal.status(),
+            ))
+        } else if err.find::<Draining>().is_some() {
+            Ok(warp::reply::with_status(
+                "server is shutting down",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ))
+        } else {
+            Err(err)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn protected(
+            token: Option<&str>,
+        ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+            warp::path("secret")
+                .and(admin(token.map(String::from)))
+                .map(|| "ok")
+                .recover(handle_rejection)
+        }
+
+        #[tokio::test]
+        async fn admin_needs_configured_token() {
+            let response = warp::test::request()
+                .path("/secret")
+                .header("authorization", "Bearer ")
+                .reply(&protected(None))
+                .await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+
+        #[tokio::test]
+        async fn admin_checks_token() {
+            let filter = protected(Some("hunter2"));
+            for header in ["Bearer hunter3", "hunter2", "Bearer hunter"].iter() {
+                let response = warp::test::request()
+                    .path("/secret")
+                    .header("authorization", *header)
+                    .reply(&filter)
+                    .await;
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", header);
+            }
+            let response = warp::test::request().path("/secret").reply(&filter).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let response = warp::test::request()
+                .path("/secret")
+                .header("authorization", "Bearer hunter2")
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn admission_checks_origin_and_limits() {
+            let config = ConnectionConfig {
+                allowed_origins: vec!["https://game.example".to_string()],
+                max_total: 1,
+                ..ConnectionConfig::default()
+            };
+            let filter = admission(config, ConnectionCounter::default())
+                .map(|slot: Slot| {
+                    // keep the slot open as if the connection was still alive
+                    std::mem::forget(slot);
+                    "ok"
+                })
+                .recover(handle_rejection);
+            let request = |origin: &str| {
+                warp::test::request()
+                    .path("/websocket")
+                    .header("host", "evil.example")
+                    .header("origin", origin)
+            };
+            let response = request("https://evil.example").reply(&filter).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            let response = request("https://game.example").reply(&filter).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let response = request("https://game.example").reply(&filter).await;
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        }
     }
 }
@@ -0,0 +1,219 @@
+use super::connections::Refusal;
+use super::rooms::Rooms;
+use common::game::{Action, GamePhase, RejectReason};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_gauge, register_histogram, register_histogram_vec, register_int_counter,
+    register_int_counter_vec, register_int_gauge, Encoder, Gauge, Histogram, HistogramVec,
+    IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+
+lazy_static! {
+    pub static ref CONNECTED_WEBSOCKETS: IntGauge = register_int_gauge!(
+        "beunique_connected_websockets",
+        "Number of open websocket connections"
+    )
+    .unwrap();
+    static ref ROOMS: IntGauge =
+        register_int_gauge!("beunique_rooms", "Number of open rooms").unwrap();
+    static ref ACTIVE_GAMES: IntGauge = register_int_gauge!(
+        "beunique_active_games",
+        "Number of rooms with a game past the lobby phase"
+    )
+    .unwrap();
+    static ref GAMES_STARTED: IntCounter =
+        register_int_counter!("beunique_games_started_total", "Games which left the lobby")
+            .unwrap();
+    static ref GAMES_FINISHED: IntCounter = register_int_counter!(
+        "beunique_games_finished_total",
+        "Games which went back to the lobby"
+    )
+    .unwrap();
+    static ref ROUNDS: IntCounterVec = register_int_counter_vec!(
+        "beunique_rounds_total",
+        "Finished rounds by whether the word was guessed",
+        &["success"]
+    )
+    .unwrap();
+    static ref GUESS_SUCCESS_RATIO: Gauge = register_gauge!(
+        "beunique_guess_success_ratio",
+        "Share of finished rounds in which the word was guessed"
+    )
+    .unwrap();
+    static ref ACTIONS: IntCounterVec = register_int_counter_vec!(
+        "beunique_actions_total",
+        "Actions received from clients by type and whether the game accepted them",
+        &["action", "outcome"]
+    )
+    .unwrap();
+    static ref REJECTED_FRAMES: IntCounterVec = register_int_counter_vec!(
+        "beunique_rejected_frames_total",
+        "Frames dropped before reaching the game by reason",
+        &["reason"]
+    )
+    .unwrap();
+    static ref REFUSED_CONNECTIONS: IntCounterVec = register_int_counter_vec!(
+        "beunique_refused_connections_total",
+        "Websocket upgrades refused by reason",
+        &["reason"]
+    )
+    .unwrap();
+    static ref NOTIFY_ALL_SECONDS: Histogram = register_histogram!(
+        "beunique_notify_all_seconds",
+        "Time to send the view to every connection"
+    )
+    .unwrap();
+    static ref MESSAGE_BYTES: HistogramVec = register_histogram_vec!(
+        "beunique_message_bytes",
+        "Size of serialized messages sent to clients",
+        &["message"],
+        prometheus::exponential_buckets(64.0, 2.0, 10).unwrap()
+    )
+    .unwrap();
+}
+
+fn outcome(accepted: bool) -> &'static str {
+    if accepted {
+        "accepted"
+    } else {
+        "rejected"
+    }
+}
+
+pub fn record_action(action: &Action, accepted: bool) {
+    ACTIONS
+        .with_label_values(&[action.name(), outcome(accepted)])
+        .inc();
+}
+
+pub fn record_rejected_frame(reason: RejectReason) {
+    REJECTED_FRAMES
+        .with_label_values(&[&format!("{:?}", reason)])
+        .inc();
+}
+
+pub fn record_refused_connection(refusal: Refusal) {
+    REFUSED_CONNECTIONS
+        .with_label_values(&[&format!("{:?}", refusal)])
+        .inc();
+}
+
+/// What we need to know about the game before an action to see what the action changed.
+pub struct Progress {
+    running: bool,
+    rounds: usize,
+}
+
+impl Progress {
+    pub fn of(state: &common::game::State) -> Self {
+        Self {
+            running: !matches!(state.phase, GamePhase::GatherPlayers),
+            rounds: state.past_rounds.len(),
+        }
+    }
+
+    /// Count started and finished games and rounds between `self` and `state`.
+    pub fn record(self, state: &common::game::State) {
+        let after = Progress::of(state);
+        match (self.running, after.running) {
+            (false, true) => GAMES_STARTED.inc(),
+            (true, false) => GAMES_FINISHED.inc(),
+            _ => {}
+        }
+        for round in state.past_rounds.iter().skip(self.rounds) {
+            ROUNDS
+                .with_label_values(&[&round.success.to_string()])
+                .inc();
+        }
+    }
+}
+
+pub fn notify_all_timer() -> prometheus::HistogramTimer {
+    NOTIFY_ALL_SECONDS.start_timer()
+}
+
+pub fn record_message_size(message: &'static str, bytes: usize) {
+    MESSAGE_BYTES
+        .with_label_values(&[message])
+        .observe(bytes as f64);
+}
+
+/// The metrics in the Prometheus text format.
+pub async fn render(rooms: Rooms) -> Result<impl warp::Reply, std::convert::Infallible> {
+    let rooms = rooms.all().await;
+    let mut running = 0;
+    for room in rooms.iter() {
+        if room.is_running().await {
+            running += 1;
+        }
+    }
+    ROOMS.set(rooms.len() as i64);
+    ACTIVE_GAMES.set(running);
+    let successes = ROUNDS.with_label_values(&["true"]).get();
+    let failures = ROUNDS.with_label_values(&["false"]).get();
+    if successes + failures > 0 {
+        GUESS_SUCCESS_RATIO.set(successes as f64 / (successes + failures) as f64);
+    }
+    let mut buffer = vec![];
+    let encoder = TextEncoder::new();
+    encoder.encode(&prometheus::gather(), &mut buffer).unwrap();
+    Ok(warp::reply::with_header(
+        buffer,
+        "content-type",
+        encoder.format_type(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::Library;
+    use warp::Reply;
+
+    #[tokio::test]
+    async fn progress_counts_started_games_and_rounds() {
+        let mut state = common::game::State::new(Library::default());
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        let started = GAMES_STARTED.get();
+        let progress = Progress::of(&state);
+        state.action(&Action::Start { id: 1 });
+        progress.record(&state);
+        assert!(GAMES_STARTED.get() > started);
+
+        let failures = ROUNDS.with_label_values(&["false"]).get();
+        for _ in 0..4 {
+            let progress = Progress::of(&state);
+            state.force_advance();
+            progress.record(&state);
+        }
+        assert!(ROUNDS.with_label_values(&["false"]).get() > failures);
+
+        let rooms = Rooms::new(Library::default(), None, None, None, 10);
+        let mut games = std::collections::BTreeMap::new();
+        games.insert("running".to_string(), state);
+        games.insert(
+            "lobby".to_string(),
+            common::game::State::new(Library::default()),
+        );
+        games.insert(
+            "other".to_string(),
+            common::game::State::new(Library::default()),
+        );
+        rooms.restore(games).await;
+        let response = render(rooms).await.unwrap().into_response();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let text = String::from_utf8_lossy(&body);
+        assert!(text.contains("beunique_rooms 3"));
+        assert!(text.contains("beunique_active_games 1"));
+        assert!(text.contains("beunique_guess_success_ratio"));
+    }
+}
@@ -0,0 +1,719 @@
+use super::handlers;
+use super::rooms::Rooms;
+use common::game::{Difficulty, Library, Word, WordPack};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// Language of packs which don't say otherwise.
+const DEFAULT_LANGUAGE: &str = "de";
+
+/// Something wrong with a word file. The entry in question is left out, the
+/// rest of the pack is still used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub path: PathBuf,
+    /// Line of the plain and TOML formats, position of the entry for JSON
+    /// files without a line of their own.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+/// The word packs at `path`, which is either a single word file or a
+/// directory of them. Every `.txt`, `.toml` and `.json` file is a pack named
+/// after the file.
+///
+/// The plain format has one word per line. Lines starting with `#` are
+/// comments, except for `# key: value` lines before the first word which
+/// describe the pack. A word may be followed by attributes separated by `;`:
+///
+/// ```text
+/// # name: Verkehr
+/// # language: de
+/// # category: Alltag
+/// # difficulty: easy
+/// Schwan
+/// Fahrrad; also: Rad, Velo; tags: Sport; difficulty: medium
+/// ```
+///
+/// The TOML and JSON formats have the same fields:
+///
+/// ```toml
+/// name = "Verkehr"
+/// difficulty = "easy"
+///
+/// [[words]]
+/// word = "Fahrrad"
+/// also = ["Rad", "Velo"]
+/// tags = ["Sport"]
+/// ```
+///
+/// In JSON `words` may also be a list of plain strings.
+///
+/// Files which can't be read or parsed keep the words they had before, so a
+/// mistake while editing a file doesn't take the pack away.
+#[derive(Debug)]
+pub struct Packs {
+    path: PathBuf,
+    files: BTreeMap<PathBuf, WordPack>,
+}
+
+/// Packs named like one read from an earlier file are left out, the game
+/// couldn't tell them apart.
+fn leave_out_duplicates(loaded: &mut BTreeMap<PathBuf, WordPack>) -> Vec<Problem> {
+    let mut first: HashMap<String, PathBuf> = HashMap::new();
+    let mut problems = vec![];
+    loaded.retain(|file, pack| match first.get(&pack.info.name) {
+        Some(earlier) => {
+            let problem = Problem {
+                path: file.clone(),
+                line: None,
+                message: format!(
+                    "pack {} is already read from {}, leaving this one out",
+                    pack.info.name,
+                    earlier.display()
+                ),
+            };
+            warn!(%problem, "problem in word file");
+            problems.push(problem);
+            false
+        }
+        None => {
+            first.insert(pack.info.name.clone(), file.clone());
+            true
+        }
+    });
+    problems
+}
+
+/// Modification time and size of every word file, to notice changes.
+pub type Fingerprint = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+impl Packs {
+    /// Nothing is read before `load`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// (Re)reads every word file. Fails without changing anything if there are
+    /// no words at all.
+    pub fn load(&mut self) -> Result<Vec<Problem>, std::io::Error> {
+        let mut loaded = BTreeMap::new();
+        let mut problems = vec![];
+        for file in self.list_files()? {
+            let (pack, mut found) = read_file(&file);
+            for problem in found.iter() {
+                warn!(%problem, "problem in word file");
+            }
+            problems.append(&mut found);
+            match pack {
+                Some(pack) if pack.words.is_empty() => {
+                    warn!(path = ?file, "pack has no words, leaving it out");
+                }
+                Some(pack) => {
+                    info!(path = ?file, pack = %pack.info.name, words = pack.words.len(), "read pack");
+                    loaded.insert(file, pack);
+                }
+                None => {
+                    if let Some(previous) = self.files.remove(&file) {
+                        warn!(path = ?file, "keeping the previous version of the pack");
+                        loaded.insert(file, previous);
+                    }
+                }
+            }
+        }
+        problems.append(&mut leave_out_duplicates(&mut loaded));
+        if loaded.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "no words in any pack",
+            ));
+        }
+        self.files = loaded;
+        Ok(problems)
+    }
+
+    pub fn library(&self) -> Library {
+        Library::new(self.files.values().cloned().collect())
+    }
+
+    pub fn fingerprint(&self) -> Result<Fingerprint, std::io::Error> {
+        Ok(self
+            .list_files()?
+            .into_iter()
+            .map(|file| {
+                let metadata = std::fs::metadata(&file).ok();
+                let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+                let size = metadata.map_or(0, |m| m.len());
+                (file, modified, size)
+            })
+            .collect())
+    }
+
+    fn list_files(&self) -> Result<Vec<PathBuf>, std::io::Error> {
+        if self.path.is_dir() {
+            let mut files: Vec<_> = std::fs::read_dir(&self.path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| format_of(file).is_some())
+                .collect();
+            files.sort();
+            Ok(files)
+        } else {
+            Ok(vec![self.path.clone()])
+        }
+    }
+}
+
+fn read_file(file: &Path) -> (Option<WordPack>, Vec<Problem>) {
+    let content = match std::fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) => {
+            let problem = Problem {
+                path: file.to_path_buf(),
+                line: None,
+                message: e.to_string(),
+            };
+            return (None, vec![problem]);
+        }
+    };
+    match format_of(file) {
+        Some(Format::Toml) => parse_structured(file, &content, Format::Toml),
+        Some(Format::Json) => parse_structured(file, &content, Format::Json),
+        Some(Format::Plain) | None => parse_plain(file, &content),
+    }
+}
+
+pub type SharedPacks = Arc<Mutex<Packs>>;
+
+/// What a reload found, for the admin API.
+#[derive(Serialize, Debug)]
+pub struct ReloadReport {
+    pub packs: usize,
+    pub words: usize,
+    pub problems: Vec<String>,
+}
+
+/// Read the word files again and let every room use them from its next game on.
+pub async fn reload(packs: &SharedPacks, rooms: &Rooms) -> Result<ReloadReport, std::io::Error> {
+    let (library, problems) = {
+        let mut packs = packs.lock().unwrap();
+        let problems = packs.load()?;
+        (packs.library(), problems)
+    };
+    let infos = library.infos();
+    let report = ReloadReport {
+        packs: infos.len(),
+        words: infos.iter().map(|info| info.size).sum(),
+        problems: problems.iter().map(|problem| problem.to_string()).collect(),
+    };
+    info!(
+        packs = report.packs,
+        words = report.words,
+        problems = report.problems.len(),
+        "reloaded word packs"
+    );
+    rooms.set_library(library).await;
+    for room in rooms.all().await {
+        handlers::notify_all(&room.users, &room.state).await;
+    }
+    Ok(report)
+}
+
+/// Reload on SIGHUP and, unless `interval` is zero, when the files change.
+pub async fn watch(packs: SharedPacks, rooms: Rooms, interval: Duration) {
+    let mut hangup = Hangup::new();
+    let mut ticks = if interval.as_secs() > 0 {
+        Some(tokio::time::interval(interval))
+    } else {
+        None
+    };
+    let fingerprint = || packs.lock().unwrap().fingerprint().ok();
+    let mut seen = fingerprint();
+    loop {
+        tokio::select! {
+            _ = hangup.recv() => info!("received SIGHUP"),
+            _ = tick(&mut ticks) => {
+                if fingerprint() == seen {
+                    continue;
+                }
+                info!("word files changed");
+            }
+        }
+        if let Err(e) = reload(&packs, &rooms).await {
+            warn!(error = %e, "couldn't reload word packs, keeping the old ones");
+        }
+        seen = fingerprint();
+    }
+}
+
+async fn tick(ticks: &mut Option<tokio::time::Interval>) {
+    match ticks {
+        Some(ticks) => {
+            ticks.tick().await;
+        }
+        None => futures::future::pending::<()>().await,
+    }
+}
+
+struct Hangup {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl Hangup {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let signal = signal(SignalKind::hangup())
+                .map_err(|e| warn!(error = %e, "can't listen for SIGHUP"))
+                .ok();
+            Self { signal }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        {
+            if let Some(signal) = self.signal.as_mut() {
+                signal.recv().await;
+                return;
+            }
+        }
+        futures::future::pending::<()>().await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Plain,
+    Toml,
+    Json,
+}
+
+fn format_of(path: &Path) -> Option<Format> {
+    match path.extension()?.to_str()? {
+        "txt" => Some(Format::Plain),
+        "toml" => Some(Format::Toml),
+        "json" => Some(Format::Json),
+        _ => None,
+    }
+}
+
+fn pack_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn parse_difficulty(value: &str) -> Option<Difficulty> {
+    match value.to_lowercase().as_str() {
+        "easy" => Some(Difficulty::Easy),
+        "medium" => Some(Difficulty::Medium),
+        "hard" => Some(Difficulty::Hard),
+        _ => None,
+    }
+}
+
+/// Collects the words of a pack and complains about what's wrong with them.
+struct Builder<'a> {
+    path: &'a Path,
+    pack: WordPack,
+    /// Every spelling seen so far, lower case, with the line it was on.
+    seen: HashMap<String, Option<usize>>,
+    problems: Vec<Problem>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(path: &'a Path) -> Self {
+        Self {
+            path,
+            pack: WordPack::new(&pack_name(path), DEFAULT_LANGUAGE, vec![]),
+            seen: HashMap::new(),
+            problems: vec![],
+        }
+    }
+
+    fn problem(&mut self, line: Option<usize>, message: String) {
+        self.problems.push(Problem {
+            path: self.path.to_path_buf(),
+            line,
+            message,
+        });
+    }
+
+    /// Checks a word or spelling for stray whitespace and characters which
+    /// don't belong in a word.
+    fn valid(&mut self, line: Option<usize>, text: &str) -> bool {
+        if text.is_empty() {
+            self.problem(line, "empty word".to_string());
+            false
+        } else if text.trim() != text || text.contains("  ") {
+            self.problem(line, format!("stray whitespace in \"{}\"", text));
+            false
+        } else if text.chars().any(|c| c.is_control() || c == ';' || c == '#') {
+            self.problem(line, format!("invalid character in \"{}\"", text));
+            false
+        } else {
+            true
+        }
+    }
+
+    fn add(&mut self, line: Option<usize>, word: Word) {
+        let spellings: Vec<&String> = std::iter::once(&word.word).chain(&word.also).collect();
+        for spelling in spellings.iter() {
+            if !self.valid(line, spelling) {
+                return;
+            }
+        }
+        for spelling in spellings.iter() {
+            if let Some(earlier) = self.seen.get(&spelling.to_lowercase()) {
+                let message = match earlier {
+                    Some(earlier) => format!("\"{}\" is already on line {}", spelling, earlier),
+                    None => format!("duplicate \"{}\"", spelling),
+                };
+                self.problem(line, message);
+                return;
+            }
+        }
+        for spelling in spellings {
+            self.seen.insert(spelling.to_lowercase(), line);
+        }
+        self.pack.words.push(word);
+    }
+
+    fn finish(mut self) -> (Option<WordPack>, Vec<Problem>) {
+        self.pack.info.size = self.pack.words.len();
+        (Some(self.pack), self.problems)
+    }
+}
+
+fn parse_plain(path: &Path, content: &str) -> (Option<WordPack>, Vec<Problem>) {
+    let mut builder = Builder::new(path);
+    for (index, line) in content.lines().enumerate() {
+        let number = Some(index + 1);
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            if builder.pack.words.is_empty() {
+                plain_metadata(&mut builder, number, comment);
+            }
+            continue;
+        }
+        if line.trim() != line {
+            builder.problem(
+                number,
+                "stray whitespace at the start or end of the line".to_string(),
+            );
+            continue;
+        }
+        if let Some(word) = plain_word(&mut builder, number, line) {
+            builder.add(number, word);
+        }
+    }
+    builder.finish()
+}
+
+/// `# key: value` before the first word, anything else is a comment.
+fn plain_metadata(builder: &mut Builder, line: Option<usize>, comment: &str) {
+    let (key, value) = match comment.split_once(':') {
+        Some((key, value)) => (key.trim(), value.trim().to_string()),
+        None => return,
+    };
+    let info = &mut builder.pack.info;
+    match key {
+        "name" => info.name = value,
+        "language" => info.language = value,
+        "category" => info.category = Some(value),
+        "difficulty" => match parse_difficulty(&value) {
+            Some(difficulty) => info.difficulty = Some(difficulty),
+            None => builder.problem(line, format!("unknown difficulty \"{}\"", value)),
+        },
+        _ => {}
+    }
+}
+
+fn plain_word(builder: &mut Builder, line: Option<usize>, text: &str) -> Option<Word> {
+    let mut fields = text.split(';');
+    let mut word = Word::from(fields.next()?.trim_end());
+    for field in fields {
+        let (key, value) = match field.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => {
+                builder.problem(
+                    line,
+                    format!("expected \"key: value\", got \"{}\"", field.trim()),
+                );
+                return None;
+            }
+        };
+        let list = || {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .collect()
+        };
+        match key {
+            "also" => word.also = list(),
+            "tags" => word.tags = list(),
+            "difficulty" => match parse_difficulty(value) {
+                Some(difficulty) => word.difficulty = Some(difficulty),
+                None => {
+                    builder.problem(line, format!("unknown difficulty \"{}\"", value));
+                    return None;
+                }
+            },
+            _ => {
+                builder.problem(line, format!("unknown attribute \"{}\"", key));
+                return None;
+            }
+        }
+    }
+    if word.tags.iter().any(|tag| tag.is_empty()) {
+        builder.problem(line, "empty tag".to_string());
+        return None;
+    }
+    Some(word)
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct PackFile {
+    name: Option<String>,
+    language: Option<String>,
+    category: Option<String>,
+    difficulty: Option<Difficulty>,
+    words: Vec<EntryFile>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum EntryFile {
+    Plain(String),
+    Full(EntryTable),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct EntryTable {
+    word: String,
+    #[serde(default)]
+    also: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    difficulty: Option<Difficulty>,
+}
+
+fn parse_structured(
+    path: &Path,
+    content: &str,
+    format: Format,
+) -> (Option<WordPack>, Vec<Problem>) {
+    let mut builder = Builder::new(path);
+    let parsed: Result<PackFile, (Option<usize>, String)> = match format {
+        Format::Toml => toml::from_str(content)
+            .map_err(|e| (e.line_col().map(|(line, _)| line + 1), e.to_string())),
+        _ => serde_json::from_str(content).map_err(|e| (Some(e.line()), e.to_string())),
+    };
+    let file = match parsed {
+        Ok(file) => file,
+        Err((line, message)) => {
+            builder.problem(line, message);
+            return (None, builder.problems);
+        }
+    };
+    let info = &mut builder.pack.info;
+    info.name = file.name.unwrap_or_else(|| info.name.clone());
+    info.language = file.language.unwrap_or_else(|| info.language.clone());
+    info.category = file.category;
+    info.difficulty = file.difficulty;
+    for (index, entry) in file.words.into_iter().enumerate() {
+        let word = match entry {
+            EntryFile::Plain(word) => Word::from(word.as_str()),
+            EntryFile::Full(entry) => Word {
+                word: entry.word,
+                also: entry.also,
+                tags: entry.tags,
+                difficulty: entry.difficulty,
+            },
+        };
+        let line = entry_line(content, format, &word.word).or(Some(index + 1));
+        builder.add(line, word);
+    }
+    builder.finish()
+}
+
+/// The line of the first `word = "..."` or `"..."` with this word, good enough
+/// to point at an entry.
+fn entry_line(content: &str, format: Format, word: &str) -> Option<usize> {
+    let quoted = match format {
+        Format::Json => serde_json::to_string(word).ok()?,
+        _ => format!("{:?}", word),
+    };
+    content
+        .lines()
+        .position(|line| line.contains(&quoted))
+        .map(|index| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(content: &str) -> (WordPack, Vec<Problem>) {
+        let (pack, problems) = parse_plain(Path::new("test.txt"), content);
+        (pack.unwrap(), problems)
+    }
+
+    #[test]
+    fn directory_of_packs_with_metadata() {
+        let dir = std::env::temp_dir().join(format!("beunique-packs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("tiere.txt"),
+            "# language: en\n# category: Natur\n# difficulty: hard\nSchwan\n\nHund\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("leer.txt"), "# name: Leer\n").unwrap();
+        std::fs::write(
+            dir.join("orte.toml"),
+            "name = \"Orte\"\n[[words]]\nword = \"Steinbruch\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.md"), "not a pack").unwrap();
+        let mut packs = Packs::new(&dir);
+        let problems = packs.load().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(problems.is_empty());
+        let infos = packs.library().infos();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].name, "Orte");
+        assert_eq!(infos[1].name, "tiere");
+        assert_eq!(infos[1].language, "en");
+        assert_eq!(infos[1].category.as_deref(), Some("Natur"));
+        assert_eq!(infos[1].difficulty, Some(Difficulty::Hard));
+        assert_eq!(infos[1].size, 2);
+    }
+
+    #[test]
+    fn packs_with_the_same_name_are_reported() {
+        let dir = std::env::temp_dir().join(format!("beunique-duplicate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("a.txt"),
+            "# name: Tiere
+Schwan
+",
+        )
+        .unwrap();
+        std::fs::write(dir.join("b.toml"), "name = \"Tiere\"\nwords = [\"Hund\"]\n").unwrap();
+        let mut packs = Packs::new(&dir);
+        let problems = packs.load().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].path, dir.join("b.toml"));
+        assert!(problems[0]
+            .message
+            .contains(&dir.join("a.txt").display().to_string()));
+        let infos = packs.library().infos();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].size, 1);
+    }
+
+    #[test]
+    fn reload_keeps_packs_which_became_unreadable() {
+        let dir = std::env::temp_dir().join(format!("beunique-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("tiere.txt"), "Schwan\nHund\n").unwrap();
+        std::fs::write(dir.join("orte.json"), r#"{"words": ["Steinbruch"]}"#).unwrap();
+        let mut packs = Packs::new(&dir);
+        packs.load().unwrap();
+        let before = packs.fingerprint().unwrap();
+
+        std::fs::write(dir.join("tiere.txt"), "Schwan\nHund\nKatze\n").unwrap();
+        std::fs::write(dir.join("orte.json"), r#"{"words": ["Steinbruch""#).unwrap();
+        assert_ne!(packs.fingerprint().unwrap(), before);
+        let problems = packs.load().unwrap();
+        assert_eq!(problems.len(), 1);
+        let infos = packs.library().infos();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].name, "orte");
+        assert_eq!(infos[0].size, 1);
+        assert_eq!(infos[1].size, 3);
+
+        std::fs::write(dir.join("tiere.txt"), "").unwrap();
+        std::fs::remove_file(dir.join("orte.json")).unwrap();
+        assert!(packs.load().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(packs.library().infos().len(), 2);
+    }
+
+    #[test]
+    fn plain_words_with_attributes() {
+        let (pack, problems) = plain(
+            "# Verkehrsmittel\n# difficulty: easy\nFahrrad; also: Rad, Velo; tags: Sport, Alltag; difficulty: medium\n# noch ein Kommentar\nZug\n",
+        );
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(pack.info.difficulty, Some(Difficulty::Easy));
+        assert_eq!(
+            pack.words[0],
+            Word {
+                word: "Fahrrad".to_string(),
+                also: vec!["Rad".to_string(), "Velo".to_string()],
+                tags: vec!["Sport".to_string(), "Alltag".to_string()],
+                difficulty: Some(Difficulty::Medium),
+            }
+        );
+        assert_eq!(pack.words[1], Word::from("Zug"));
+    }
+
+    #[test]
+    fn plain_problems_have_line_numbers() {
+        let (pack, problems) = plain(
+            "Schwan\n Hund\nKatze; farbe: rot\nschwan\nRad; also: Schwan\nAuto ; also: Wagen,\n",
+        );
+        let lines: Vec<Option<usize>> = problems.iter().map(|p| p.line).collect();
+        assert_eq!(lines, vec![Some(2), Some(3), Some(4), Some(5), Some(6)]);
+        assert!(problems[2].message.contains("line 1"));
+        assert_eq!(pack.words, vec![Word::from("Schwan")]);
+        assert_eq!(
+            problems[0].to_string(),
+            "test.txt:2: stray whitespace at the start or end of the line"
+        );
+    }
+
+    #[test]
+    fn structured_formats() {
+        let json = r#"{"name": "Tiere", "words": ["Schwan", {"word": "Hund", "also": ["Köter"]}, "hund"]}"#;
+        let (pack, problems) = parse_structured(Path::new("tiere.json"), json, Format::Json);
+        let pack = pack.unwrap();
+        assert_eq!(pack.info.name, "Tiere");
+        assert_eq!(pack.words.len(), 2);
+        assert_eq!(problems.len(), 1);
+
+        let toml = "difficulty = \"hard\"\n\n[[words]]\nword = \"Schwan\"\nsurprise = 1\n";
+        let (pack, problems) = parse_structured(Path::new("tiere.toml"), toml, Format::Toml);
+        assert!(pack.is_none());
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].line.is_some());
+    }
+}
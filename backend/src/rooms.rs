@@ -0,0 +1,307 @@
+use super::connections::Refusal;
+use super::flags;
+use super::history;
+use super::models::{ActionLog, State, UserSender, Users};
+use common::game::{GamePhase, Library};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Longest room name.
+pub const MAX_ROOM_CHARS: usize = 30;
+
+/// Room names end up in file names, so they are limited to letters, digits,
+/// `-` and `_`.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_ROOM_CHARS
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// A game and the connections playing it.
+#[derive(Clone)]
+pub struct Room {
+    pub name: String,
+    pub state: State,
+    pub users: Users,
+    pub log: ActionLog,
+}
+
+impl Room {
+    pub fn new(name: &str, game: common::game::State) -> Self {
+        Self {
+            name: name.to_string(),
+            state: State::new(tokio::sync::Mutex::new(game)),
+            users: Users::default(),
+            log: ActionLog::default(),
+        }
+    }
+
+    /// Whether a game is past the lobby.
+    pub async fn is_running(&self) -> bool {
+        !matches!(self.state.lock().await.phase, GamePhase::GatherPlayers)
+    }
+}
+
+/// Every open room by name. A room is opened by the first connection to it
+/// and closed by the admin API or once nobody was connected for a while.
+#[derive(Clone)]
+pub struct Rooms {
+    rooms: Arc<RwLock<BTreeMap<String, Room>>>,
+    /// The packs of new rooms, replaced when the word files are reloaded.
+    library: Arc<std::sync::Mutex<Library>>,
+    seed: Option<u64>,
+    history: Option<PathBuf>,
+    flags: Option<PathBuf>,
+    /// Every room holds a copy of the packs and has a history file, so their
+    /// number is limited.
+    max_rooms: usize,
+}
+
+impl Rooms {
+    /// New rooms draw from `library` with `seed`, or a random seed if there
+    /// is none. Their word history is kept in the directory `history`, and
+    /// their flags are appended to `flags` when they close. At most
+    /// `max_rooms` are open at once.
+    pub fn new(
+        library: Library,
+        seed: Option<u64>,
+        history: Option<PathBuf>,
+        flags: Option<PathBuf>,
+        max_rooms: usize,
+    ) -> Self {
+        Self {
+            rooms: Arc::default(),
+            library: Arc::new(std::sync::Mutex::new(library)),
+            seed,
+            history,
+            flags,
+            max_rooms,
+        }
+    }
+
+    /// Prepare `game` to be played in the room `name`: current packs and the
+    /// stored word history. The seed is the game's, so a restored room goes on
+    /// drawing where it was.
+    fn setup(&self, name: &str, mut game: common::game::State) -> Room {
+        game.set_library(self.library.lock().unwrap().clone());
+        if let Some(path) = self.history_path(name) {
+            match history::load(&path) {
+                Ok(history) => game.history = history,
+                Err(e) => warn!(?path, error = %e, "couldn't read word history"),
+            }
+        }
+        info!(room = name, seed = game.seed, "opened room");
+        Room::new(name, game)
+    }
+
+    /// Where the words played in the room `name` are kept.
+    pub fn history_path(&self, name: &str) -> Option<PathBuf> {
+        Some(history::path(self.history.as_deref()?, name))
+    }
+
+    /// Open the rooms of a snapshot again.
+    pub async fn restore(&self, games: BTreeMap<String, common::game::State>) {
+        let mut rooms = self.rooms.write().await;
+        for (name, game) in games {
+            let room = self.setup(&name, game);
+            rooms.insert(name, room);
+        }
+    }
+
+    pub async fn get(&self, name: &str) -> Option<Room> {
+        self.rooms.read().await.get(name).cloned()
+    }
+
+    pub async fn contains(&self, name: &str) -> bool {
+        self.rooms.read().await.contains_key(name)
+    }
+
+    /// Whether the room `name` is open or can be opened.
+    pub async fn admits(&self, name: &str) -> bool {
+        let rooms = self.rooms.read().await;
+        rooms.contains_key(name) || rooms.len() < self.max_rooms
+    }
+
+    /// Every open room, ordered by name.
+    pub async fn all(&self) -> Vec<Room> {
+        self.rooms.read().await.values().cloned().collect()
+    }
+
+    /// Add connection `id` to the room `name`, which is opened with a new game
+    /// if it isn't open yet and there is space for it.
+    pub async fn enter(&self, name: &str, id: usize, tx: UserSender) -> Result<Room, Refusal> {
+        let mut rooms = self.rooms.write().await;
+        let room = match rooms.get(name) {
+            Some(room) => room.clone(),
+            None if rooms.len() >= self.max_rooms => {
+                warn!(
+                    room = name,
+                    max_rooms = self.max_rooms,
+                    "refusing to open room"
+                );
+                return Err(Refusal::TooManyRooms);
+            }
+            None => {
+                let library = self.library.lock().unwrap().clone();
+                let mut game = common::game::State::new(library);
+                game.seed = self.seed.unwrap_or_else(rand::random);
+                let room = self.setup(name, game);
+                rooms.insert(name.to_string(), room.clone());
+                room
+            }
+        };
+        room.users.write().await.insert(id, tx);
+        Ok(room)
+    }
+
+    /// Let every room use `library` from its next game on, and new rooms as well.
+    pub async fn set_library(&self, library: Library) {
+        *self.library.lock().unwrap() = library.clone();
+        for room in self.all().await {
+            room.state.lock().await.set_library(library.clone());
+        }
+    }
+
+    /// Remove the room `name` and store its history and flags. Its
+    /// connections are left to the caller.
+    pub async fn close(&self, name: &str) -> Option<Room> {
+        let room = self.rooms.write().await.remove(name)?;
+        self.store(&room).await;
+        Some(room)
+    }
+
+    /// Close the room `name` if nobody is connected to it.
+    async fn close_if_empty(&self, name: &str) -> bool {
+        let room = {
+            let mut rooms = self.rooms.write().await;
+            match rooms.get(name) {
+                Some(room) if room.users.read().await.is_empty() => rooms.remove(name),
+                _ => None,
+            }
+        };
+        match room {
+            Some(room) => {
+                self.store(&room).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Write what has to outlive the room.
+    pub async fn store(&self, room: &Room) {
+        if let Some(path) = self.history_path(&room.name) {
+            if let Err(e) = history::save(&room.state.lock().await.history, &path) {
+                warn!(?path, error = %e, "couldn't save word history");
+            }
+        }
+        flags::save_new(room, self.flags.as_deref()).await;
+    }
+
+    pub fn flag_file(&self) -> Option<&Path> {
+        self.flags.as_deref()
+    }
+}
+
+/// Close the rooms nobody was connected to during a whole `interval`, until
+/// the process ends. Players who lose their connection have that long to
+/// come back to a running game.
+pub async fn sweep(rooms: Rooms, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    let mut empty_before = BTreeSet::new();
+    loop {
+        ticks.tick().await;
+        let mut empty = BTreeSet::new();
+        for room in rooms.all().await {
+            if room.users.read().await.is_empty() {
+                empty.insert(room.name);
+            }
+        }
+        for name in empty.intersection(&empty_before) {
+            if rooms.close_if_empty(name).await {
+                info!(room = %name, "closed empty room");
+            }
+        }
+        empty_before = empty;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_safe_for_files() {
+        assert!(is_valid_name("stammtisch"));
+        assert!(is_valid_name("Küche_2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("../etc"));
+        assert!(!is_valid_name("a b"));
+        assert!(!is_valid_name(&"x".repeat(MAX_ROOM_CHARS + 1)));
+    }
+
+    #[tokio::test]
+    async fn rooms_are_opened_on_entering_and_closed_when_empty() {
+        let rooms = Rooms::new(Library::default(), Some(7), None, None, 10);
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let room = rooms.enter("a", 1, tx.clone()).await.unwrap();
+        assert_eq!(room.state.lock().await.seed, 7);
+        rooms.enter("b", 2, tx).await.unwrap();
+        let names: Vec<String> = rooms.all().await.into_iter().map(|r| r.name).collect();
+        assert_eq!(names, ["a", "b"]);
+
+        assert!(!rooms.close_if_empty("a").await);
+        room.users.write().await.remove(&1);
+        assert!(rooms.close_if_empty("a").await);
+        assert!(!rooms.contains("a").await);
+        assert!(rooms.close("b").await.is_some());
+        assert!(rooms.all().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn restored_rooms_keep_their_seed() {
+        let rooms = Rooms::new(Library::default(), Some(7), None, None, 10);
+        let mut game = common::game::State::new(Library::default());
+        game.seed = 42;
+        rooms
+            .restore(BTreeMap::from([("a".to_string(), game)]))
+            .await;
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let room = rooms.enter("a", 1, tx).await.unwrap();
+        assert_eq!(room.state.lock().await.seed, 42);
+    }
+
+    #[tokio::test]
+    async fn the_number_of_rooms_is_limited() {
+        let rooms = Rooms::new(Library::default(), None, None, None, 2);
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let a = rooms.enter("a", 1, tx.clone()).await.unwrap();
+        rooms.enter("b", 2, tx.clone()).await.unwrap();
+        assert!(!rooms.admits("c").await);
+        let refused = rooms.enter("c", 3, tx.clone()).await;
+        assert!(matches!(refused, Err(Refusal::TooManyRooms)));
+        // open rooms can still be entered
+        assert!(rooms.admits("a").await);
+        rooms.enter("a", 4, tx.clone()).await.unwrap();
+
+        // a swept room makes space for another one
+        a.users.write().await.clear();
+        let sweep = tokio::spawn(sweep(rooms.clone(), Duration::from_millis(10)));
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while rooms.contains("a").await {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("the empty room is closed");
+        sweep.abort();
+        assert!(rooms.admits("c").await);
+        rooms.enter("c", 3, tx).await.unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+use super::handlers;
+use super::rooms::Rooms;
+use common::game::{GamePhase, ServerMessage, CLOSE_GOING_AWAY};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+/// Shared between everything that has to behave differently while the server drains.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    draining: Arc<AtomicBool>,
+    requested: Arc<Notify>,
+}
+
+impl Shutdown {
+    /// While draining no new rooms and no new games are accepted.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Start draining as if the process had received SIGTERM.
+    pub fn request(&self) {
+        self.requested.notify_one();
+    }
+
+    /// Resolves on SIGTERM, Ctrl-C or `request`.
+    pub async fn requested(&self) {
+        #[cfg(unix)]
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut signal) => {
+                    signal.recv().await;
+                }
+                Err(e) => {
+                    warn!(error = %e, "can't listen for SIGTERM");
+                    futures::future::pending::<()>().await
+                }
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = futures::future::pending::<()>();
+        tokio::select! {
+            _ = terminate => info!("received SIGTERM"),
+            _ = tokio::signal::ctrl_c() => info!("received Ctrl-C"),
+            _ = self.requested.notified() => info!("shutdown requested"),
+        }
+    }
+
+    /// Tell everyone we're going away, let the current rounds finish within `grace`,
+    /// write the snapshot and close all connections.
+    pub async fn drain(&self, rooms: &Rooms, grace: Duration, snapshot: Option<&Path>) {
+        self.draining.store(true, Ordering::Relaxed);
+        info!(grace_seconds = grace.as_secs(), "draining");
+        let message = ServerMessage::ShuttingDown {
+            grace_seconds: grace.as_secs(),
+        };
+        let mut rounds_before = BTreeMap::new();
+        for room in rooms.all().await {
+            handlers::broadcast(&room.users, &message).await;
+            rounds_before.insert(room.name.clone(), room.state.lock().await.past_rounds.len());
+        }
+
+        let rounds_finished = async {
+            loop {
+                let mut finished = true;
+                for room in rooms.all().await {
+                    let state = room.state.lock().await;
+                    let before = rounds_before.get(&room.name).copied().unwrap_or(0);
+                    finished &= matches!(state.phase, GamePhase::GatherPlayers)
+                        || state.past_rounds.len() > before;
+                }
+                if finished {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+        };
+        if tokio::time::timeout(grace, rounds_finished).await.is_err() {
+            info!("grace period is over, closing with rounds in progress");
+        }
+
+        if let Some(path) = snapshot {
+            let mut games = BTreeMap::new();
+            for room in rooms.all().await {
+                games.insert(room.name.clone(), room.state.lock().await.clone());
+            }
+            match write_snapshot(&games, path) {
+                Ok(()) => info!(?path, rooms = games.len(), "wrote snapshot"),
+                Err(e) => warn!(?path, error = %e, "couldn't write snapshot"),
+            }
+        }
+
+        for room in rooms.all().await {
+            handlers::close_all(&room.users, CLOSE_GOING_AWAY, "server shutting down").await;
+            rooms.store(&room).await;
+        }
+        // give the close frames a moment to reach the clients
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// The games of all rooms by room name.
+pub type Snapshot = BTreeMap<String, common::game::State>;
+
+fn write_snapshot(games: &Snapshot, path: &Path) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(games)?;
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, json)?;
+    std::fs::rename(&temporary, path)
+}
+
+/// Load the games written by the last shutdown. The connection ids of the old
+/// process mean nothing anymore, so every player starts out disconnected and
+/// gets their seat back by joining with the same name. Bots keep theirs.
+pub fn read_snapshot(path: &Path) -> std::io::Result<Snapshot> {
+    let json = std::fs::read_to_string(path)?;
+    let mut games: Snapshot = serde_json::from_str(&json)?;
+    for state in games.values_mut() {
+        for player in state.players.iter_mut().filter(|p| p.bot.is_none()) {
+            player.id = None;
+        }
+    }
+    Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::{Action, Difficulty, Library};
+
+    #[test]
+    fn snapshot_round_trip_disconnects_players() {
+        let mut state = common::game::State::new(Library::default());
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        state.action(&Action::AddBot {
+            id: 1,
+            difficulty: Difficulty::Easy,
+        });
+        state.action(&Action::Start { id: 1 });
+        let mut games = Snapshot::new();
+        games.insert("stammtisch".to_string(), state);
+        games.insert(
+            "empty".to_string(),
+            common::game::State::new(Library::default()),
+        );
+        let path =
+            std::env::temp_dir().join(format!("beunique-snapshot-{}.json", std::process::id()));
+        write_snapshot(&games, &path).unwrap();
+        let restored = read_snapshot(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.len(), 2);
+        let restored = &restored["stammtisch"];
+        assert_eq!(restored.players.len(), 3);
+        let connected: Vec<&str> = restored
+            .players
+            .iter()
+            .filter(|p| p.id.is_some())
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(connected, ["Bot 1"]);
+        assert!(matches!(restored.phase, GamePhase::HintCollection(_)));
+    }
+
+    #[tokio::test]
+    async fn drain_without_game_closes_immediately() {
+        let shutdown = Shutdown::default();
+        let rooms = Rooms::new(Library::default(), None, None, None, 10);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        rooms.enter("default", 1, tx).await.unwrap();
+        shutdown.drain(&rooms, Duration::from_secs(60), None).await;
+        assert!(shutdown.is_draining());
+        let announcement = rx.recv().await.unwrap().unwrap();
+        assert!(announcement.to_str().unwrap().contains("ShuttingDown"));
+        let close = rx.recv().await.unwrap().unwrap();
+        assert_eq!(
+            close.close_frame().map(|(code, _)| code),
+            Some(CLOSE_GOING_AWAY)
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! Writes the JSON Schema of the messages into the directory given as the
+//! only argument, or prints them all as one object without an argument.
+
+use std::path::PathBuf;
+
+fn main() {
+    let schemas = common::game::schemas();
+    let directory = match std::env::args().nth(1) {
+        Some(directory) => PathBuf::from(directory),
+        None => {
+            let all: serde_json::Map<_, _> = schemas
+                .iter()
+                .map(|(name, schema)| (name.to_string(), serde_json::to_value(schema).unwrap()))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&all).unwrap());
+            return;
+        }
+    };
+    for (name, schema) in schemas.iter() {
+        let path = directory.join(format!("{}.schema.json", name));
+        let json = serde_json::to_string_pretty(schema).unwrap() + "\n";
+        if let Err(e) = std::fs::write(&path, json) {
+            eprintln!("couldn't write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+        println!("wrote {}", path.display());
+    }
+}
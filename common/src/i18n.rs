@@ -0,0 +1,488 @@
+//! The texts of the clients in every supported language.
+//!
+//! Every text is a variant of `Text` and every language has a function which
+//! turns it into a string, so a missing translation doesn't compile.
+
+use crate::game::{Difficulty, FlagReason, Notice, PackInfo, RejectReason};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    German,
+    English,
+}
+
+/// CLDR plural categories, German and English only use these two.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Plural {
+    One,
+    Other,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::German, Language::English];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::German => "de",
+            Language::English => "en",
+        }
+    }
+
+    /// Accepts language tags like "de", "de-CH" or "en_US".
+    pub fn from_code(code: &str) -> Option<Self> {
+        let primary = code.split(['-', '_']).next()?.to_ascii_lowercase();
+        Language::ALL
+            .iter()
+            .copied()
+            .find(|language| language.code() == primary)
+    }
+
+    /// The name of the language in the language itself, for the language menu.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::German => "Deutsch",
+            Language::English => "English",
+        }
+    }
+
+    pub fn plural(self, n: usize) -> Plural {
+        match self {
+            Language::German | Language::English => {
+                if n == 1 {
+                    Plural::One
+                } else {
+                    Plural::Other
+                }
+            }
+        }
+    }
+
+    pub fn text(self, text: Text) -> String {
+        match self {
+            Language::German => german(text),
+            Language::English => english(text),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Text {
+    Language,
+    Connect,
+    ConnectNow,
+    Ok,
+    ReconnectIn {
+        seconds: u64,
+    },
+    Reconnecting,
+    ShuttingDown {
+        grace_seconds: u64,
+    },
+    Restarting,
+    /// The seat couldn't be taken again after reconnecting.
+    SeatTaken {
+        name: String,
+    },
+    Rejected(RejectReason),
+    Notice(Notice),
+    MyName,
+    ExplicitlyDisconnected,
+    Start,
+    Hint,
+    Delete,
+    Valid,
+    Invalid,
+    FinishFiltering,
+    MyGuess,
+    NoIdea,
+    Correct,
+    Wrong,
+    NoGuess,
+    FinishRound,
+    Leave,
+    Me,
+    ConnectionLost,
+    PlayersHeading,
+    NobodyHere,
+    Summary {
+        successes: usize,
+        rounds: usize,
+    },
+    Guessed {
+        name: String,
+        word: String,
+    },
+    NotGuessed {
+        name: String,
+        word: String,
+    },
+    History,
+    NotStarted,
+    NotJoined,
+    HintsReceived(usize),
+    YourHint {
+        hint: String,
+        word: String,
+    },
+    GiveHint {
+        active_player: String,
+        word: String,
+    },
+    HintsLeft(usize),
+    WhichHintsValid {
+        word: String,
+    },
+    TheHintsAre,
+    WhichWord,
+    WaitingForGuess {
+        active_player: String,
+    },
+    TheWordWas {
+        word: String,
+    },
+    HintsHeading,
+    Packs,
+    Pack(PackInfo),
+    CustomWords,
+    AddCustomWord,
+    CustomWordsInGame(usize),
+    FlagWord,
+    FlagReason(FlagReason),
+    AddBot(Difficulty),
+    RemoveBot(String),
+    Bot(Difficulty),
+    /// The key bindings of the terminal client.
+    Keys,
+}
+
+fn german_difficulty(difficulty: Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Easy => "leicht",
+        Difficulty::Medium => "mittel",
+        Difficulty::Hard => "schwer",
+    }
+}
+
+fn english_difficulty(difficulty: Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Easy => "easy",
+        Difficulty::Medium => "medium",
+        Difficulty::Hard => "hard",
+    }
+}
+
+/// Name, then language, category, difficulty, size and the words not played
+/// yet, if there are any which were, in parentheses.
+fn describe_pack(pack: PackInfo, difficulty: &str, size: String, unused: String) -> String {
+    let mut details = vec![pack.language];
+    details.extend(pack.category);
+    if pack.difficulty.is_some() {
+        details.push(difficulty.to_string());
+    }
+    details.push(size);
+    if pack.unused < pack.size {
+        details.push(unused);
+    }
+    format!("{} ({})", pack.name, details.join(", "))
+}
+
+fn german(text: Text) -> String {
+    let plural = |n| Language::German.plural(n);
+    match text {
+        Text::Language => "Sprache".to_string(),
+        Text::Connect => "Verbinden".to_string(),
+        Text::ConnectNow => "Jetzt verbinden".to_string(),
+        Text::Ok => "OK".to_string(),
+        Text::ReconnectIn { seconds } => match plural(seconds as usize) {
+            Plural::One => "Die Verbindung ist unterbrochen, neuer Versuch in 1 Sekunde.".to_string(),
+            Plural::Other => format!(
+                "Die Verbindung ist unterbrochen, neuer Versuch in {} Sekunden.",
+                seconds
+            ),
+        },
+        Text::Reconnecting => "Verbindung wird wiederhergestellt …".to_string(),
+        Text::ShuttingDown { grace_seconds } => format!(
+            "Der Server wird für Wartungsarbeiten neu gestartet. Die laufende Runde kann noch bis zu {} Sekunden lang zu Ende gespielt werden.",
+            grace_seconds
+        ),
+        Text::Restarting => "Der Server wird gerade neu gestartet. Sobald er wieder da ist, geht es automatisch weiter.".to_string(),
+        Text::SeatTaken { name } => format!(
+            "Der Platz von {} ist noch besetzt, bitte unter einem anderen Namen mitspielen.",
+            name
+        ),
+        Text::Rejected(reason) => match reason {
+            RejectReason::RateLimited => "Zu viele Eingaben auf einmal, bitte etwas langsamer.",
+            RejectReason::TooLarge | RejectReason::Malformed => {
+                "Die Eingabe konnte nicht verarbeitet werden."
+            }
+            RejectReason::TooLong => "Die Eingabe ist zu lang.",
+            RejectReason::ShuttingDown => {
+                "Der Server wird gleich neu gestartet, bis dahin beginnt kein neues Spiel."
+            }
+        }
+        .to_string(),
+        Text::Notice(notice) => match notice {
+            Notice::Kicked => "Du wurdest aus dem Spiel entfernt.".to_string(),
+            Notice::GameClosed => "Das Spiel wurde beendet.".to_string(),
+            Notice::Message(message) => message,
+        },
+        Text::MyName => "Mein Name: ".to_string(),
+        Text::ExplicitlyDisconnected => {
+            "Das sollte nicht passieren (Verbindung explizit getrennt). Bitte neu laden."
+                .to_string()
+        }
+        Text::Start => "Start".to_string(),
+        Text::Hint => "Hinweis: ".to_string(),
+        Text::Delete => "Löschen".to_string(),
+        Text::Valid => " gültig ".to_string(),
+        Text::Invalid => " ungültig ".to_string(),
+        Text::FinishFiltering => "Hinweisbeurteilung abschliessen".to_string(),
+        Text::MyGuess => "Ich rate: ".to_string(),
+        Text::NoIdea => "Keine Ahnung".to_string(),
+        Text::Correct => " richtig".to_string(),
+        Text::Wrong => " falsch".to_string(),
+        Text::NoGuess => "Es wurde nicht geraten.".to_string(),
+        Text::FinishRound => "Runde abschliessen".to_string(),
+        Text::Leave => "Spiel verlassen".to_string(),
+        Text::Me => " (ich)".to_string(),
+        Text::ConnectionLost => " (Verbindung verloren)".to_string(),
+        Text::PlayersHeading => "Es spielen mit:".to_string(),
+        Text::NobodyHere => "Es ist noch niemand hier.".to_string(),
+        Text::Summary { successes, rounds } => match plural(rounds) {
+            Plural::One => format!("Es wurde in {} von 1 Runde richtig geraten.", successes),
+            Plural::Other => format!(
+                "Es wurde in {} von {} Runden richtig geraten.",
+                successes, rounds
+            ),
+        },
+        Text::Guessed { name, word } => format!("{} hat \"{}\" erraten.", name, word),
+        Text::NotGuessed { name, word } => format!("{} hat \"{}\" nicht erraten.", name, word),
+        Text::History => "Was bisher geschah: ".to_string(),
+        Text::NotStarted => "Das Spiel hat noch nicht angefangen.".to_string(),
+        Text::NotJoined => "Noch nicht angemeldet.".to_string(),
+        Text::HintsReceived(0) => "Es sind noch keine Hinweise eingegangen.".to_string(),
+        Text::HintsReceived(n) => match plural(n) {
+            Plural::One => "Es ist schon 1 Hinweis eingegangen.".to_string(),
+            Plural::Other => format!("Es sind schon {} Hinweise eingegangen.", n),
+        },
+        Text::YourHint { hint, word } => {
+            format!("Du gibst den Hinweis \"{}\" für \"{}\".", hint, word)
+        }
+        Text::GiveHint {
+            active_player,
+            word,
+        } => format!(
+            "Bitte gib {} einen Hinweis für \"{}\".",
+            active_player, word
+        ),
+        Text::HintsLeft(n) => match plural(n) {
+            Plural::One => "Es werden doppelte Hinweise entfernt, aktuell ist 1 übrig.".to_string(),
+            Plural::Other => format!(
+                "Es werden doppelte Hinweise entfernt, aktuell sind {} übrig.",
+                n
+            ),
+        },
+        Text::WhichHintsValid { word } => format!("Welche Hinweise für \"{}\" sind gültig?", word),
+        Text::TheHintsAre => "Die Hinweise sind:".to_string(),
+        Text::WhichWord => "Welches Wort ist gesucht?".to_string(),
+        Text::WaitingForGuess { active_player } => {
+            format!("Wir warten bis {} geraten hat.", active_player)
+        }
+        Text::TheWordWas { word } => format!("Das gesuchte Wort war \"{}\".", word),
+        Text::HintsHeading => "Hinweise".to_string(),
+        Text::Packs => "Wortlisten:".to_string(),
+        Text::Pack(pack) => {
+            let difficulty = german_difficulty(pack.difficulty.unwrap_or(Difficulty::Hard));
+            let size = match plural(pack.size) {
+                Plural::One => "1 Wort".to_string(),
+                Plural::Other => format!("{} Wörter", pack.size),
+            };
+            let unused = format!("davon {} neu", pack.unused);
+            describe_pack(pack, difficulty, size, unused)
+        }
+        Text::CustomWords => {
+            "Eigene Wörter, nur du siehst sie und du musst sie nicht raten:".to_string()
+        }
+        Text::AddCustomWord => "Neues Wort: ".to_string(),
+        Text::CustomWordsInGame(n) => match plural(n) {
+            Plural::One => "Insgesamt ist 1 eigenes Wort im Spiel.".to_string(),
+            Plural::Other => format!("Insgesamt sind {} eigene Wörter im Spiel.", n),
+        },
+        Text::FlagWord => "Das Wort melden: ".to_string(),
+        Text::FlagReason(reason) => match reason {
+            FlagReason::Unknown => "unbekannt",
+            FlagReason::Offensive => "anstößig",
+            FlagReason::Unguessable => "nicht zu erraten",
+        }
+        .to_string(),
+        Text::AddBot(difficulty) => format!("Bot dazu ({})", german_difficulty(difficulty)),
+        Text::RemoveBot(name) => format!("{} entfernen", name),
+        Text::Bot(difficulty) => format!(" (Bot, {})", german_difficulty(difficulty)),
+        Text::Keys => {
+            "↑/↓ auswählen · Enter bestätigen · Leertaste ankreuzen · F2 Sprache · F5 jetzt verbinden · Esc beenden"
+                .to_string()
+        }
+    }
+}
+
+fn english(text: Text) -> String {
+    let plural = |n| Language::English.plural(n);
+    match text {
+        Text::Language => "Language".to_string(),
+        Text::Connect => "Connect".to_string(),
+        Text::ConnectNow => "Connect now".to_string(),
+        Text::Ok => "OK".to_string(),
+        Text::ReconnectIn { seconds } => match plural(seconds as usize) {
+            Plural::One => "The connection was interrupted, trying again in 1 second.".to_string(),
+            Plural::Other => format!(
+                "The connection was interrupted, trying again in {} seconds.",
+                seconds
+            ),
+        },
+        Text::Reconnecting => "Reconnecting …".to_string(),
+        Text::ShuttingDown { grace_seconds } => format!(
+            "The server is restarting for maintenance. The current round can be finished within the next {} seconds.",
+            grace_seconds
+        ),
+        Text::Restarting => "The server is restarting. The game continues as soon as it is back.".to_string(),
+        Text::SeatTaken { name } => format!(
+            "The seat of {} is still taken, please join under another name.",
+            name
+        ),
+        Text::Rejected(reason) => match reason {
+            RejectReason::RateLimited => "Too much input at once, please slow down.",
+            RejectReason::TooLarge | RejectReason::Malformed => "The input couldn't be processed.",
+            RejectReason::TooLong => "The input is too long.",
+            RejectReason::ShuttingDown => {
+                "The server is about to restart, no new game starts until then."
+            }
+        }
+        .to_string(),
+        Text::Notice(notice) => match notice {
+            Notice::Kicked => "You were removed from the game.".to_string(),
+            Notice::GameClosed => "The game was closed.".to_string(),
+            Notice::Message(message) => message,
+        },
+        Text::MyName => "My name: ".to_string(),
+        Text::ExplicitlyDisconnected => {
+            "This shouldn't happen (explicitly disconnected). Please reload.".to_string()
+        }
+        Text::Start => "Start".to_string(),
+        Text::Hint => "Hint: ".to_string(),
+        Text::Delete => "Delete".to_string(),
+        Text::Valid => " valid ".to_string(),
+        Text::Invalid => " invalid ".to_string(),
+        Text::FinishFiltering => "Done checking hints".to_string(),
+        Text::MyGuess => "My guess: ".to_string(),
+        Text::NoIdea => "No idea".to_string(),
+        Text::Correct => " correct".to_string(),
+        Text::Wrong => " wrong".to_string(),
+        Text::NoGuess => "There was no guess.".to_string(),
+        Text::FinishRound => "Finish round".to_string(),
+        Text::Leave => "Leave game".to_string(),
+        Text::Me => " (me)".to_string(),
+        Text::ConnectionLost => " (connection lost)".to_string(),
+        Text::PlayersHeading => "Playing:".to_string(),
+        Text::NobodyHere => "Nobody is here yet.".to_string(),
+        Text::Summary { successes, rounds } => match plural(rounds) {
+            Plural::One => format!("The word was guessed in {} of 1 round.", successes),
+            Plural::Other => format!(
+                "The word was guessed in {} of {} rounds.",
+                successes, rounds
+            ),
+        },
+        Text::Guessed { name, word } => format!("{} guessed \"{}\".", name, word),
+        Text::NotGuessed { name, word } => format!("{} didn't guess \"{}\".", name, word),
+        Text::History => "What happened so far: ".to_string(),
+        Text::NotStarted => "The game hasn't started yet.".to_string(),
+        Text::NotJoined => "Not signed in yet.".to_string(),
+        Text::HintsReceived(0) => "No hints have arrived yet.".to_string(),
+        Text::HintsReceived(n) => match plural(n) {
+            Plural::One => "1 hint has arrived.".to_string(),
+            Plural::Other => format!("{} hints have arrived.", n),
+        },
+        Text::YourHint { hint, word } => {
+            format!("Your hint for \"{}\" is \"{}\".", word, hint)
+        }
+        Text::GiveHint {
+            active_player,
+            word,
+        } => format!(
+            "Please give {} a hint for \"{}\".",
+            active_player, word
+        ),
+        Text::HintsLeft(n) => match plural(n) {
+            Plural::One => "Duplicate hints are being removed, 1 is left.".to_string(),
+            Plural::Other => format!("Duplicate hints are being removed, {} are left.", n),
+        },
+        Text::WhichHintsValid { word } => format!("Which hints for \"{}\" are valid?", word),
+        Text::TheHintsAre => "The hints are:".to_string(),
+        Text::WhichWord => "Which word are we looking for?".to_string(),
+        Text::WaitingForGuess { active_player } => {
+            format!("Waiting for {} to guess.", active_player)
+        }
+        Text::TheWordWas { word } => format!("The word was \"{}\".", word),
+        Text::HintsHeading => "Hints".to_string(),
+        Text::Packs => "Word packs:".to_string(),
+        Text::Pack(pack) => {
+            let difficulty = english_difficulty(pack.difficulty.unwrap_or(Difficulty::Hard));
+            let size = match plural(pack.size) {
+                Plural::One => "1 word".to_string(),
+                Plural::Other => format!("{} words", pack.size),
+            };
+            let unused = format!("{} not played yet", pack.unused);
+            describe_pack(pack, difficulty, size, unused)
+        }
+        Text::CustomWords => {
+            "Your own words, only you see them and you won't have to guess them:".to_string()
+        }
+        Text::AddCustomWord => "New word: ".to_string(),
+        Text::CustomWordsInGame(n) => match plural(n) {
+            Plural::One => "There is 1 custom word in the game.".to_string(),
+            Plural::Other => format!("There are {} custom words in the game.", n),
+        },
+        Text::FlagWord => "Report the word: ".to_string(),
+        Text::FlagReason(reason) => match reason {
+            FlagReason::Unknown => "unknown",
+            FlagReason::Offensive => "offensive",
+            FlagReason::Unguessable => "unguessable",
+        }
+        .to_string(),
+        Text::AddBot(difficulty) => format!("Add a bot ({})", english_difficulty(difficulty)),
+        Text::RemoveBot(name) => format!("Remove {}", name),
+        Text::Bot(difficulty) => format!(" (bot, {})", english_difficulty(difficulty)),
+        Text::Keys => {
+            "↑/↓ select · Enter confirm · Space tick · F2 language · F5 connect now · Esc quit"
+                .to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_tags_are_matched_by_primary_subtag() {
+        assert_eq!(Language::from_code("de-CH"), Some(Language::German));
+        assert_eq!(Language::from_code("EN_us"), Some(Language::English));
+        assert_eq!(Language::from_code("fr"), None);
+    }
+
+    #[test]
+    fn counts_are_pluralized() {
+        for language in Language::ALL.iter() {
+            let one = language.text(Text::HintsReceived(1));
+            let two = language.text(Text::HintsReceived(2));
+            assert!(one.contains('1') && two.contains('2'));
+            assert_ne!(one.replace('1', ""), two.replace('2', ""));
+        }
+        assert_eq!(
+            Language::German.text(Text::Summary {
+                successes: 0,
+                rounds: 1
+            }),
+            "Es wurde in 0 von 1 Runde richtig geraten."
+        );
+    }
+}
@@ -1,11 +1,40 @@
 pub mod game {
+    //! Everything the server and the clients exchange is JSON made by serde
+    //! from the types here, and third-party clients may rely on it:
+    //!
+    //! - `Action` and `ServerMessage` are objects with the variant in `type`
+    //!   and named fields next to it (`{"type":"Join","id":1,"name":"a"}`,
+    //!   `{"type":"ShuttingDown","grace_seconds":30}`),
+    //! - other enums are externally tagged: a unit variant is its name as a
+    //!   string (`"GatherPlayers"`), any other variant an object with the name
+    //!   as its only key holding the value (`{"Message":"Gleich geht es los"}`),
+    //! - struct fields keep their names, `Option`s are `null` when empty and
+    //!   maps of hints are objects keyed by the author,
+    //! - `Difficulty` is lowercase.
+    //!
+    //! The files in `common/snapshots` show every `Action`, every message of
+    //! the server and a view of every phase for both roles. The tests fail if
+    //! the encoding changes, so a rename has to be a deliberate decision which
+    //! updates the snapshots with `UPDATE_SNAPSHOTS=1 cargo test -p common`.
+    //!
+    //! `common/schema` has the JSON Schema of `Action`, which the clients
+    //! send, and of `ServerMessage`, which the server sends. It is written by
+    //! `cargo run -p common --features schema --bin schema -- common/schema`.
+
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
     use serde::{Deserialize, Serialize};
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+    use tracing::{debug, info};
 
     #[derive(Eq, PartialEq, Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct Player {
         pub id: Option<usize>,
         pub name: String,
+        /// Set for players the server plays, how hard their hints are.
+        #[serde(default)]
+        pub bot: Option<Difficulty>,
     }
 
     #[derive(Eq, PartialEq, Serialize, Deserialize, Debug)]
@@ -14,21 +43,182 @@ pub mod game {
         pub new_value: i64,
     }
 
-    #[derive(Eq, PartialEq, Serialize, Deserialize, Debug)]
+    #[derive(Eq, PartialEq, Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    #[serde(tag = "type")]
     pub enum Action {
-        Join(usize, String),
-        DisconnectPlayer(usize),
-        Start(usize),
-        GiveHint(usize, Option<String>),
-        FilterHint(usize, String, bool),
-        FinishHintFiltering(usize),
-        Guess(usize, Option<String>),
-        Judge(usize, bool),
-        FinishJudging(usize),
-        Leave(usize),
+        Join {
+            id: usize,
+            name: String,
+        },
+        DisconnectPlayer {
+            id: usize,
+        },
+        Start {
+            id: usize,
+        },
+        /// No hint takes back the one given before.
+        GiveHint {
+            id: usize,
+            hint: Option<String>,
+        },
+        /// Mark every hint with this content as allowed or not.
+        FilterHint {
+            id: usize,
+            hint: String,
+            allowed: bool,
+        },
+        FinishHintFiltering {
+            id: usize,
+        },
+        /// No guess is giving up.
+        Guess {
+            id: usize,
+            guess: Option<String>,
+        },
+        /// Count a guess which wasn't exactly the word as correct or not.
+        Judge {
+            id: usize,
+            correct: bool,
+        },
+        FinishJudging {
+            id: usize,
+        },
+        Leave {
+            id: usize,
+        },
+        /// Play with these word packs, only in the lobby.
+        SelectPacks {
+            id: usize,
+            packs: Vec<String>,
+        },
+        /// Replace the player's secret words which are mixed into the deck,
+        /// only in the lobby.
+        SubmitWords {
+            id: usize,
+            words: Vec<String>,
+        },
+        /// Report the current word to whoever curates the word packs.
+        FlagWord {
+            id: usize,
+            reason: FlagReason,
+        },
+        /// Seat a bot, only in the lobby.
+        AddBot {
+            id: usize,
+            difficulty: Difficulty,
+        },
+        /// Take the bot with this name out of the game, only in the lobby.
+        RemoveBot {
+            id: usize,
+            name: String,
+        },
+    }
+
+    /// Why a word shouldn't be in a pack.
+    #[derive(Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub enum FlagReason {
+        /// Nobody knew the word.
+        Unknown,
+        Offensive,
+        /// The word can't be guessed from single-word hints.
+        Unguessable,
+    }
+
+    impl FlagReason {
+        pub const ALL: [FlagReason; 3] = [
+            FlagReason::Unknown,
+            FlagReason::Offensive,
+            FlagReason::Unguessable,
+        ];
+    }
+
+    /// A flagged word the server hasn't stored yet.
+    #[derive(Eq, PartialEq, Serialize, Deserialize, Debug, Clone)]
+    pub struct WordFlag {
+        pub word: String,
+        /// The pack the word came from, none for custom words.
+        pub pack: Option<String>,
+        pub reason: FlagReason,
+        pub player: String,
+    }
+
+    impl Action {
+        /// Name of the variant, for logging and metrics.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Action::Join { .. } => "Join",
+                Action::DisconnectPlayer { .. } => "DisconnectPlayer",
+                Action::Start { .. } => "Start",
+                Action::GiveHint { .. } => "GiveHint",
+                Action::FilterHint { .. } => "FilterHint",
+                Action::FinishHintFiltering { .. } => "FinishHintFiltering",
+                Action::Guess { .. } => "Guess",
+                Action::Judge { .. } => "Judge",
+                Action::FinishJudging { .. } => "FinishJudging",
+                Action::Leave { .. } => "Leave",
+                Action::SelectPacks { .. } => "SelectPacks",
+                Action::SubmitWords { .. } => "SubmitWords",
+                Action::FlagWord { .. } => "FlagWord",
+                Action::AddBot { .. } => "AddBot",
+                Action::RemoveBot { .. } => "RemoveBot",
+            }
+        }
+
+        /// The same action on behalf of the connection `new_id`, for clients
+        /// which replay actions after reconnecting.
+        pub fn with_id(mut self, new_id: usize) -> Self {
+            use Action::*;
+            match &mut self {
+                Join { id, .. }
+                | DisconnectPlayer { id }
+                | Start { id }
+                | GiveHint { id, .. }
+                | FilterHint { id, .. }
+                | FinishHintFiltering { id }
+                | Guess { id, .. }
+                | Judge { id, .. }
+                | FinishJudging { id }
+                | Leave { id }
+                | SelectPacks { id, .. }
+                | SubmitWords { id, .. }
+                | FlagWord { id, .. }
+                | AddBot { id, .. }
+                | RemoveBot { id, .. } => *id = new_id,
+            }
+            self
+        }
+
+        /// A copy of the action with hints, guesses and custom words replaced
+        /// by `REDACTED`.
+        pub fn redacted(&self) -> Self {
+            let redact = |content: &Option<String>| content.as_ref().map(|_| REDACTED.to_string());
+            match self {
+                Action::GiveHint { id, hint } => Action::GiveHint {
+                    id: *id,
+                    hint: redact(hint),
+                },
+                Action::FilterHint { id, allowed, .. } => Action::FilterHint {
+                    id: *id,
+                    hint: REDACTED.to_string(),
+                    allowed: *allowed,
+                },
+                Action::Guess { id, guess } => Action::Guess {
+                    id: *id,
+                    guess: redact(guess),
+                },
+                Action::SubmitWords { id, words } => Action::SubmitWords {
+                    id: *id,
+                    words: words.iter().map(|_| REDACTED.to_string()).collect(),
+                },
+                other => other.clone(),
+            }
+        }
     }
 
     #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct Hint {
         pub content: String,
         pub allowed: bool,
@@ -41,10 +231,12 @@ pub mod game {
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct ActiveHintCollection {
         pub players_done: Vec<String>,
     }
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct InactiveHintCollection {
         pub active_player: String,
         pub word: String,
@@ -52,35 +244,41 @@ pub mod game {
         pub players_done: Vec<String>,
     }
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub enum VisibleHintCollection {
         Active(ActiveHintCollection),
         Inactive(InactiveHintCollection),
     }
 
     #[derive(Serialize, Deserialize, Debug, Default, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct HintFiltering {
         pub word: String,
         pub hints: HashMap<String, Hint>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct ActiveHintFiltering {
         pub players_valid_hints: Vec<String>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct InactiveHintFiltering {
         pub active_player: String,
         pub hint_filtering: HintFiltering,
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub enum VisibleHintFiltering {
         Active(ActiveHintFiltering),
         Inactive(InactiveHintFiltering),
     }
 
     #[derive(Serialize, Deserialize, Debug, Default, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct Guessing {
         word: String,
         hints: HashMap<String, Hint>,
@@ -88,6 +286,7 @@ pub mod game {
     }
 
     #[derive(Serialize, Deserialize, Debug, Default, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct VisibleHint(String);
 
     impl std::fmt::Display for VisibleHint {
@@ -110,23 +309,27 @@ pub mod game {
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct ActiveGuessing {
         pub hints: HashMap<String, VisibleHint>,
         pub guess: Option<String>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct InactiveGuessing {
         pub active_player: String,
         pub guessing: Guessing,
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub enum VisibleGuessing {
         Active(ActiveGuessing),
         Inactive(InactiveGuessing),
     }
     #[derive(Serialize, Deserialize, Debug, Default, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct Judging {
         pub word: String,
         pub hints: HashMap<String, Hint>,
@@ -134,12 +337,14 @@ pub mod game {
         pub success: Option<bool>,
     }
     #[derive(Serialize, Deserialize, Debug, Default, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct InactiveJudging {
         pub active_player: String,
         pub judging: Judging,
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub enum VisibleJudging {
         Active(Judging),
         Inactive(InactiveJudging),
@@ -160,7 +365,273 @@ pub mod game {
         }
     }
 
+    impl GamePhase {
+        /// Name of the variant, for logging.
+        pub fn name(&self) -> &'static str {
+            match self {
+                GamePhase::GatherPlayers => "GatherPlayers",
+                GamePhase::HintCollection(_) => "HintCollection",
+                GamePhase::HintFiltering(_) => "HintFiltering",
+                GamePhase::Guessing(_) => "Guessing",
+                GamePhase::Judging(_) => "Judging",
+            }
+        }
+
+        /// The word of the current round.
+        pub fn word(&self) -> Option<&str> {
+            match self {
+                GamePhase::GatherPlayers => None,
+                GamePhase::HintCollection(HintCollection { word, .. })
+                | GamePhase::HintFiltering(HintFiltering { word, .. })
+                | GamePhase::Guessing(Guessing { word, .. })
+                | GamePhase::Judging(Judging { word, .. }) => Some(word),
+            }
+        }
+
+        /// The hints of the current round by author.
+        pub fn hints(&self) -> Option<&HashMap<String, Hint>> {
+            match self {
+                GamePhase::GatherPlayers => None,
+                GamePhase::HintCollection(HintCollection { hints, .. })
+                | GamePhase::HintFiltering(HintFiltering { hints, .. })
+                | GamePhase::Guessing(Guessing { hints, .. })
+                | GamePhase::Judging(Judging { hints, .. }) => Some(hints),
+            }
+        }
+    }
+
+    /// Placeholder for secret content in redacted output.
+    pub const REDACTED: &str = "<redacted>";
+
+    fn redact_hints(hints: &HashMap<String, Hint>) -> HashMap<String, Hint> {
+        hints
+            .iter()
+            .map(|(author, hint)| {
+                (
+                    author.clone(),
+                    Hint {
+                        content: REDACTED.to_string(),
+                        allowed: hint.allowed,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    impl GamePhase {
+        /// A copy of the phase with the word, the hints and the guess replaced by `REDACTED`,
+        /// the authors and the filtering/judging decisions are kept.
+        pub fn redacted(&self) -> Self {
+            let redacted_word = || REDACTED.to_string();
+            let redacted_guess = |guess: &Option<String>| guess.as_ref().map(|_| redacted_word());
+            match self {
+                GamePhase::GatherPlayers => GamePhase::GatherPlayers,
+                GamePhase::HintCollection(HintCollection { word: _, hints }) => {
+                    GamePhase::HintCollection(HintCollection {
+                        word: redacted_word(),
+                        hints: redact_hints(hints),
+                    })
+                }
+                GamePhase::HintFiltering(HintFiltering { word: _, hints }) => {
+                    GamePhase::HintFiltering(HintFiltering {
+                        word: redacted_word(),
+                        hints: redact_hints(hints),
+                    })
+                }
+                GamePhase::Guessing(Guessing {
+                    word: _,
+                    hints,
+                    guess,
+                }) => GamePhase::Guessing(Guessing {
+                    word: redacted_word(),
+                    hints: redact_hints(hints),
+                    guess: redacted_guess(guess),
+                }),
+                GamePhase::Judging(Judging {
+                    word: _,
+                    hints,
+                    guess,
+                    success,
+                }) => GamePhase::Judging(Judging {
+                    word: redacted_word(),
+                    hints: redact_hints(hints),
+                    guess: redacted_guess(guess),
+                    success: *success,
+                }),
+            }
+        }
+    }
+
+    /// Who looks at the round: the player who guesses or one of the others.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Role {
+        Active,
+        Inactive,
+    }
+
+    /// How much of the hints a role sees.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum HintSight {
+        /// Who gave a hint and whether it is allowed, not what it says.
+        Authors,
+        /// Like `Authors`, but the own hint in full.
+        Own,
+        /// The allowed hints in full, the others not at all.
+        Allowed,
+        All,
+    }
+
+    /// What a role sees of the current round.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Visibility {
+        pub word: bool,
+        pub hints: HintSight,
+        pub guess: bool,
+    }
+
+    impl GamePhase {
+        /// Who sees what. Views only show what `seen_by` leaves of the phase,
+        /// so these rules are the one place which decides it.
+        pub fn visibility(&self, role: Role) -> Visibility {
+            let (word, hints, guess) = match (self, role) {
+                (GamePhase::GatherPlayers, _) => (false, HintSight::Authors, false),
+                (GamePhase::HintCollection(_), Role::Active)
+                | (GamePhase::HintFiltering(_), Role::Active) => (false, HintSight::Authors, false),
+                (GamePhase::Guessing(_), Role::Active) => (false, HintSight::Allowed, false),
+                (GamePhase::HintCollection(_), Role::Inactive) => (true, HintSight::Own, false),
+                (GamePhase::HintFiltering(_), Role::Inactive)
+                | (GamePhase::Guessing(_), Role::Inactive)
+                | (GamePhase::Judging(_), _) => (true, HintSight::All, true),
+            };
+            Visibility { word, hints, guess }
+        }
+
+        /// A copy of the phase without anything `role` mustn't see, `me` is the
+        /// name of whoever looks. Hidden words and hints are left empty.
+        pub fn seen_by(&self, role: Role, me: &str) -> Self {
+            let visibility = self.visibility(role);
+            let word = |word: &String| {
+                if visibility.word {
+                    word.clone()
+                } else {
+                    String::new()
+                }
+            };
+            let hints = |hints: &HashMap<String, Hint>| {
+                hints
+                    .iter()
+                    .filter_map(|(author, hint)| {
+                        let content = match visibility.hints {
+                            HintSight::All => hint.content.clone(),
+                            HintSight::Own if author == me => hint.content.clone(),
+                            HintSight::Allowed if hint.allowed => hint.content.clone(),
+                            HintSight::Allowed => return None,
+                            HintSight::Authors | HintSight::Own => String::new(),
+                        };
+                        let allowed = hint.allowed;
+                        Some((author.clone(), Hint { content, allowed }))
+                    })
+                    .collect()
+            };
+            let guess = |guess: &Option<String>| guess.clone().filter(|_| visibility.guess);
+            match self {
+                GamePhase::GatherPlayers => GamePhase::GatherPlayers,
+                GamePhase::HintCollection(phase) => GamePhase::HintCollection(HintCollection {
+                    word: word(&phase.word),
+                    hints: hints(&phase.hints),
+                }),
+                GamePhase::HintFiltering(phase) => GamePhase::HintFiltering(HintFiltering {
+                    word: word(&phase.word),
+                    hints: hints(&phase.hints),
+                }),
+                GamePhase::Guessing(phase) => GamePhase::Guessing(Guessing {
+                    word: word(&phase.word),
+                    hints: hints(&phase.hints),
+                    guess: guess(&phase.guess),
+                }),
+                GamePhase::Judging(phase) => GamePhase::Judging(Judging {
+                    word: word(&phase.word),
+                    hints: hints(&phase.hints),
+                    guess: guess(&phase.guess),
+                    success: phase.success,
+                }),
+            }
+        }
+
+        /// What `role` gets shown of a phase which went through `seen_by`.
+        fn into_visible(self, role: Role, me: &str, active_player: String) -> VisibleGamePhase {
+            // sorted, so the same game is always sent the same way
+            let allowed = |hints: &HashMap<String, Hint>| {
+                let mut authors: Vec<String> = hints
+                    .iter()
+                    .filter(|(_, hint)| hint.allowed)
+                    .map(|(author, _)| author.clone())
+                    .collect();
+                authors.sort();
+                authors
+            };
+            match (self, role) {
+                (GamePhase::GatherPlayers, _) => VisibleGamePhase::GatherPlayers,
+                (GamePhase::HintCollection(HintCollection { word, hints }), role) => {
+                    let mut players_done: Vec<String> = hints.keys().cloned().collect();
+                    players_done.sort();
+                    VisibleGamePhase::HintCollection(match role {
+                        Role::Active => {
+                            VisibleHintCollection::Active(ActiveHintCollection { players_done })
+                        }
+                        Role::Inactive => VisibleHintCollection::Inactive(InactiveHintCollection {
+                            active_player,
+                            word,
+                            hint: hints.get(me).cloned(),
+                            players_done,
+                        }),
+                    })
+                }
+                (GamePhase::HintFiltering(hint_filtering), Role::Active) => {
+                    VisibleGamePhase::HintFiltering(VisibleHintFiltering::Active(
+                        ActiveHintFiltering {
+                            players_valid_hints: allowed(&hint_filtering.hints),
+                        },
+                    ))
+                }
+                (GamePhase::HintFiltering(hint_filtering), Role::Inactive) => {
+                    VisibleGamePhase::HintFiltering(VisibleHintFiltering::Inactive(
+                        InactiveHintFiltering {
+                            active_player,
+                            hint_filtering,
+                        },
+                    ))
+                }
+                (GamePhase::Guessing(Guessing { hints, guess, .. }), Role::Active) => {
+                    VisibleGamePhase::Guessing(VisibleGuessing::Active(ActiveGuessing {
+                        hints: hints
+                            .into_iter()
+                            .map(|(author, hint)| (author, VisibleHint(hint.content)))
+                            .collect(),
+                        guess,
+                    }))
+                }
+                (GamePhase::Guessing(guessing), Role::Inactive) => {
+                    VisibleGamePhase::Guessing(VisibleGuessing::Inactive(InactiveGuessing {
+                        active_player,
+                        guessing,
+                    }))
+                }
+                (GamePhase::Judging(judging), Role::Active) => {
+                    VisibleGamePhase::Judging(VisibleJudging::Active(judging))
+                }
+                (GamePhase::Judging(judging), Role::Inactive) => {
+                    VisibleGamePhase::Judging(VisibleJudging::Inactive(InactiveJudging {
+                        active_player,
+                        judging,
+                    }))
+                }
+            }
+        }
+    }
+
     #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub enum VisibleGamePhase {
         GatherPlayers,
         HintCollection(VisibleHintCollection),
@@ -177,35 +648,300 @@ pub mod game {
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Dictionary {
-        words: Vec<String>,
-        next_index: usize,
+        words: Vec<Word>,
+        /// Words drawn from this dictionary so far, picks the random stream of the next draw.
+        #[serde(default)]
+        draws: u64,
+    }
+
+    /// The words a group has already played, so they don't come up again.
+    #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+    pub struct WordHistory {
+        used: BTreeSet<String>,
+    }
+
+    impl WordHistory {
+        pub fn len(&self) -> usize {
+            self.used.len()
+        }
+        pub fn is_empty(&self) -> bool {
+            self.used.is_empty()
+        }
+        pub fn contains(&self, word: &str) -> bool {
+            self.used.contains(word)
+        }
+        pub fn clear(&mut self) {
+            self.used.clear();
+        }
     }
 
     impl Default for Dictionary {
         fn default() -> Self {
-            Dictionary::new(vec!["Steinbruch".to_string(), "Schwan".to_string()])
+            Dictionary::new(vec!["Steinbruch".into(), "Schwan".into()])
         }
     }
 
+    /// A word to be guessed and what we know about it.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct Word {
+        pub word: String,
+        /// Other spellings which count as guessing the word.
+        #[serde(default)]
+        pub also: Vec<String>,
+        #[serde(default)]
+        pub tags: Vec<String>,
+        /// Overrides the difficulty of the pack.
+        #[serde(default)]
+        pub difficulty: Option<Difficulty>,
+    }
+
+    impl From<&str> for Word {
+        fn from(word: &str) -> Self {
+            Self {
+                word: word.to_string(),
+                also: vec![],
+                tags: vec![],
+                difficulty: None,
+            }
+        }
+    }
+
+    impl Word {
+        /// Whether `guess` is the word or one of its other spellings, ignoring case.
+        pub fn accepts(&self, guess: &str) -> bool {
+            let guess = guess.trim().to_lowercase();
+            std::iter::once(&self.word)
+                .chain(self.also.iter())
+                .any(|spelling| spelling.to_lowercase() == guess)
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    #[serde(rename_all = "lowercase")]
+    pub enum Difficulty {
+        Easy,
+        Medium,
+        Hard,
+    }
+
+    /// What the lobby shows about a word pack.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub struct PackInfo {
+        pub name: String,
+        /// Language code like "de" or "en".
+        pub language: String,
+        pub category: Option<String>,
+        pub difficulty: Option<Difficulty>,
+        /// Number of words.
+        pub size: usize,
+        /// Number of words the group hasn't played yet.
+        #[serde(default)]
+        pub unused: usize,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    pub struct WordPack {
+        pub info: PackInfo,
+        pub words: Vec<Word>,
+    }
+
+    impl WordPack {
+        pub fn new(name: &str, language: &str, words: Vec<Word>) -> Self {
+            Self {
+                info: PackInfo {
+                    name: name.to_string(),
+                    language: language.to_string(),
+                    category: None,
+                    difficulty: None,
+                    size: words.len(),
+                    unused: words.len(),
+                },
+                words,
+            }
+        }
+    }
+
+    /// Every word pack the server offers, by name.
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    pub struct Library {
+        packs: Vec<WordPack>,
+    }
+
+    impl Default for Library {
+        fn default() -> Self {
+            Library::new(vec![WordPack::new(
+                "Standard",
+                "de",
+                vec!["Steinbruch".into(), "Schwan".into()],
+            )])
+        }
+    }
+
+    impl Library {
+        /// Packs without words are left out, later packs with the name of an
+        /// earlier one as well.
+        pub fn new(packs: Vec<WordPack>) -> Self {
+            let mut library = Self { packs: vec![] };
+            for pack in packs {
+                if !pack.words.is_empty() && !library.contains(&pack.info.name) {
+                    library.packs.push(pack);
+                }
+            }
+            library
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.packs.is_empty()
+        }
+
+        pub fn contains(&self, name: &str) -> bool {
+            self.packs.iter().any(|pack| pack.info.name == name)
+        }
+
+        pub fn names(&self) -> Vec<String> {
+            self.packs
+                .iter()
+                .map(|pack| pack.info.name.clone())
+                .collect()
+        }
+
+        pub fn infos(&self) -> Vec<PackInfo> {
+            self.packs.iter().map(|pack| pack.info.clone()).collect()
+        }
+
+        /// The words of the `selected` packs.
+        pub fn deck(&self, selected: &[String]) -> Option<Dictionary> {
+            let words: Vec<Word> = self
+                .packs
+                .iter()
+                .filter(|pack| selected.contains(&pack.info.name))
+                .flat_map(|pack| pack.words.iter().cloned())
+                .collect();
+            if words.is_empty() {
+                None
+            } else {
+                Some(Dictionary::new(words))
+            }
+        }
+
+        /// Name of the first pack containing `word`.
+        pub fn pack_of(&self, word: &str) -> Option<&str> {
+            self.packs
+                .iter()
+                .find(|pack| pack.words.iter().any(|entry| entry.word == word))
+                .map(|pack| pack.info.name.as_str())
+        }
+
+        /// Pack infos with the number of words not in `history`.
+        pub fn infos_for(&self, history: &WordHistory) -> Vec<PackInfo> {
+            self.packs
+                .iter()
+                .map(|pack| PackInfo {
+                    unused: pack
+                        .words
+                        .iter()
+                        .filter(|word| !history.contains(&word.word))
+                        .count(),
+                    ..pack.info.clone()
+                })
+                .collect()
+        }
+    }
+
+    /// Below this many unused words the packs count as nearly exhausted.
+    pub const NEARLY_EXHAUSTED: usize = 10;
+
     impl Dictionary {
-        pub fn new(words: Vec<String>) -> Self {
+        pub fn new(words: Vec<Word>) -> Self {
             assert!(words.len() > 0, "cannot have an empty dictionary");
             Self {
                 words: words,
-                next_index: 0,
+                draws: 0,
             }
         }
-        fn get_word(&mut self) -> String {
-            let word = self.words[self.next_index].clone();
-            self.next_index = self.next_index + 1;
-            if self.next_index >= self.words.len() {
-                self.next_index = 0;
+        pub fn len(&self) -> usize {
+            self.words.len()
+        }
+        pub fn is_empty(&self) -> bool {
+            self.words.is_empty()
+        }
+        /// Add `word` unless the dictionary has it already, in any case.
+        fn add(&mut self, word: &str) {
+            let lowercase = word.to_lowercase();
+            if !self
+                .words
+                .iter()
+                .any(|entry| entry.word.to_lowercase() == lowercase)
+            {
+                self.words.push(word.into());
+            }
+        }
+
+        pub fn words(&self) -> &[Word] {
+            &self.words
+        }
+
+        /// The entry for `word` if it is in the dictionary.
+        pub fn find(&self, word: &str) -> Option<&Word> {
+            self.words.iter().find(|entry| entry.word == word)
+        }
+
+        /// Words `history` doesn't know yet.
+        pub fn unused(&self, history: &WordHistory) -> usize {
+            self.words
+                .iter()
+                .filter(|word| !history.contains(&word.word))
+                .count()
+        }
+
+        /// A random word the group hasn't played, never one of `excluded`
+        /// unless there is nothing else. Once every word is used the history
+        /// of this dictionary is forgotten and it starts over. The same
+        /// `seed`, history and words give the same sequence.
+        fn draw(&mut self, seed: u64, history: &mut WordHistory, excluded: &[String]) -> String {
+            let excluded: Vec<String> = excluded.iter().map(|word| word.to_lowercase()).collect();
+            let allowed: Vec<&Word> = self
+                .words
+                .iter()
+                .filter(|word| !excluded.contains(&word.word.to_lowercase()))
+                .collect();
+            let allowed = if allowed.is_empty() {
+                self.words.iter().collect()
+            } else {
+                allowed
+            };
+            let mut unused: Vec<&Word> = allowed
+                .iter()
+                .copied()
+                .filter(|word| !history.contains(&word.word))
+                .collect();
+            if unused.is_empty() {
+                info!(
+                    words = self.words.len(),
+                    "every word was played, starting over"
+                );
+                for word in self.words.iter() {
+                    history.used.remove(&word.word);
+                }
+                unused = allowed;
+            }
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(self.draws);
+            self.draws += 1;
+            let word = unused[rng.gen_range(0..unused.len())].word.clone();
+            history.used.insert(word.clone());
+            let left = unused.len() - 1;
+            if left <= NEARLY_EXHAUSTED {
+                info!(left, "running out of unused words");
             }
             word
         }
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct PastRound {
         pub name: String,
         pub word: String,
@@ -218,53 +954,287 @@ pub mod game {
         pub active_index: Option<usize>,
         pub phase: GamePhase,
         pub past_rounds: Vec<PastRound>,
+        /// The words of the current game, drawn from the selected packs.
         pub dictionary: Dictionary,
+        /// Comes from the word files and isn't part of a snapshot.
+        #[serde(skip)]
+        pub library: Library,
+        #[serde(default)]
+        pub selected_packs: Vec<String>,
+        /// The library or selection changed during a game, the next game needs a new deck.
+        #[serde(skip)]
+        outdated_deck: bool,
+        /// Seeds the random draw of words.
+        #[serde(default)]
+        pub seed: u64,
+        #[serde(default)]
+        pub history: WordHistory,
+        /// Secret words of each player by name, mixed into the deck. Nobody
+        /// has to guess their own.
+        #[serde(default)]
+        pub custom_words: BTreeMap<String, Vec<String>>,
+        /// Flags the server still has to store, it takes them out of here.
+        #[serde(default)]
+        pub flags: Vec<WordFlag>,
+        /// Players who flagged the current word.
+        #[serde(default)]
+        flagged_by: Vec<String>,
     }
 
+    /// How many custom words a single player may add.
+    pub const MAX_CUSTOM_WORDS: usize = 20;
+
+    /// How many bots may play in a game.
+    pub const MAX_BOTS: usize = 4;
+
+    /// Bots get ids counting down from here, connections count up from 1.
+    const FIRST_BOT_ID: usize = usize::MAX;
+
     impl State {
-        fn restart(&mut self) {
+        pub fn restart(&mut self) {
             self.players = vec![];
             self.active_index = None;
             self.phase = GamePhase::GatherPlayers;
             self.past_rounds = vec![];
-            // leave the dictionary as it is
+            if !self.custom_words.is_empty() {
+                // the dictionary still has the custom words, the next game gets a new one
+                self.custom_words.clear();
+                self.outdated_deck = true;
+            }
         }
     }
 
     #[derive(Serialize, Deserialize, Debug, Default)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct PlayerView {
         pub players: Vec<Player>,
         pub me: Option<Player>,
         pub phase: VisibleGamePhase,
         pub actions: Vec<Action>,
         pub past_rounds: Vec<PastRound>,
+        /// Every pack the server offers.
+        pub packs: Vec<PackInfo>,
+        pub selected_packs: Vec<String>,
+        /// Number of custom words of all players, the words themselves are
+        /// only shown to whoever submitted them.
+        #[serde(default)]
+        pub custom_words: usize,
+    }
+
+    /// Close code of the connections when the server goes down, "going away"
+    /// in RFC 6455. Clients tell the player that it will be back soon.
+    pub const CLOSE_GOING_AWAY: u16 = 1001;
+
+    /// Everything the server sends over the websocket.
+    #[derive(Serialize, Deserialize, Debug)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    #[serde(tag = "type")]
+    pub enum ServerMessage {
+        /// The game as seen by the receiving connection, sent after every change.
+        View { view: Box<PlayerView> },
+        /// Something to be shown to the player.
+        Notice { notice: Notice },
+        /// The server is going down, the connection will be closed once the
+        /// current round is over, at the latest after `grace_seconds`.
+        ShuttingDown { grace_seconds: u64 },
+        /// The last frame from this connection was dropped.
+        Rejected { reason: RejectReason },
+    }
+
+    /// JSON Schemas of what the clients send and what the server sends, by
+    /// the name of their file in `common/schema`.
+    #[cfg(feature = "schema")]
+    pub fn schemas() -> Vec<(&'static str, schemars::schema::RootSchema)> {
+        vec![
+            ("action", schemars::schema_for!(Action)),
+            ("server_message", schemars::schema_for!(ServerMessage)),
+        ]
+    }
+
+    /// What the server has to tell a player outside of the game. Everything
+    /// but `Message` is a code which the client turns into text in the
+    /// player's language.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub enum Notice {
+        /// The operator removed the player from the game.
+        Kicked,
+        /// The operator closed the room, everyone starts over in a new one.
+        GameClosed,
+        /// Free text from the operator, shown as it is.
+        Message(String),
+    }
+
+    /// Why the server dropped a frame without looking at its content.
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub enum RejectReason {
+        /// Too many frames in a short time.
+        RateLimited,
+        /// The frame is larger than the server accepts.
+        TooLarge,
+        /// The frame isn't an `Action`.
+        Malformed,
+        /// A name, hint or guess is too long.
+        TooLong,
+        /// The server is going down, no new game is started.
+        ShuttingDown,
     }
 
     impl State {
-        pub fn new(dictionary: Dictionary) -> Self {
+        /// A new game with every pack of `library` selected.
+        pub fn new(library: Library) -> Self {
+            let selected_packs = library.names();
+            let dictionary = library
+                .deck(&selected_packs)
+                .expect("cannot have an empty library");
             Self {
                 players: vec![],
                 active_index: None,
                 phase: GamePhase::default(),
                 past_rounds: vec![],
                 dictionary: dictionary,
+                library,
+                selected_packs,
+                outdated_deck: false,
+                seed: 0,
+                history: WordHistory::default(),
+                custom_words: BTreeMap::new(),
+                flags: vec![],
+                flagged_by: vec![],
+            }
+        }
+
+        /// Use the packs of `library` from the next game on, the one in progress
+        /// keeps its words. Selected packs which are gone are dropped.
+        pub fn set_library(&mut self, library: Library) {
+            assert!(!library.is_empty(), "cannot have an empty library");
+            self.selected_packs.retain(|name| library.contains(name));
+            if self.selected_packs.is_empty() {
+                self.selected_packs = library.names();
+            }
+            self.library = library;
+            self.outdated_deck = true;
+            if matches!(self.phase, GamePhase::GatherPlayers) {
+                self.renew_deck();
+            }
+        }
+
+        /// A word for the active player, who never gets one of their own.
+        fn draw_word(&mut self) -> String {
+            let own = self
+                .active_player()
+                .and_then(|player| self.custom_words.get(&player.name))
+                .cloned()
+                .unwrap_or_default();
+            self.flagged_by.clear();
+            self.dictionary.draw(self.seed, &mut self.history, &own)
+        }
+
+        /// Whether the player at `index` knows the word and hasn't flagged it yet.
+        /// The active player only learns the word when the guess is judged.
+        fn can_flag(&self, index: usize) -> bool {
+            let active = Some(index) == self.active_index;
+            let knows_word = match self.phase {
+                GamePhase::GatherPlayers => false,
+                GamePhase::Judging(_) => true,
+                _ => !active,
+            };
+            knows_word && !self.flagged_by.contains(&self.players[index].name)
+        }
+
+        fn flag_word(&mut self, id: usize, reason: FlagReason) -> Option<()> {
+            let index = self.player_index(id)?;
+            if !self.can_flag(index) {
+                debug!("can't flag the word");
+                return None;
+            }
+            let word = self.phase.word()?.to_string();
+            let player = self.players[index].name.clone();
+            self.flags.push(WordFlag {
+                pack: self.library.pack_of(&word).map(str::to_string),
+                word,
+                reason,
+                player: player.clone(),
+            });
+            self.flagged_by.push(player);
+            Some(())
+        }
+
+        fn renew_deck(&mut self) {
+            if let Some(mut deck) = self.library.deck(&self.selected_packs) {
+                for word in self.custom_words.values().flatten() {
+                    deck.add(word);
+                }
+                self.dictionary = deck;
+                self.outdated_deck = false;
             }
         }
 
+        fn select_packs(&mut self, id: usize, packs: &[String]) -> Option<()> {
+            self.player(id)?;
+            if !matches!(self.phase, GamePhase::GatherPlayers)
+                || packs.is_empty()
+                || !packs.iter().all(|name| self.library.contains(name))
+            {
+                debug!("invalid pack selection");
+                return None;
+            }
+            self.selected_packs = packs.to_vec();
+            self.renew_deck();
+            Some(())
+        }
+
+        /// Duplicates and empty words are dropped, an empty list takes back
+        /// every word of the player.
+        fn submit_words(&mut self, id: usize, words: &[String]) -> Option<()> {
+            let name = self.player(id)?.name.clone();
+            if !matches!(self.phase, GamePhase::GatherPlayers) || words.len() > MAX_CUSTOM_WORDS {
+                debug!("invalid custom words");
+                return None;
+            }
+            let mut own: Vec<String> = vec![];
+            for word in words.iter().map(|word| word.trim()) {
+                let lowercase = word.to_lowercase();
+                if !word.is_empty() && !own.iter().any(|w| w.to_lowercase() == lowercase) {
+                    own.push(word.to_string());
+                }
+            }
+            if own.is_empty() {
+                self.custom_words.remove(&name);
+            } else {
+                self.custom_words.insert(name, own);
+            }
+            self.renew_deck();
+            Some(())
+        }
+
         pub fn action(&mut self, action: &Action) -> Option<()> {
             match action {
-                Action::Join(new_id, new_name) => self.join(*new_id, new_name),
-                Action::DisconnectPlayer(id) => self.disconnect_player(*id),
-                Action::Start(_id) => self.start(),
-                Action::GiveHint(id, hint) => {
+                Action::Join {
+                    id: new_id,
+                    name: new_name,
+                } => self.join(*new_id, new_name),
+                Action::DisconnectPlayer { id } => self.disconnect_player(*id),
+                Action::Start { .. } => self.start(),
+                Action::GiveHint { id, hint } => {
                     self.process_hint(*id, (*hint).as_ref().map(|s| s.as_str()))
                 }
-                Action::FilterHint(id, hint, valid) => self.process_hint_filter(*id, hint, *valid),
-                Action::FinishHintFiltering(id) => self.process_finish_hint_filter(*id),
-                Action::Guess(id, guess) => self.process_guess(*id, guess),
-                Action::Judge(id, correct) => self.process_guess_judgement(*id, *correct),
-                Action::FinishJudging(id) => self.process_finish_judging(*id),
-                Action::Leave(id) => self.process_leave(*id),
+                Action::FilterHint {
+                    id,
+                    hint,
+                    allowed: valid,
+                } => self.process_hint_filter(*id, hint, *valid),
+                Action::FinishHintFiltering { id } => self.process_finish_hint_filter(*id),
+                Action::Guess { id, guess } => self.process_guess(*id, guess),
+                Action::Judge { id, correct } => self.process_guess_judgement(*id, *correct),
+                Action::FinishJudging { id } => self.process_finish_judging(*id),
+                Action::Leave { id } => self.process_leave(*id),
+                Action::SelectPacks { id, packs } => self.select_packs(*id, packs),
+                Action::SubmitWords { id, words } => self.submit_words(*id, words),
+                Action::FlagWord { id, reason } => self.flag_word(*id, *reason),
+                Action::AddBot { id, difficulty } => self.add_bot(*id, *difficulty),
+                Action::RemoveBot { id, name } => self.remove_bot(*id, name),
             }
         }
 
@@ -277,17 +1247,27 @@ pub mod game {
         }
 
         pub fn join(&mut self, new_id: usize, new_name: &str) -> Option<()> {
+            if self.player(new_id).is_some_and(|p| p.name != new_name) {
+                debug!(new_id, "already seated under another name");
+                return None;
+            }
             match self.players.iter_mut().find(|p| p.name == new_name) {
-                Some(player) => {
-                    if player.id.is_none() {
+                Some(player) => match player.id {
+                    None => {
                         player.id = Some(new_id);
+                        Some(())
                     }
-                    Some(())
-                }
+                    Some(id) if id == new_id => Some(()),
+                    Some(_) => {
+                        debug!(new_id, "seat is taken");
+                        None
+                    }
+                },
                 None => {
                     self.players.push(Player {
                         id: Some(new_id),
                         name: new_name.to_string(),
+                        bot: None,
                     });
                     if self.active_index.is_none() {
                         self.active_index = Some(self.players.len() - 1);
@@ -297,6 +1277,47 @@ pub mod game {
             }
         }
 
+        /// Bots join as "Bot 1", "Bot 2" and so on, with the first free number.
+        fn add_bot(&mut self, id: usize, difficulty: Difficulty) -> Option<()> {
+            if self.player(id)?.bot.is_some()
+                || !matches!(self.phase, GamePhase::GatherPlayers)
+                || self.bots().count() >= MAX_BOTS
+            {
+                debug!("can't add a bot");
+                return None;
+            }
+            let bot_id = (0..)
+                .map(|n| FIRST_BOT_ID - n)
+                .find(|bot_id| self.player_index(*bot_id).is_none())?;
+            let name = (1..)
+                .map(|n| format!("Bot {}", n))
+                .find(|name| self.players.iter().all(|p| &p.name != name))?;
+            info!(%name, ?difficulty, "adding bot");
+            self.players.push(Player {
+                id: Some(bot_id),
+                name,
+                bot: Some(difficulty),
+            });
+            Some(())
+        }
+
+        fn remove_bot(&mut self, id: usize, name: &str) -> Option<()> {
+            self.player(id)?;
+            let index = self
+                .players
+                .iter()
+                .position(|p| p.name == name && p.bot.is_some())?;
+            if !matches!(self.phase, GamePhase::GatherPlayers) {
+                debug!("can only remove bots in the lobby");
+                return None;
+            }
+            self.remove_player_at(index)
+        }
+
+        pub fn bots(&self) -> impl Iterator<Item = &Player> {
+            self.players.iter().filter(|p| p.bot.is_some())
+        }
+
         fn player_index(&self, id: usize) -> Option<usize> {
             self.players.iter().position(|p| p.id == Some(id))
         }
@@ -316,14 +1337,17 @@ pub mod game {
         fn start(&mut self) -> Option<()> {
             match &mut self.phase {
                 GamePhase::GatherPlayers => {
+                    if self.outdated_deck {
+                        self.renew_deck();
+                    }
                     self.phase = GamePhase::HintCollection(HintCollection {
-                        word: self.dictionary.get_word(),
+                        word: self.draw_word(),
                         hints: HashMap::new(),
                     });
                     Some(())
                 }
                 _ => {
-                    eprintln!("start in wrong game state");
+                    debug!("start in wrong game state");
                     None
                 }
             }
@@ -357,12 +1381,12 @@ pub mod game {
                         }
                         Some(())
                     } else {
-                        eprintln!("hint from active player? {:?}", (id, hint));
+                        debug!(id, "hint from active player");
                         None
                     }
                 }
                 _ => {
-                    eprintln!("hint in wrong game state");
+                    debug!("hint in wrong game state");
                     None
                 }
             }
@@ -380,12 +1404,12 @@ pub mod game {
                         }
                         Some(())
                     } else {
-                        eprintln!("hint filtering from active player");
+                        debug!("hint filtering from active player");
                         None
                     }
                 }
                 _ => {
-                    eprintln!("hint filtering in wrong game state");
+                    debug!("hint filtering in wrong game state");
                     None
                 }
             }
@@ -403,12 +1427,12 @@ pub mod game {
                         });
                         Some(())
                     } else {
-                        eprintln!("finish hint filtering from active player");
+                        debug!("finish hint filtering from active player");
                         None
                     }
                 }
                 _ => {
-                    eprintln!("finish hint filtering in wrong game state");
+                    debug!("finish hint filtering in wrong game state");
                     None
                 }
             }
@@ -420,16 +1444,12 @@ pub mod game {
                 GamePhase::Guessing(Guessing { word, hints, guess }) => {
                     if active {
                         *guess = input_guess.clone();
-                        let normalized_guess = guess
-                            .as_ref()
-                            .map(|s| s.to_lowercase())
-                            .unwrap_or("".to_string());
-                        let normalized_word = word.to_lowercase();
-                        let success_default = if normalized_guess == normalized_word {
-                            Some(true)
-                        } else {
-                            None
+                        let accepted = match (guess.as_deref(), self.dictionary.find(word)) {
+                            (Some(guess), Some(entry)) => entry.accepts(guess),
+                            (Some(guess), None) => Word::from(word.as_str()).accepts(guess),
+                            (None, _) => false,
                         };
+                        let success_default = if accepted { Some(true) } else { None };
                         self.phase = GamePhase::Judging(Judging {
                             word: word.clone(),
                             hints: hints.clone(),
@@ -438,12 +1458,12 @@ pub mod game {
                         });
                         Some(())
                     } else {
-                        eprintln!("guess from inactive player");
+                        debug!("guess from inactive player");
                         None
                     }
                 }
                 _ => {
-                    eprintln!("guess in wrong game state");
+                    debug!("guess in wrong game state");
                     None
                 }
             }
@@ -462,18 +1482,22 @@ pub mod game {
                         *success = Some(correct);
                         Some(())
                     } else {
-                        eprintln!("judgement from active player");
+                        debug!("judgement from active player");
                         None
                     }
                 }
                 _ => {
-                    eprintln!("guess judgement in wrong game state");
+                    debug!("guess judgement in wrong game state");
                     None
                 }
             }
         }
 
         fn process_finish_judging(&mut self, _id: usize) -> Option<()> {
+            self.finish_round()
+        }
+
+        fn finish_round(&mut self) -> Option<()> {
             match &mut self.phase {
                 GamePhase::Judging(Judging {
                     word,
@@ -489,13 +1513,13 @@ pub mod game {
                     });
                     self.active_index = Some((self.active_index? + 1) % self.players.len());
                     self.phase = GamePhase::HintCollection(HintCollection {
-                        word: self.dictionary.get_word(),
+                        word: self.draw_word(),
                         hints: HashMap::new(),
                     });
                     Some(())
                 }
                 _ => {
-                    eprintln!("finish judging in wrong game state");
+                    debug!("finish judging in wrong game state");
                     None
                 }
             }
@@ -503,6 +1527,16 @@ pub mod game {
 
         fn process_leave(&mut self, id: usize) -> Option<()> {
             let leaving_index = self.player_index(id)?;
+            self.remove_player_at(leaving_index)
+        }
+
+        /// Remove a player regardless of whether they are connected, e.g. when kicked by an admin.
+        pub fn remove_player(&mut self, name: &str) -> Option<()> {
+            let index = self.players.iter().position(|p| p.name == name)?;
+            self.remove_player_at(index)
+        }
+
+        fn remove_player_at(&mut self, leaving_index: usize) -> Option<()> {
             // circumvent index logic by determining the next player, then determining its index
             let next_active_player: Option<Player> = (|| {
                 if self.active_index? == leaving_index {
@@ -512,25 +1546,29 @@ pub mod game {
                     Some(self.players[self.active_index?].clone())
                 }
             })();
-            eprintln!(
-                "target active player after leaving: {:?}",
-                next_active_player
-            );
-            self.players.remove(leaving_index);
+            debug!(?next_active_player, "target active player after leaving");
+            let left = self.players.remove(leaving_index);
+            if self.custom_words.remove(&left.name).is_some() {
+                self.outdated_deck = true;
+            }
             self.active_index = (|| {
                 let next = next_active_player?;
                 self.players.iter().position(|p| p == &next)
             })();
-            eprintln!("active index after leaving: {:?}", self.active_index);
+            debug!(active_index = ?self.active_index, "active index after leaving");
             match self.phase {
-                GamePhase::GatherPlayers => {}
+                GamePhase::GatherPlayers => {
+                    if self.outdated_deck {
+                        self.renew_deck();
+                    }
+                }
                 GamePhase::HintCollection(_)
                 | GamePhase::HintFiltering(_)
                 | GamePhase::Guessing(_)
                 | GamePhase::Judging(_) => {
                     if self.players.len() >= 2 {
                         self.phase = GamePhase::HintCollection(HintCollection {
-                            word: self.dictionary.get_word(),
+                            word: self.draw_word(),
                             hints: HashMap::new(),
                         });
                     } else {
@@ -538,42 +1576,116 @@ pub mod game {
                     }
                 }
             }
-            if self.players.len() == 0 {
-                eprintln!("no players left, restart");
+            if self.players.iter().all(|p| p.bot.is_some()) {
+                info!("no players left, restart");
                 self.restart();
             }
             Some(())
         }
 
+        /// Move a stuck game on to the next phase as if the missing players had acted:
+        /// missing hints are skipped, the filtering is taken as it is, a missing guess
+        /// counts as a pass and an open judgement as wrong.
+        pub fn force_advance(&mut self) -> Option<()> {
+            match &self.phase {
+                GamePhase::GatherPlayers => {
+                    if self.players.len() >= 2 {
+                        self.start()
+                    } else {
+                        debug!("can't start with fewer than two players");
+                        None
+                    }
+                }
+                GamePhase::HintCollection(HintCollection { word, hints }) => {
+                    self.phase = GamePhase::HintFiltering(HintFiltering {
+                        word: word.clone(),
+                        hints: hints.clone(),
+                    });
+                    Some(())
+                }
+                GamePhase::HintFiltering(HintFiltering { word, hints }) => {
+                    self.phase = GamePhase::Guessing(Guessing {
+                        word: word.clone(),
+                        hints: hints.clone(),
+                        guess: None,
+                    });
+                    Some(())
+                }
+                GamePhase::Guessing(Guessing {
+                    word,
+                    hints,
+                    guess: _,
+                }) => {
+                    self.phase = GamePhase::Judging(Judging {
+                        word: word.clone(),
+                        hints: hints.clone(),
+                        guess: None,
+                        success: None,
+                    });
+                    Some(())
+                }
+                GamePhase::Judging(_) => self.finish_round(),
+            }
+        }
+
         pub fn list_actions(&self, id: usize) -> Vec<Action> {
             match self.player_index(id) {
                 Some(i) => {
                     let active = i == self.active_index.unwrap_or(0);
                     let mut actions = match &self.phase {
                         GamePhase::GatherPlayers => {
+                            let own = self.custom_words.get(&self.players[i].name);
+                            let mut actions = vec![
+                                Action::SelectPacks {
+                                    id,
+                                    packs: self.selected_packs.clone(),
+                                },
+                                Action::SubmitWords {
+                                    id,
+                                    words: own.cloned().unwrap_or_default(),
+                                },
+                            ];
+                            if self.players[i].bot.is_none() {
+                                if self.bots().count() < MAX_BOTS {
+                                    actions.push(Action::AddBot {
+                                        id,
+                                        difficulty: Difficulty::Medium,
+                                    });
+                                }
+                                actions.extend(self.bots().map(|bot| Action::RemoveBot {
+                                    id,
+                                    name: bot.name.clone(),
+                                }));
+                            }
                             if self.players.len() >= 2 {
-                                vec![Action::Start(id)]
-                            } else {
-                                vec![]
+                                actions.push(Action::Start { id });
                             }
+                            actions
                         }
                         GamePhase::HintCollection(_) => {
                             if !active {
-                                vec![Action::GiveHint(id, None)]
+                                vec![Action::GiveHint { id, hint: None }]
                             } else {
                                 vec![]
                             }
                         }
                         GamePhase::HintFiltering(HintFiltering { word: _, hints }) => {
                             if !active {
+                                // by author, so the same game is always sent the same way
                                 let mut actions: Vec<Action> = hints
                                     .iter()
-                                    .map(|(_, hint)| {
+                                    .collect::<BTreeMap<_, _>>()
+                                    .into_values()
+                                    .map(|hint| {
                                         // note: if the hint is allowed the possible action is to set it to disallowed
-                                        Action::FilterHint(id, hint.content.clone(), !hint.allowed)
+                                        Action::FilterHint {
+                                            id,
+                                            hint: hint.content.clone(),
+                                            allowed: !hint.allowed,
+                                        }
                                     })
                                     .collect();
-                                actions.push(Action::FinishHintFiltering(id));
+                                actions.push(Action::FinishHintFiltering { id });
                                 actions
                             } else {
                                 vec![]
@@ -583,7 +1695,7 @@ pub mod game {
                             if !active {
                                 vec![]
                             } else {
-                                vec![Action::Guess(id, None)]
+                                vec![Action::Guess { id, guess: None }]
                             }
                         }
                         GamePhase::Judging(Judging {
@@ -595,118 +1707,48 @@ pub mod game {
                             let mut actions: Vec<Action> = vec![];
                             if !active {
                                 if guess.is_some() {
-                                    actions.push(Action::Judge(id, !success.unwrap_or(false)));
+                                    actions.push(Action::Judge {
+                                        id,
+                                        correct: !success.unwrap_or(false),
+                                    });
                                 }
-                                actions.push(Action::FinishJudging(id));
+                                actions.push(Action::FinishJudging { id });
                             }
                             actions
                         }
                     };
-                    actions.push(Action::Leave(id));
+                    if self.can_flag(i) {
+                        actions.push(Action::FlagWord {
+                            id,
+                            reason: FlagReason::Unknown,
+                        });
+                    }
+                    actions.push(Action::Leave { id });
                     actions
                 }
                 None => {
-                    vec![Action::Join(id, String::new())]
+                    vec![Action::Join {
+                        id,
+                        name: String::new(),
+                    }]
                 }
             }
         }
 
         pub fn get_view(&self, id: usize) -> PlayerView {
-            let visible_phase: VisibleGamePhase = match self.player_index(id) {
+            let visible_phase = match self.player_index(id) {
                 Some(index) => {
-                    let active = index == self.active_index.unwrap_or(0);
-                    let player = &self.players[index];
-                    match &self.phase {
-                        GamePhase::GatherPlayers => VisibleGamePhase::GatherPlayers,
-                        GamePhase::HintCollection(HintCollection { word, hints }) => {
-                            let done = hints.iter().map(|(key, _)| key.to_string()).collect();
-                            if active {
-                                VisibleGamePhase::HintCollection(VisibleHintCollection::Active(
-                                    ActiveHintCollection { players_done: done },
-                                ))
-                            } else {
-                                let own_hint = hints.get(&player.name).cloned();
-                                VisibleGamePhase::HintCollection(VisibleHintCollection::Inactive(
-                                    InactiveHintCollection {
-                                        active_player: self
-                                            .active_player_name()
-                                            .unwrap_or(String::new()),
-                                        word: word.clone(),
-                                        hint: own_hint,
-                                        players_done: done,
-                                    },
-                                ))
-                            }
-                        }
-                        GamePhase::HintFiltering(HintFiltering { word, hints }) => {
-                            if active {
-                                VisibleGamePhase::HintFiltering(VisibleHintFiltering::Active(
-                                    ActiveHintFiltering {
-                                        players_valid_hints: hints
-                                            .iter()
-                                            .filter(|(_name, hint)| hint.allowed)
-                                            .map(|(name, _)| name.to_string())
-                                            .collect(),
-                                    },
-                                ))
-                            } else {
-                                VisibleGamePhase::HintFiltering(VisibleHintFiltering::Inactive(
-                                    InactiveHintFiltering {
-                                        active_player: self
-                                            .active_player_name()
-                                            .unwrap_or(String::new()),
-                                        hint_filtering: HintFiltering {
-                                            word: word.clone(),
-                                            hints: hints.clone(),
-                                        },
-                                    },
-                                ))
-                            }
-                        }
-                        GamePhase::Guessing(Guessing { word, hints, guess }) => {
-                            if active {
-                                VisibleGamePhase::Guessing(VisibleGuessing::Active(
-                                    ActiveGuessing {
-                                        hints: hints
-                                            .iter()
-                                            .filter(|(_key, value)| value.allowed)
-                                            .map(|(key, value)| {
-                                                (key.clone(), VisibleHint(value.content.clone()))
-                                            })
-                                            .collect(),
-                                        guess: None,
-                                    },
-                                ))
-                            } else {
-                                VisibleGamePhase::Guessing(VisibleGuessing::Inactive(
-                                    InactiveGuessing {
-                                        active_player: self
-                                            .active_player_name()
-                                            .unwrap_or(String::new()),
-                                        guessing: Guessing {
-                                            word: word.clone(),
-                                            hints: hints.clone(),
-                                            guess: guess.clone(),
-                                        },
-                                    },
-                                ))
-                            }
-                        }
-                        GamePhase::Judging(judging) => {
-                            if active {
-                                VisibleGamePhase::Judging(VisibleJudging::Active(judging.clone()))
-                            } else {
-                                VisibleGamePhase::Judging(VisibleJudging::Inactive(
-                                    InactiveJudging {
-                                        active_player: self
-                                            .active_player_name()
-                                            .unwrap_or(String::new()),
-                                        judging: judging.clone(),
-                                    },
-                                ))
-                            }
-                        }
-                    }
+                    let role = if index == self.active_index.unwrap_or(0) {
+                        Role::Active
+                    } else {
+                        Role::Inactive
+                    };
+                    let me = &self.players[index].name;
+                    self.phase.seen_by(role, me).into_visible(
+                        role,
+                        me,
+                        self.active_player_name().unwrap_or_default(),
+                    )
                 }
                 None => VisibleGamePhase::GatherPlayers,
             };
@@ -717,15 +1759,1064 @@ pub mod game {
                 phase: visible_phase,
                 actions: actions,
                 past_rounds: self.past_rounds.clone(),
+                packs: self.library.infos_for(&self.history),
+                selected_packs: self.selected_packs.clone(),
+                custom_words: self.custom_words.values().map(Vec::len).sum(),
+            }
+        }
+
+        /// Checks the serialized view of `id` against the phase itself, not against
+        /// the visibility rules: nobody outside the game sees the word or a hint,
+        /// the active player sees neither the word nor a filtered hint before
+        /// judging, nobody sees the others' hints while they are collected and
+        /// nobody sees the custom words of the others. Only the phase and the
+        /// offered actions are about the current round, the past rounds show
+        /// earlier words on purpose. The tests and the simulator run it after
+        /// every action.
+        pub fn audit_view(&self, id: usize) -> Result<(), String> {
+            let view = self.get_view(id);
+            let json =
+                serde_json::to_string(&(&view.phase, &view.actions)).map_err(|e| e.to_string())?;
+            let index = self.players.iter().position(|p| p.id == Some(id));
+            let active = index.is_some() && index == self.active_index;
+            let me = index.map(|index| self.players[index].name.as_str());
+            let judging = matches!(self.phase, GamePhase::Judging(_));
+            let collecting = matches!(self.phase, GamePhase::HintCollection(_));
+            let guessing = matches!(self.phase, GamePhase::Guessing(_));
+            let sees_word = index.is_some() && (!active || judging);
+
+            let hints = self.phase.hints().into_iter().flatten();
+            let own = hints
+                .clone()
+                .find(|(author, _)| Some(author.as_str()) == me)
+                .map(|(_, hint)| hint.content.as_str());
+            let shown: Vec<&str> = hints
+                .clone()
+                .filter(|(_, hint)| index.is_some() && guessing && hint.allowed)
+                .map(|(_, hint)| hint.content.as_str())
+                .chain(own)
+                .chain(self.phase.word().filter(|_| sees_word))
+                .collect();
+
+            // a hint nobody filtered may spell out the word, and a hint may be
+            // the word the viewer sees anyway
+            let mut secrets: Vec<&str> = vec![];
+            if let Some(word) = self.phase.word() {
+                if !shown.contains(&word) {
+                    secrets.push(word);
+                }
+            }
+            for (_, hint) in hints.clone() {
+                let secret = index.is_none()
+                    || (active && !judging && !(guessing && hint.allowed))
+                    || (collecting && Some(hint.content.as_str()) != own);
+                if secret && !hint.content.is_empty() && !shown.contains(&hint.content.as_str()) {
+                    secrets.push(&hint.content);
+                }
+            }
+            // the current word and the hints are checked above, whichever of the
+            // others' custom words they happen to be
+            let mine = me.and_then(|me| self.custom_words.get(me));
+            let others = self
+                .custom_words
+                .iter()
+                .filter(|(author, _)| Some(author.as_str()) != me)
+                .flat_map(|(_, words)| words);
+            for word in others {
+                let known = mine.is_some_and(|mine| mine.contains(word))
+                    || self.phase.word() == Some(word.as_str())
+                    || hints.clone().any(|(_, hint)| &hint.content == word);
+                if !known {
+                    secrets.push(word);
+                }
             }
+            for secret in secrets {
+                if json.contains(&serde_json::to_string(secret).map_err(|e| e.to_string())?) {
+                    return Err(format!(
+                        "{} sees {} in {}: {}",
+                        id,
+                        secret,
+                        self.phase.name(),
+                        json
+                    ));
+                }
+            }
+            Ok(())
         }
     }
 }
 
+pub mod i18n;
+
 #[cfg(test)]
 mod tests {
+    use super::game::*;
+    use proptest::prelude::*;
+    use proptest::sample::Index;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn redacted_phase_hides_word_and_hints() {
+        let mut state = State::new(Library::new(vec![WordPack::new(
+            "test",
+            "de",
+            vec!["Geheimnis".into()],
+        )]));
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        state.action(&Action::Start { id: 1 });
+        state.action(&Action::GiveHint {
+            id: 2,
+            hint: Some("Versteck".to_string()),
+        });
+        let redacted = format!("{:?}", state.phase.redacted());
+        assert!(!redacted.contains("Geheimnis"));
+        assert!(!redacted.contains("Versteck"));
+        assert!(redacted.contains("\"b\""));
+    }
+
+    #[test]
+    fn packs_are_selected_in_the_lobby() {
+        let words = |words: &[&str]| words.iter().map(|&w| Word::from(w)).collect();
+        let mut state = State::new(Library::new(vec![
+            WordPack::new("tiere", "de", words(&["Schwan", "Hund", "Katze"])),
+            WordPack::new("orte", "de", words(&["Steinbruch"])),
+        ]));
+        assert_eq!(state.dictionary.len(), 4);
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        assert!(state.get_view(1).packs.iter().any(|p| p.name == "orte"));
+        assert!(state
+            .action(&Action::SelectPacks {
+                id: 1,
+                packs: vec!["fehlt".to_string()]
+            })
+            .is_none());
+        assert!(state
+            .action(&Action::SelectPacks {
+                id: 1,
+                packs: vec!["orte".to_string()]
+            })
+            .is_some());
+        assert_eq!(state.get_view(2).selected_packs, vec!["orte".to_string()]);
+        state.action(&Action::Start { id: 1 });
+        assert!(matches!(
+            &state.phase,
+            GamePhase::HintCollection(HintCollection { word, .. }) if word == "Steinbruch"
+        ));
+        assert!(state
+            .action(&Action::SelectPacks {
+                id: 1,
+                packs: vec!["tiere".to_string()]
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn custom_words_are_secret_and_not_for_their_author() {
+        let mut state = State::new(Library::new(vec![WordPack::new(
+            "orte",
+            "de",
+            vec!["Steinbruch".into()],
+        )]));
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        let words = vec![
+            " Quatsch".to_string(),
+            "quatsch".to_string(),
+            "".to_string(),
+        ];
+        assert!(state
+            .action(&Action::SubmitWords { id: 1, words })
+            .is_some());
+        assert_eq!(state.dictionary.len(), 2);
+        let own = |state: &State, id| {
+            state
+                .get_view(id)
+                .actions
+                .into_iter()
+                .find_map(|action| match action {
+                    Action::SubmitWords { words, .. } => Some(words),
+                    _ => None,
+                })
+        };
+        assert_eq!(own(&state, 1), Some(vec!["Quatsch".to_string()]));
+        assert_eq!(own(&state, 2), Some(vec![]));
+        assert_eq!(state.get_view(2).custom_words, 1);
+
+        state.action(&Action::Start { id: 1 });
+        let mut drawn_for_b = vec![];
+        for _ in 0..6 {
+            let word = match &state.phase {
+                GamePhase::HintCollection(HintCollection { word, .. }) => word.clone(),
+                other => panic!("expected hint collection, got {:?}", other),
+            };
+            if state.active_index == Some(0) {
+                assert_eq!(word, "Steinbruch");
+            } else {
+                drawn_for_b.push(word);
+            }
+            for _ in 0..4 {
+                state.force_advance();
+            }
+        }
+        assert!(drawn_for_b.contains(&"Quatsch".to_string()));
+        assert!(state
+            .action(&Action::SubmitWords {
+                id: 2,
+                words: vec!["Unsinn".to_string()]
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn words_are_flagged_once_by_those_who_know_them() {
+        let mut state = State::new(Library::default());
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        assert!(state
+            .action(&Action::FlagWord {
+                id: 2,
+                reason: FlagReason::Unknown
+            })
+            .is_none());
+        state.action(&Action::Start { id: 1 });
+        let word = state.phase.word().unwrap().to_string();
+        assert!(state
+            .action(&Action::FlagWord {
+                id: 1,
+                reason: FlagReason::Offensive
+            })
+            .is_none());
+        assert!(state
+            .action(&Action::FlagWord {
+                id: 2,
+                reason: FlagReason::Offensive
+            })
+            .is_some());
+        assert!(state
+            .action(&Action::FlagWord {
+                id: 2,
+                reason: FlagReason::Unknown
+            })
+            .is_none());
+        assert!(!state
+            .list_actions(2)
+            .iter()
+            .any(|action| matches!(action, Action::FlagWord { .. })));
+        state.force_advance();
+        state.force_advance();
+        state.force_advance();
+        assert!(state
+            .action(&Action::FlagWord {
+                id: 1,
+                reason: FlagReason::Unguessable
+            })
+            .is_some());
+        assert_eq!(
+            state.flags,
+            vec![
+                WordFlag {
+                    word: word.clone(),
+                    pack: Some("Standard".to_string()),
+                    reason: FlagReason::Offensive,
+                    player: "b".to_string(),
+                },
+                WordFlag {
+                    word,
+                    pack: Some("Standard".to_string()),
+                    reason: FlagReason::Unguessable,
+                    player: "a".to_string(),
+                },
+            ]
+        );
+        state.force_advance();
+        assert!(state
+            .action(&Action::FlagWord {
+                id: 1,
+                reason: FlagReason::Unknown
+            })
+            .is_some());
+    }
+
+    #[test]
+    fn bots_are_seated_in_the_lobby_and_leave_with_the_last_player() {
+        let mut state = State::new(Library::default());
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        for _ in 0..MAX_BOTS {
+            assert!(state
+                .action(&Action::AddBot {
+                    id: 1,
+                    difficulty: Difficulty::Easy
+                })
+                .is_some());
+        }
+        assert!(state
+            .action(&Action::AddBot {
+                id: 1,
+                difficulty: Difficulty::Easy
+            })
+            .is_none());
+        assert_eq!(state.bots().count(), MAX_BOTS);
+        assert!(state
+            .action(&Action::RemoveBot {
+                id: 1,
+                name: "Bot 2".to_string()
+            })
+            .is_some());
+        assert!(state
+            .action(&Action::AddBot {
+                id: 1,
+                difficulty: Difficulty::Hard
+            })
+            .is_some());
+        let bot = state.players.last().unwrap().clone();
+        assert_eq!(bot.name, "Bot 2");
+        assert_eq!(bot.bot, Some(Difficulty::Hard));
+        assert!(state
+            .action(&Action::AddBot {
+                id: bot.id.unwrap(),
+                difficulty: Difficulty::Hard
+            })
+            .is_none());
+        assert!(state.list_actions(1).contains(&Action::RemoveBot {
+            id: 1,
+            name: "Bot 2".to_string()
+        }));
+        state.action(&Action::Start { id: 1 });
+        assert!(state
+            .action(&Action::RemoveBot {
+                id: 1,
+                name: "Bot 1".to_string()
+            })
+            .is_none());
+        state.action(&Action::Leave { id: 1 });
+        assert!(state.players.is_empty());
+        assert!(matches!(state.phase, GamePhase::GatherPlayers));
+    }
+
+    #[test]
+    fn other_spellings_are_judged_correct() {
+        let word = Word {
+            also: vec!["Velo".to_string()],
+            ..Word::from("Fahrrad")
+        };
+        let mut state = State::new(Library::new(vec![WordPack::new("test", "de", vec![word])]));
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        state.action(&Action::Start { id: 1 });
+        state.action(&Action::GiveHint {
+            id: 2,
+            hint: Some("Pedal".to_string()),
+        });
+        state.action(&Action::FinishHintFiltering { id: 2 });
+        state.action(&Action::Guess {
+            id: 1,
+            guess: Some(" velo".to_string()),
+        });
+        assert!(matches!(
+            state.phase,
+            GamePhase::Judging(Judging {
+                success: Some(true),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn words_are_not_repeated_and_draws_are_reproducible() {
+        let words: Vec<Word> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|&w| w.into())
+            .collect();
+        let play = |seed: u64, history: WordHistory| {
+            let mut state = State::new(Library::new(vec![WordPack::new(
+                "test",
+                "de",
+                words.clone(),
+            )]));
+            state.seed = seed;
+            state.history = history;
+            state.action(&Action::Join {
+                id: 1,
+                name: "a".to_string(),
+            });
+            state.action(&Action::Join {
+                id: 2,
+                name: "b".to_string(),
+            });
+            state.action(&Action::Start { id: 1 });
+            for _ in 0..2 {
+                for _ in 0..4 {
+                    state.force_advance();
+                }
+            }
+            state
+        };
+        let first = play(7, WordHistory::default());
+        let played: Vec<&String> = first.past_rounds.iter().map(|round| &round.word).collect();
+        assert_eq!(played.len(), 2);
+        assert_ne!(played[0], played[1]);
+        assert_eq!(first.history.len(), 3);
+        assert_eq!(first.get_view(1).packs[0].unused, 2);
+        assert_eq!(
+            play(7, WordHistory::default()).past_rounds,
+            first.past_rounds
+        );
+
+        // the next game of the group only gets the words which are left
+        let second = play(7, first.history.clone());
+        assert!(second
+            .past_rounds
+            .iter()
+            .all(|round| !first.history.contains(&round.word)));
+    }
+
+    #[test]
+    fn force_advance_walks_through_a_round() {
+        let mut state = State::new(Library::new(vec![WordPack::new(
+            "test",
+            "de",
+            vec!["Geheimnis".into()],
+        )]));
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        assert!(state.force_advance().is_none());
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        for _ in 0..5 {
+            assert!(state.force_advance().is_some());
+        }
+        assert!(matches!(state.phase, GamePhase::HintCollection(_)));
+        assert_eq!(
+            state.past_rounds,
+            vec![PastRound {
+                name: "a".to_string(),
+                word: "Geheimnis".to_string(),
+                success: false
+            }]
+        );
+        assert_eq!(state.active_index, Some(1));
+    }
+
+    #[test]
+    fn seated_players_cannot_take_a_second_seat() {
+        let mut state = State::new(Library::new(vec![WordPack::new(
+            "test",
+            "de",
+            vec!["Geheimnis".into()],
+        )]));
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        state.action(&Action::DisconnectPlayer { id: 2 });
+        assert!(state
+            .action(&Action::Join {
+                id: 1,
+                name: "b".to_string()
+            })
+            .is_none());
+        assert_eq!(state.players[1].id, None);
+        assert!(state
+            .action(&Action::Join {
+                id: 1,
+                name: "a".to_string()
+            })
+            .is_some());
+        assert!(state
+            .action(&Action::Join {
+                id: 3,
+                name: "b".to_string()
+            })
+            .is_some());
+    }
+
+    #[test]
+    fn a_seat_held_by_a_connection_cannot_be_taken() {
+        let mut state = State::new(Library::new(vec![WordPack::new(
+            "test",
+            "de",
+            vec!["Geheimnis".into()],
+        )]));
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        // the old connection of "a" isn't known to be gone yet
+        assert!(state
+            .action(&Action::Join {
+                id: 2,
+                name: "a".to_string()
+            })
+            .is_none());
+        assert_eq!(state.players[0].id, Some(1));
+        assert!(state
+            .action(&Action::Join {
+                id: 1,
+                name: "a".to_string()
+            })
+            .is_some());
+        state.action(&Action::DisconnectPlayer { id: 1 });
+        assert!(state
+            .action(&Action::Join {
+                id: 2,
+                name: "a".to_string()
+            })
+            .is_some());
+        assert_eq!(state.players[0].id, Some(2));
+    }
+
+    #[test]
+    fn views_show_every_role_only_what_it_may_see() {
+        let mut state = State::new(Library::new(vec![WordPack::new(
+            "test",
+            "de",
+            vec!["Geheimnis".into(), "Schwan".into()],
+        )]));
+        let ids = [1, 2, 3, 4, 9];
+        let audit_all = |state: &State| {
+            for id in ids.iter() {
+                if let Err(leak) = state.audit_view(*id) {
+                    panic!("{}", leak);
+                }
+            }
+        };
+        for (id, name) in ids[..4].iter().zip(["a", "b", "c", "d"].iter()) {
+            state.action(&Action::Join {
+                id: *id,
+                name: name.to_string(),
+            });
+        }
+        let hint = |hint: &str| Some(hint.to_string());
+        let steps = [
+            Action::Start { id: 1 },
+            Action::GiveHint {
+                id: 2,
+                hint: hint("Versteck"),
+            },
+            Action::GiveHint {
+                id: 3,
+                hint: hint("Tresor"),
+            },
+            Action::GiveHint {
+                id: 4,
+                hint: hint("Rätsel"),
+            },
+            Action::FilterHint {
+                id: 2,
+                hint: "Versteck".to_string(),
+                allowed: false,
+            },
+            Action::FinishHintFiltering { id: 3 },
+            Action::Guess {
+                id: 1,
+                guess: hint("Tresor"),
+            },
+            Action::Judge {
+                id: 2,
+                correct: false,
+            },
+            Action::FinishJudging { id: 2 },
+            Action::GiveHint {
+                id: 1,
+                hint: hint("Versteck"),
+            },
+        ];
+        audit_all(&state);
+        for step in steps.iter() {
+            assert!(state.action(step).is_some(), "{:?}", step);
+            audit_all(&state);
+        }
+
+        // the rules themselves, as the views show them
+        let word = state.phase.word().unwrap().to_string();
+        assert_ne!(word, state.past_rounds[0].word);
+        let guesser = serde_json::to_string(&state.get_view(2)).unwrap();
+        assert!(!guesser.contains(&word) && !guesser.contains("Versteck"));
+        let other = serde_json::to_string(&state.get_view(1)).unwrap();
+        assert!(other.contains(&word) && other.contains("Versteck"));
+        assert!(!serde_json::to_string(&state.get_view(3))
+            .unwrap()
+            .contains("Versteck"));
+    }
+
+    /// Compare with `common/snapshots/<name>.json`, or write it with
+    /// `UPDATE_SNAPSHOTS=1`. Values are compared, not text, so the order of
+    /// object keys doesn't matter.
+    fn snapshot<T: serde::Serialize + serde::de::DeserializeOwned>(name: &str, value: &T) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("snapshots")
+            .join(format!("{}.json", name));
+        let json = serde_json::to_value(value).unwrap();
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let text = serde_json::to_string_pretty(&json).unwrap() + "\n";
+            std::fs::write(&path, text).unwrap();
+            return;
+        }
+        let text = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("{}: {}, create it with UPDATE_SNAPSHOTS=1", name, e));
+        let expected: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            json,
+            expected,
+            "{} changed:\n{}",
+            name,
+            serde_json::to_string_pretty(&json).unwrap()
+        );
+        // what was sent before can still be read
+        let read: T = serde_json::from_value(expected.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&read).unwrap(), expected, "{}", name);
+    }
+
+    #[test]
+    fn actions_keep_their_encoding() {
+        let text = |text: &str| Some(text.to_string());
+        let actions = vec![
+            Action::Join {
+                id: 1,
+                name: "a".to_string(),
+            },
+            Action::DisconnectPlayer { id: 1 },
+            Action::Start { id: 1 },
+            Action::GiveHint {
+                id: 2,
+                hint: text("Versteck"),
+            },
+            Action::GiveHint { id: 2, hint: None },
+            Action::FilterHint {
+                id: 2,
+                hint: "Versteck".to_string(),
+                allowed: false,
+            },
+            Action::FinishHintFiltering { id: 2 },
+            Action::Guess {
+                id: 1,
+                guess: text("Geheimnis"),
+            },
+            Action::Judge {
+                id: 2,
+                correct: true,
+            },
+            Action::FinishJudging { id: 2 },
+            Action::Leave { id: 1 },
+            Action::SelectPacks {
+                id: 1,
+                packs: vec!["tiere".to_string()],
+            },
+            Action::SubmitWords {
+                id: 1,
+                words: vec!["Schwan".to_string()],
+            },
+            Action::FlagWord {
+                id: 2,
+                reason: FlagReason::Unguessable,
+            },
+            Action::AddBot {
+                id: 1,
+                difficulty: Difficulty::Hard,
+            },
+            Action::RemoveBot {
+                id: 1,
+                name: "Bot 1".to_string(),
+            },
+        ];
+        snapshot("actions", &actions);
+    }
+
+    #[test]
+    fn server_messages_keep_their_encoding() {
+        let messages = vec![
+            ServerMessage::Notice {
+                notice: Notice::Kicked,
+            },
+            ServerMessage::Notice {
+                notice: Notice::GameClosed,
+            },
+            ServerMessage::Notice {
+                notice: Notice::Message("Gleich geht es los".to_string()),
+            },
+            ServerMessage::ShuttingDown { grace_seconds: 30 },
+            ServerMessage::Rejected {
+                reason: RejectReason::RateLimited,
+            },
+            ServerMessage::Rejected {
+                reason: RejectReason::TooLarge,
+            },
+            ServerMessage::Rejected {
+                reason: RejectReason::Malformed,
+            },
+            ServerMessage::Rejected {
+                reason: RejectReason::TooLong,
+            },
+            ServerMessage::Rejected {
+                reason: RejectReason::ShuttingDown,
+            },
+        ];
+        snapshot("server_messages", &messages);
+    }
+
+    #[test]
+    fn views_keep_their_encoding() {
+        let mut state = State::new(Library::new(vec![WordPack::new(
+            "test",
+            "de",
+            vec!["Geheimnis".into()],
+        )]));
+        state.action(&Action::Join {
+            id: 1,
+            name: "a".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 2,
+            name: "b".to_string(),
+        });
+        state.action(&Action::Join {
+            id: 3,
+            name: "c".to_string(),
+        });
+        let views = |state: &State, phase: &str| {
+            snapshot(&format!("view_{}_active", phase), &state.get_view(1));
+            snapshot(&format!("view_{}_inactive", phase), &state.get_view(2));
+        };
+        snapshot("view_gather_players", &state.get_view(1));
+        snapshot("view_outsider", &state.get_view(9));
+        let hint = |hint: &str| Some(hint.to_string());
+        let steps = [
+            (Action::Start { id: 1 }, Some("hint_collection")),
+            (
+                Action::GiveHint {
+                    id: 2,
+                    hint: hint("Versteck"),
+                },
+                None,
+            ),
+            (
+                Action::GiveHint {
+                    id: 3,
+                    hint: hint("Rätsel"),
+                },
+                Some("hint_filtering"),
+            ),
+            (
+                Action::FilterHint {
+                    id: 3,
+                    hint: "Versteck".to_string(),
+                    allowed: false,
+                },
+                None,
+            ),
+            (Action::FinishHintFiltering { id: 3 }, Some("guessing")),
+            (
+                Action::Guess {
+                    id: 1,
+                    guess: hint("Geheimnis"),
+                },
+                Some("judging"),
+            ),
+        ];
+        for (action, phase) in steps.iter() {
+            assert!(state.action(action).is_some(), "{:?}", action);
+            if let Some(phase) = phase {
+                views(&state, phase);
+            }
+        }
+        // the view is sent wrapped in a message
+        snapshot(
+            "message_view",
+            &ServerMessage::View {
+                view: Box::new(state.get_view(3)),
+            },
+        );
+    }
+
+    fn compiled_schemas() -> std::collections::HashMap<&'static str, jsonschema::JSONSchema> {
+        schemas()
+            .into_iter()
+            .map(|(name, schema)| {
+                let schema = serde_json::to_value(schema).unwrap();
+                (name, jsonschema::JSONSchema::compile(&schema).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn schemas_are_up_to_date() {
+        for (name, schema) in schemas() {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("schema")
+                .join(format!("{}.schema.json", name));
+            let text = std::fs::read_to_string(&path).unwrap();
+            let written: serde_json::Value = serde_json::from_str(&text).unwrap();
+            assert_eq!(
+                serde_json::to_value(schema).unwrap(),
+                written,
+                "{} is outdated, run the schema binary",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn messages_match_the_schema() {
+        let schemas = compiled_schemas();
+        let check = |name: &str, message: &serde_json::Value| {
+            if let Err(errors) = schemas[name].validate(message) {
+                let errors: Vec<String> = errors.map(|e| e.to_string()).collect();
+                panic!("{} doesn't match {}: {:?}", message, name, errors);
+            }
+        };
+        let snapshots = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("snapshots");
+        for entry in std::fs::read_dir(snapshots).unwrap() {
+            let path = entry.unwrap().path();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+            match path.file_stem().unwrap().to_str().unwrap() {
+                "actions" => json
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .for_each(|action| check("action", action)),
+                "server_messages" => json
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .for_each(|message| check("server_message", message)),
+                "message_view" => check("server_message", &json),
+                _ => check(
+                    "server_message",
+                    &serde_json::json!({ "type": "View", "view": json }),
+                ),
+            }
+        }
+
+        // and what a game offers and sends on the way
+        let mut state = State::new(small_library());
+        for (id, name) in [(1, "a"), (2, "b"), (3, "c")].iter() {
+            state.action(&Action::Join {
+                id: *id,
+                name: name.to_string(),
+            });
+        }
+        for _ in 0..12 {
+            for id in 1..=3 {
+                let view = ServerMessage::View {
+                    view: Box::new(state.get_view(id)),
+                };
+                check("server_message", &serde_json::to_value(&view).unwrap());
+                for action in state.list_actions(id) {
+                    check("action", &serde_json::to_value(&action).unwrap());
+                }
+            }
+            state.force_advance();
+        }
+
+        let wrong = [
+            serde_json::json!({ "type": "Join", "id": 1 }),
+            serde_json::json!({ "type": "Start", "id": -1 }),
+            serde_json::json!({ "Join": [1, "a"] }),
+            serde_json::json!({ "type": "AddBot", "id": 1, "difficulty": "Medium" }),
+        ];
+        for action in wrong.iter() {
+            assert!(!schemas["action"].is_valid(action), "{}", action);
+        }
+    }
+
+    /// One step of a generated game: an arbitrary action, or one of the
+    /// actions currently offered to anyone, which gets the game further than
+    /// random actions alone.
+    #[derive(Debug, Clone)]
+    enum Step {
+        Any(Action),
+        Offered(Index, Option<String>),
+    }
+
+    const WORDS: [&str; 4] = ["Geheimnis", "Schwan", "Hund", "Katze"];
+
+    fn small_library() -> Library {
+        let words = |words: &[&str]| words.iter().map(|&w| Word::from(w)).collect();
+        Library::new(vec![
+            WordPack::new("tiere", "de", words(&WORDS[1..])),
+            WordPack::new("orte", "de", words(&WORDS[..1])),
+        ])
+    }
+
+    /// Connection ids of a few people and of the first bots.
+    fn any_id() -> impl Strategy<Value = usize> {
+        prop_oneof![0..5usize, (0..3usize).prop_map(|n| usize::MAX - n)]
+    }
+
+    fn any_name() -> impl Strategy<Value = String> {
+        prop::sample::select(vec!["a", "b", "c", "d", "Bot 1"]).prop_map(String::from)
+    }
+
+    /// Hints never spell out a word, so they can't leak it by themselves.
+    fn any_hint() -> impl Strategy<Value = Option<String>> {
+        prop::option::of(prop::sample::select(vec!["Feder", "Versteck", "Wolf"]))
+            .prop_map(|hint| hint.map(String::from))
+    }
+
+    fn any_guess() -> impl Strategy<Value = Option<String>> {
+        prop::option::of(prop::sample::select(WORDS.to_vec())).prop_map(|g| g.map(String::from))
+    }
+
+    fn any_action() -> impl Strategy<Value = Action> {
+        let difficulty =
+            prop::sample::select(vec![Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]);
+        let packs = prop::sample::subsequence(vec!["tiere", "orte", "fehlt"], 0..=3)
+            .prop_map(|packs| packs.into_iter().map(String::from).collect::<Vec<_>>());
+        let custom = prop::collection::vec("[A-Z][a-z]{1,4}", 0..3);
+        prop_oneof![
+            (any_id(), any_name()).prop_map(|(id, name)| Action::Join { id, name }),
+            any_id().prop_map(|id| Action::DisconnectPlayer { id }),
+            any_id().prop_map(|id| Action::Start { id }),
+            (any_id(), any_hint()).prop_map(|(id, hint)| Action::GiveHint { id, hint }),
+            (any_id(), any_hint(), any::<bool>()).prop_map(|(id, hint, allowed)| {
+                Action::FilterHint {
+                    id,
+                    hint: hint.unwrap_or_default(),
+                    allowed,
+                }
+            }),
+            any_id().prop_map(|id| Action::FinishHintFiltering { id }),
+            (any_id(), any_guess()).prop_map(|(id, guess)| Action::Guess { id, guess }),
+            (any_id(), any::<bool>()).prop_map(|(id, correct)| Action::Judge { id, correct }),
+            any_id().prop_map(|id| Action::FinishJudging { id }),
+            any_id().prop_map(|id| Action::Leave { id }),
+            (any_id(), packs).prop_map(|(id, packs)| Action::SelectPacks { id, packs }),
+            (any_id(), custom).prop_map(|(id, words)| Action::SubmitWords { id, words }),
+            (any_id(), prop::sample::select(FlagReason::ALL.to_vec()))
+                .prop_map(|(id, reason)| Action::FlagWord { id, reason }),
+            (any_id(), difficulty).prop_map(|(id, difficulty)| Action::AddBot { id, difficulty }),
+            (any_id(), any_name()).prop_map(|(id, name)| Action::RemoveBot { id, name }),
+        ]
+    }
+
+    fn any_step() -> impl Strategy<Value = Step> {
+        prop_oneof![
+            1 => any_action().prop_map(Step::Any),
+            2 => (any::<Index>(), any_hint()).prop_map(|(index, text)| Step::Offered(index, text)),
+        ]
+    }
+
+    /// The action of a step, none if nothing is offered.
+    fn action_of(state: &State, step: &Step) -> Option<Action> {
+        match step {
+            Step::Any(action) => Some(action.clone()),
+            Step::Offered(index, text) => {
+                let offered: Vec<Action> = state
+                    .players
+                    .iter()
+                    .filter_map(|p| p.id)
+                    .flat_map(|id| state.list_actions(id))
+                    .collect();
+                if offered.is_empty() {
+                    return None;
+                }
+                Some(match offered[index.index(offered.len())].clone() {
+                    Action::GiveHint { id, .. } => Action::GiveHint {
+                        id,
+                        hint: text.clone(),
+                    },
+                    Action::Guess { id, .. } => Action::Guess {
+                        id,
+                        guess: text
+                            .clone()
+                            .or_else(|| state.phase.word().map(String::from)),
+                    },
+                    other => other,
+                })
+            }
+        }
+    }
+
+    fn check_properties(state: &State) -> Result<(), TestCaseError> {
+        match state.active_index {
+            Some(index) => prop_assert!(index < state.players.len()),
+            None => prop_assert!(state.players.is_empty()),
+        }
+        for player in state.players.iter() {
+            let id = match player.id {
+                Some(id) => id,
+                None => continue,
+            };
+            for action in state.list_actions(id) {
+                prop_assert!(
+                    state.clone().action(&action).is_some(),
+                    "{:?} is offered but rejected",
+                    action
+                );
+            }
+            state.audit_view(id).map_err(TestCaseError::fail)?;
+        }
+        state.audit_view(0).map_err(TestCaseError::fail)?;
+        Ok(())
+    }
+
+    proptest! {
+        // Failing cases are kept in common/proptest-regressions and replayed
+        // first on the next run, commit them along with the fix.
+        #![proptest_config(ProptestConfig::with_cases(200))]
+
+        #[test]
+        fn generated_games_keep_the_invariants(
+            steps in prop::collection::vec(any_step(), 0..150),
+            seed in any::<u64>(),
+        ) {
+            let mut state = State::new(small_library());
+            state.seed = seed;
+            for step in steps.iter() {
+                if let Some(action) = action_of(&state, step) {
+                    state.action(&action);
+                }
+                check_properties(&state)?;
+            }
+        }
+    }
 }
@@ -1,31 +1,90 @@
 #![recursion_limit = "256"]
 
 use anyhow::Error;
+use common::i18n::{Language, Text};
+use std::mem::discriminant;
+use std::time::Duration;
 use wasm_bindgen::prelude::*;
-use yew::format::Json;
 use yew::prelude::*;
-use yew::services::websocket::{WebSocketService, WebSocketStatus, WebSocketTask};
+use yew::services::storage::{Area, StorageService};
+use yew::services::timeout::{TimeoutService, TimeoutTask};
 
 extern crate common;
 use common::game;
 
+mod socket;
+use socket::{Socket, SocketStatus};
+
+/// Where the language chosen by the player is remembered.
+const LANGUAGE_KEY: &str = "beunique.language";
+
+/// The first of the browser's preferred languages we know, German if there is none.
+fn browser_language() -> Language {
+    let navigator = yew::utils::window().navigator();
+    navigator
+        .languages()
+        .iter()
+        .filter_map(|tag| tag.as_string())
+        .chain(navigator.language())
+        .find_map(|tag| Language::from_code(&tag))
+        .unwrap_or(Language::German)
+}
+
 struct Model {
     link: ComponentLink<Self>,
     state: game::PlayerView,
-    ws: Option<WebSocketTask>,
+    language: Language,
+    /// Remembers the language chosen by the player, if the browser lets us.
+    storage: Option<StorageService>,
+    notice: Option<Text>,
+    /// The server announced that it is going down, so losing the connection is expected.
+    shutting_down: bool,
+    ws: Option<Socket>,
+    /// The name we joined with, used to take the seat again after reconnecting.
+    name: Option<String>,
+    /// The connection was lost and we are trying to get back into the game.
+    reconnecting: bool,
+    /// Failed attempts since the connection was lost, determines the backoff.
+    reconnect_attempt: u32,
+    /// The next attempt, `None` while an attempt is underway.
+    retry: Option<TimeoutTask>,
+    retry_delay: Duration,
+    /// Whether the seat was asked for on this connection, see `WsReady`.
+    rejoined: bool,
+    /// Connections which were refused the seat since the connection was lost.
+    refused_rejoins: u32,
+    /// Text submitted while there was no connection, sent once we have our
+    /// seat back if the game still asks for it.
+    unsent: Vec<game::Action>,
 }
 
 pub enum WsAction {
     Connect,
     Disconnect,
-    Lost,
+    /// With the close code if the connection was closed.
+    Lost(Option<u16>),
+}
+
+const FIRST_RETRY: Duration = Duration::from_millis(500);
+const MAX_RETRY: Duration = Duration::from_secs(30);
+/// How many connections may be refused the seat before we stop reconnecting
+/// and the player joins under another name.
+const MAX_REFUSED_REJOINS: u32 = 3;
+
+/// Doubles with every failed attempt up to `MAX_RETRY`.
+fn retry_delay(attempt: u32) -> Duration {
+    FIRST_RETRY
+        .checked_mul(1 << attempt.min(16))
+        .map_or(MAX_RETRY, |delay| delay.min(MAX_RETRY))
 }
 
 enum Msg {
     Ignore,
     WsAction(WsAction),
-    WsReady(Result<game::PlayerView, Error>),
+    WsReady(Result<game::ServerMessage, Error>),
     WsSend(common::game::Action),
+    DismissNotice,
+    SetLanguage(Language),
 }
 
 impl From<WsAction> for Msg {
@@ -34,14 +93,70 @@ impl From<WsAction> for Msg {
     }
 }
 
+/// The listener which served the page accepts websocket connections as well,
+/// whichever address it was configured with. If the page came over https the
+/// backend has TLS enabled, so use wss as well. The room is named after the
+/// `#` of the page's address, the browser keeps it percent-encoded, without
+/// one the server picks its default room.
+fn websocket_url() -> String {
+    let location = yew::utils::window().location();
+    let scheme = match location.protocol() {
+        Ok(protocol) if protocol == "https:" => "wss",
+        _ => "ws",
+    };
+    let host = location
+        .host()
+        .unwrap_or_else(|_| "localhost:8080".to_string());
+    let room = location.hash().unwrap_or_default();
+    format!(
+        "{}://{}/websocket/{}",
+        scheme,
+        host,
+        room.trim_start_matches('#')
+    )
+}
+
+/// Whether `view` offers an action of the same kind as `action`.
+fn is_offered(view: &game::PlayerView, action: &game::Action) -> bool {
+    view.actions
+        .iter()
+        .any(|offered| discriminant(offered) == discriminant(action))
+}
+
+impl Model {
+    fn send(&mut self, action: &game::Action) {
+        if let Some(socket) = &self.ws {
+            let serialized = serde_json::to_string(action).ok().unwrap();
+            socket.send(&serialized);
+        }
+    }
+}
+
 impl Component for Model {
     type Message = Msg;
     type Properties = ();
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let storage = StorageService::new(Area::Local).ok();
+        let chosen = storage.as_ref().and_then(|storage| {
+            let code: Result<String, Error> = storage.restore(LANGUAGE_KEY);
+            code.ok().and_then(|code| Language::from_code(&code))
+        });
         Self {
             link,
             state: game::PlayerView::default(),
+            language: chosen.unwrap_or_else(browser_language),
+            storage,
+            notice: None,
+            shutting_down: false,
             ws: None,
+            name: None,
+            reconnecting: false,
+            reconnect_attempt: 0,
+            retry: None,
+            retry_delay: FIRST_RETRY,
+            rejoined: false,
+            refused_rejoins: 0,
+            unsent: vec![],
         }
     }
 
@@ -51,44 +166,159 @@ impl Component for Model {
             Msg::WsAction(action) => {
                 match action {
                     WsAction::Connect => {
-                        let callback = self.link.callback(|Json(data)| Msg::WsReady(data));
+                        let callback = self.link.callback(|text: String| {
+                            Msg::WsReady(serde_json::from_str(&text).map_err(Error::from))
+                        });
                         let notification = self.link.callback(|status| match status {
-                            WebSocketStatus::Opened => Msg::Ignore,
-                            WebSocketStatus::Closed | WebSocketStatus::Error => {
-                                WsAction::Lost.into()
-                            }
+                            SocketStatus::Closed(code) => WsAction::Lost(Some(code)),
+                            SocketStatus::Error => WsAction::Lost(None),
                         });
-                        let task = WebSocketService::connect(
-                            "ws://localhost:9001/websocket",
-                            callback,
-                            notification,
-                        )
-                        .unwrap();
-                        self.ws = Some(task);
+                        self.retry = None;
+                        match Socket::connect(&websocket_url(), callback, notification) {
+                            Ok(socket) => {
+                                self.ws = Some(socket);
+                                self.shutting_down = false;
+                                self.rejoined = false;
+                            }
+                            Err(e) => {
+                                log::warn!("can't connect: {:?}", e);
+                                self.link.send_message(WsAction::Lost(None));
+                            }
+                        }
                     }
                     WsAction::Disconnect => {
                         self.ws.take();
+                        self.reconnecting = false;
+                        self.retry = None;
                     }
-                    WsAction::Lost => {
+                    WsAction::Lost(code) => {
+                        if self.retry.is_some() {
+                            // closing after an error is reported twice
+                            return false;
+                        }
                         self.ws = None;
+                        if self.reconnecting {
+                            self.reconnect_attempt += 1;
+                        } else {
+                            self.reconnecting = true;
+                            self.reconnect_attempt = 0;
+                        }
+                        // the announcement may not have made it, the close code always does
+                        if self.shutting_down || code == Some(game::CLOSE_GOING_AWAY) {
+                            self.notice = Some(Text::Restarting);
+                        }
+                        self.retry_delay = retry_delay(self.reconnect_attempt);
+                        self.retry = Some(TimeoutService::spawn(
+                            self.retry_delay,
+                            self.link.callback(|_| WsAction::Connect),
+                        ));
                     }
                 };
                 true
             }
             Msg::WsReady(response) => {
                 match response {
-                    Ok(new_state) => self.state = new_state,
+                    Ok(game::ServerMessage::View { view: new_state }) if self.reconnecting => {
+                        match (&new_state.me, &self.name) {
+                            (None, Some(name)) if self.rejoined => {
+                                // the seat is still held by the old connection or was
+                                // taken by someone else, asking again would loop
+                                self.refused_rejoins += 1;
+                                if self.refused_rejoins < MAX_REFUSED_REJOINS {
+                                    self.ws = None;
+                                    self.link.send_message(WsAction::Lost(None));
+                                    return false;
+                                }
+                                // the old connection should be gone by now, so somebody
+                                // else has the seat
+                                self.notice = Some(Text::SeatTaken { name: name.clone() });
+                                self.name = None;
+                                self.unsent.clear();
+                                self.reconnecting = false;
+                                self.reconnect_attempt = 0;
+                                self.refused_rejoins = 0;
+                                self.state = *new_state;
+                            }
+                            (None, Some(name)) => {
+                                // keep showing the old state until we have our seat back,
+                                // so the page and whatever was typed into it stay as they are
+                                self.rejoined = true;
+                                let join = new_state.actions.iter().find_map(|a| match a {
+                                    game::Action::Join { id, .. } => Some(*id),
+                                    _ => None,
+                                });
+                                if let Some(id) = join {
+                                    self.send(&game::Action::Join {
+                                        id,
+                                        name: name.clone(),
+                                    });
+                                }
+                                return false;
+                            }
+                            (me, _) => {
+                                self.reconnecting = false;
+                                self.reconnect_attempt = 0;
+                                self.refused_rejoins = 0;
+                                if let Some(id) = me.as_ref().and_then(|me| me.id) {
+                                    for action in std::mem::take(&mut self.unsent) {
+                                        if is_offered(&new_state, &action) {
+                                            self.send(&action.with_id(id));
+                                        }
+                                    }
+                                }
+                                self.unsent.clear();
+                                self.state = *new_state;
+                            }
+                        }
+                    }
+                    Ok(game::ServerMessage::View { view: new_state }) => self.state = *new_state,
+                    Ok(game::ServerMessage::Notice { notice }) => {
+                        self.notice = Some(Text::Notice(notice))
+                    }
+                    Ok(game::ServerMessage::ShuttingDown { grace_seconds }) => {
+                        self.shutting_down = true;
+                        self.notice = Some(Text::ShuttingDown { grace_seconds });
+                    }
+                    Ok(game::ServerMessage::Rejected { reason }) => {
+                        self.notice = Some(Text::Rejected(reason))
+                    }
                     Err(_) => {}
                 }
                 true
             }
+            Msg::DismissNotice => {
+                self.notice = None;
+                true
+            }
+            Msg::SetLanguage(language) => {
+                self.language = language;
+                if let Some(storage) = &mut self.storage {
+                    storage.store(LANGUAGE_KEY, Ok(language.code().to_string()));
+                }
+                true
+            }
             Msg::WsSend(action) => {
-                match &mut self.ws {
-                    Some(task) => {
-                        let serialized = serde_json::to_string(&action).ok().unwrap();
-                        task.send(Ok(serialized));
+                if self.reconnecting {
+                    match action {
+                        // taken along with the seat
+                        game::Action::Join { name, .. } => self.name = Some(name),
+                        game::Action::GiveHint { .. }
+                        | game::Action::Guess { .. }
+                        | game::Action::SubmitWords { .. } => {
+                            let kind = discriminant(&action);
+                            self.unsent.retain(|a| discriminant(a) != kind);
+                            self.unsent.push(action);
+                        }
+                        // clicks were meant for a game which may have moved on
+                        _ => {}
                     }
-                    None => {}
+                } else if self.ws.is_some() {
+                    match &action {
+                        game::Action::Join { name, .. } => self.name = Some(name.clone()),
+                        game::Action::Leave { .. } => self.name = None,
+                        _ => {}
+                    }
+                    self.send(&action);
                 }
                 false
             }
@@ -103,6 +333,7 @@ impl Component for Model {
     }
 
     fn view(&self) -> Html {
+        let t = |text: Text| self.language.text(text);
         let to_html = |action: &common::game::Action| {
             let guess = match &self.state.phase {
                 game::VisibleGamePhase::Judging(game::VisibleJudging::Active(judging))
@@ -112,12 +343,15 @@ impl Component for Model {
                 _ => None,
             };
             match action {
-                common::game::Action::Join(id, _) => {
+                common::game::Action::Join { id, .. } => {
                     let cloned_id: usize = *id;
                     let send_name = move |e: ChangeData| match e {
                         ChangeData::Value(value) => {
                             if !value.trim().is_empty() {
-                                Msg::WsSend(common::game::Action::Join(cloned_id, value))
+                                Msg::WsSend(common::game::Action::Join {
+                                    id: cloned_id,
+                                    name: value,
+                                })
                             } else {
                                 Msg::Ignore
                             }
@@ -126,30 +360,31 @@ impl Component for Model {
                     };
                     html! {
                             <div>
-                                <label for="uname">{ "Mein Name: " }</label>
+                                <label for="uname">{ t(Text::MyName) }</label>
                                 <input type="text" id="uname" name="name" autocomplete="off" onchange=self.link.callback(send_name)/>
                             </div>
                     }
                 }
-                common::game::Action::DisconnectPlayer(_id) => {
+                common::game::Action::DisconnectPlayer { .. } => {
                     html! {
                         <div>
-                            { "Das sollte nicht passieren (Verbindung explizit getrennt). Bitte neu laden." }
+                            { t(Text::ExplicitlyDisconnected) }
                         </div>
                     }
                 }
-                common::game::Action::Start(id) => {
+                common::game::Action::Start { id } => {
                     let cloned_id: usize = *id;
-                    let send_start = move |_| Msg::WsSend(common::game::Action::Start(cloned_id));
+                    let send_start =
+                        move |_| Msg::WsSend(common::game::Action::Start { id: cloned_id });
                     html! {
                         <div>
                             <button onclick=self.link.callback(send_start) class="button actionbutton startbutton">
-                            {"Start"}
+                            { t(Text::Start) }
                             </button>
                         </div>
                     }
                 }
-                common::game::Action::GiveHint(id, _hint) => {
+                common::game::Action::GiveHint { id, .. } => {
                     let cloned_id: usize = *id;
                     let send_hint = move |e: ChangeData| match e {
                         ChangeData::Value(value) => {
@@ -159,7 +394,10 @@ impl Component for Model {
                             } else {
                                 Some(trimmed.to_string())
                             };
-                            Msg::WsSend(common::game::Action::GiveHint(cloned_id, hint))
+                            Msg::WsSend(common::game::Action::GiveHint {
+                                id: cloned_id,
+                                hint,
+                            })
                         }
                         _ => Msg::Ignore,
                     };
@@ -172,8 +410,8 @@ impl Component for Model {
                     let revoke_hint = if hint_exists {
                         let another_cloned_id: usize = *id;
                         html! {
-                            <button onclick=self.link.callback(move |_| Msg::WsSend(common::game::Action::GiveHint(another_cloned_id, None))) class="button deletebutton">
-                            {"Löschen"}
+                            <button onclick=self.link.callback(move |_| Msg::WsSend(common::game::Action::GiveHint { id: another_cloned_id, hint: None })) class="button deletebutton">
+                            { t(Text::Delete) }
                             </button>
                         }
                     } else {
@@ -181,13 +419,17 @@ impl Component for Model {
                     };
                     html! {
                         <div>
-                            <label for="hint">{ "Hinweis: " }</label>
+                            <label for="hint">{ t(Text::Hint) }</label>
                             <input type="text" id="hint" name="hint" autocomplete="off" onchange=self.link.callback(send_hint)/>
                             {revoke_hint}
                         </div>
                     }
                 }
-                common::game::Action::FilterHint(id, hint, change_validity_to) => {
+                common::game::Action::FilterHint {
+                    id,
+                    hint,
+                    allowed: change_validity_to,
+                } => {
                     // log::info!("to_html on FilterHint: {:?}, {:?}, {:?}", id, hint, valid);
                     let flip_hint = {
                         let id = *id;
@@ -200,11 +442,11 @@ impl Component for Model {
                             //     hint_loc,
                             //     valid_loc
                             // );
-                            Msg::WsSend(common::game::Action::FilterHint(
+                            Msg::WsSend(common::game::Action::FilterHint {
                                 id,
-                                hint.clone(),
-                                change_validity_to,
-                            ))
+                                hint: hint.clone(),
+                                allowed: change_validity_to,
+                            })
                         }
                     };
                     let is_valid = !change_validity_to;
@@ -220,10 +462,10 @@ impl Component for Model {
                             <input type="checkbox" class="pseudoradio" id={valid_id.clone()}
                                 name={hint} checked=is_valid disabled=is_valid
                                 style="padding-left: 5px;"
-                                onchange=self.link.callback(flip_hint.clone())/> <label for={valid_id.clone()}>{" gültig "}</label>
+                                onchange=self.link.callback(flip_hint.clone())/> <label for={valid_id.clone()}>{ t(Text::Valid) }</label>
                             <input type="checkbox" class="pseudoradio" id={invalid_id.clone()}
                                 name={hint} checked=!is_valid disabled=!is_valid
-                                onchange=self.link.callback(flip_hint.clone())/> <label for={invalid_id.clone()}>{" ungültig "}</label>
+                                onchange=self.link.callback(flip_hint.clone())/> <label for={invalid_id.clone()}>{ t(Text::Invalid) }</label>
                         </>
                     };
                     html! {
@@ -237,48 +479,56 @@ impl Component for Model {
                         </div>
                     }
                 }
-                common::game::Action::FinishHintFiltering(id) => {
+                common::game::Action::FinishHintFiltering { id } => {
                     let id = *id;
                     let send_finish_filtering =
-                        move |_| Msg::WsSend(common::game::Action::FinishHintFiltering(id));
+                        move |_| Msg::WsSend(common::game::Action::FinishHintFiltering { id });
                     html! {
                         <div>
                             <button onclick=self.link.callback(send_finish_filtering) class="button actionbutton proceedbutton">
-                            {"Hinweisbeurteilung abschliessen"}
+                            { t(Text::FinishFiltering) }
                             </button>
                         </div>
                     }
                 }
-                common::game::Action::Guess(id, _) => {
+                common::game::Action::Guess { id, .. } => {
                     let id: usize = *id;
                     let send_guess = move |e: ChangeData| match e {
-                        ChangeData::Value(value) => {
-                            Msg::WsSend(common::game::Action::Guess(id, Some(value)))
-                        }
+                        ChangeData::Value(value) => Msg::WsSend(common::game::Action::Guess {
+                            id,
+                            guess: Some(value),
+                        }),
                         _ => Msg::Ignore,
                     };
-                    let send_no_guess = move |_| Msg::WsSend(common::game::Action::Guess(id, None));
+                    let send_no_guess =
+                        move |_| Msg::WsSend(common::game::Action::Guess { id, guess: None });
                     html! {
                         <>
                         <div>
-                            <label for="hint">{ "Ich rate: " }</label>
+                            <label for="hint">{ t(Text::MyGuess) }</label>
                             <input type="text" id="guess" name="guess" autocomplete="off" onchange=self.link.callback(send_guess)/>
                         </div>
                         <div style="padding-top: 5px">
                             <button onclick=self.link.callback(send_no_guess) class="button actionbutton guessbutton">
-                            {"Keine Ahnung"}
+                            { t(Text::NoIdea) }
                             </button>
                         </div>
                         </>
                     }
                 }
-                common::game::Action::Judge(id, change_correct_to) => match guess {
+                common::game::Action::Judge {
+                    id,
+                    correct: change_correct_to,
+                } => match guess {
                     Some(guess) => {
                         let flip_judgement = {
                             let change_correct_to = *change_correct_to;
                             let id = *id;
                             move |_: yew::ChangeData| {
-                                Msg::WsSend(common::game::Action::Judge(id, change_correct_to))
+                                Msg::WsSend(common::game::Action::Judge {
+                                    id,
+                                    correct: change_correct_to,
+                                })
                             }
                         };
                         let is_correct = !change_correct_to;
@@ -292,11 +542,11 @@ impl Component for Model {
                                 <input type="checkbox" class="pseudoradio" id="valid"
                                     name={guess.clone()} checked=is_correct disabled=is_correct
                                     onchange=self.link.callback(flip_judgement)/>
-                                <label for="valid" style="padding-right: 5px;">{" richtig"}</label>
+                                <label for="valid" style="padding-right: 5px;">{ t(Text::Correct) }</label>
                                 <input type="checkbox" class="pseudoradio" id="invalid"
                                     name={guess.clone()} checked=!is_correct disabled=!is_correct
                                     onchange=self.link.callback(flip_judgement)/>
-                                <label for="invalid">{" falsch"}</label>
+                                <label for="invalid">{ t(Text::Wrong) }</label>
                             </>
                         };
                         html! {
@@ -311,22 +561,22 @@ impl Component for Model {
                         }
                     }
                     None => {
-                        eprintln!("judging action but there is no guess");
+                        log::warn!("judging action but there is no guess");
                         html! {
                             <></>
                         }
                     }
                 },
-                common::game::Action::FinishJudging(id) => {
+                common::game::Action::FinishJudging { id } => {
                     let id = *id;
                     let send_finish_judging =
-                        move |_| Msg::WsSend(common::game::Action::FinishJudging(id));
+                        move |_| Msg::WsSend(common::game::Action::FinishJudging { id });
                     let noguess = match guess {
                         Some(_) => html! {<></>},
                         None => {
                             html! {
                                 <div class="hintline">
-                                    {"Es wurde nicht geraten."}
+                                    { t(Text::NoGuess) }
                                 </div>
                             }
                         }
@@ -336,19 +586,160 @@ impl Component for Model {
                         { noguess }
                         <div>
                             <button onclick=self.link.callback(send_finish_judging) class="button actionbutton finishjudgingbutton">
-                            {"Runde abschliessen"}
+                            { t(Text::FinishRound) }
                             </button>
                         </div>
                         </>
                     }
                 }
-                common::game::Action::Leave(id) => {
+                common::game::Action::SelectPacks {
+                    id,
+                    packs: selected,
+                } => {
+                    let pack_line = |pack: &game::PackInfo| {
+                        let checked = selected.contains(&pack.name);
+                        let mut packs = selected.clone();
+                        if checked {
+                            packs.retain(|name| name != &pack.name);
+                        } else {
+                            packs.push(pack.name.clone());
+                        }
+                        let id = *id;
+                        let toggle = move |_: ChangeData| {
+                            Msg::WsSend(common::game::Action::SelectPacks {
+                                id,
+                                packs: packs.clone(),
+                            })
+                        };
+                        let checkbox_id = format!("pack_{}", pack.name);
+                        // at least one pack has to stay selected
+                        let last = checked && selected.len() == 1;
+                        html! {
+                            <div>
+                                <input type="checkbox" id={checkbox_id.clone()} checked=checked disabled=last
+                                    onchange=self.link.callback(toggle)/>
+                                <label for={checkbox_id}>{ t(Text::Pack(pack.clone())) }</label>
+                            </div>
+                        }
+                    };
+                    html! {
+                        <div class="packs">
+                            { t(Text::Packs) }
+                            { for self.state.packs.iter().map(pack_line) }
+                        </div>
+                    }
+                }
+                common::game::Action::SubmitWords { id, words: own } => {
+                    let id = *id;
+                    let word_line = |word: &String| {
+                        let mut rest = own.clone();
+                        rest.retain(|w| w != word);
+                        let remove = move |_| {
+                            Msg::WsSend(common::game::Action::SubmitWords {
+                                id,
+                                words: rest.clone(),
+                            })
+                        };
+                        html! {
+                            <div class="hintline">
+                                <div class="hintlabel">{ word }</div>
+                                <button onclick=self.link.callback(remove) class="button deletebutton">
+                                { t(Text::Delete) }
+                                </button>
+                            </div>
+                        }
+                    };
+                    let add_word = if own.len() < game::MAX_CUSTOM_WORDS {
+                        let own = own.clone();
+                        let add = move |e: ChangeData| match e {
+                            ChangeData::Value(value) => {
+                                let mut words = own.clone();
+                                words.push(value);
+                                Msg::WsSend(common::game::Action::SubmitWords { id, words })
+                            }
+                            _ => Msg::Ignore,
+                        };
+                        html! {
+                            <div>
+                                <label for="customword">{ t(Text::AddCustomWord) }</label>
+                                <input type="text" id="customword" name="customword" autocomplete="off" onchange=self.link.callback(add)/>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    };
+                    html! {
+                        <div class="customwords">
+                            { t(Text::CustomWords) }
+                            { for own.iter().map(word_line) }
+                            { add_word }
+                            { t(Text::CustomWordsInGame(self.state.custom_words)) }
+                        </div>
+                    }
+                }
+                common::game::Action::FlagWord { id, .. } => {
+                    let id = *id;
+                    let reason_button = |reason: &game::FlagReason| {
+                        let reason = *reason;
+                        let flag =
+                            move |_| Msg::WsSend(common::game::Action::FlagWord { id, reason });
+                        html! {
+                            <button onclick=self.link.callback(flag) class="button flagbutton">
+                            { t(Text::FlagReason(reason)) }
+                            </button>
+                        }
+                    };
+                    html! {
+                        <div class="flagword">
+                            { t(Text::FlagWord) }
+                            { for game::FlagReason::ALL.iter().map(reason_button) }
+                        </div>
+                    }
+                }
+                common::game::Action::AddBot { id, .. } => {
+                    let id = *id;
+                    let add_button = |difficulty: &game::Difficulty| {
+                        let difficulty = *difficulty;
+                        let add =
+                            move |_| Msg::WsSend(common::game::Action::AddBot { id, difficulty });
+                        html! {
+                            <button onclick=self.link.callback(add) class="button botbutton">
+                            { t(Text::AddBot(difficulty)) }
+                            </button>
+                        }
+                    };
+                    let difficulties = [
+                        game::Difficulty::Easy,
+                        game::Difficulty::Medium,
+                        game::Difficulty::Hard,
+                    ];
+                    html! {
+                        <div>
+                            { for difficulties.iter().map(add_button) }
+                        </div>
+                    }
+                }
+                common::game::Action::RemoveBot { id, name } => {
+                    let action = common::game::Action::RemoveBot {
+                        id: *id,
+                        name: name.clone(),
+                    };
+                    let remove = move |_| Msg::WsSend(action.clone());
+                    html! {
+                        <div>
+                            <button onclick=self.link.callback(remove) class="button deletebutton">
+                            { t(Text::RemoveBot(name.clone())) }
+                            </button>
+                        </div>
+                    }
+                }
+                common::game::Action::Leave { id } => {
                     let id = *id;
-                    let send_leave = move |_| Msg::WsSend(common::game::Action::Leave(id));
+                    let send_leave = move |_| Msg::WsSend(common::game::Action::Leave { id });
                     html! {
                         <div>
                             <button onclick=self.link.callback(send_leave) class="button leavebutton">
-                            { "Spiel verlassen" }
+                            { t(Text::Leave) }
                             </button>
                         </div>
                     }
@@ -360,7 +751,7 @@ impl Component for Model {
             .state
             .actions
             .iter()
-            .filter(|a| !matches!(a, game::Action::Leave(_)))
+            .filter(|a| !matches!(a, game::Action::Leave { .. }))
             .map(|action| to_html(action))
             .collect::<Vec<Html>>();
 
@@ -372,10 +763,13 @@ impl Component for Model {
             let list_item = |p: &game::Player| {
                 let mut content = p.name.clone();
                 if is_me(p) {
-                    content += " (ich)";
+                    content += &t(Text::Me);
+                }
+                if let Some(difficulty) = p.bot {
+                    content += &t(Text::Bot(difficulty));
                 }
                 if p.id.is_none() {
-                    content += " (Verbindung verloren)"
+                    content += &t(Text::ConnectionLost)
                 }
                 html! {
                     <li>
@@ -386,7 +780,7 @@ impl Component for Model {
             let list_players = if self.state.players.len() > 0 {
                 html! {
                     <p>
-                        { "Es spielen mit:" }
+                        { t(Text::PlayersHeading) }
                         <ul class="item-list">
                             { for self.state.players.iter().map(list_item) }
                         </ul>
@@ -395,7 +789,7 @@ impl Component for Model {
             } else {
                 html! {
                     <p>
-                        { "Es ist noch niemand hier." }
+                        { t(Text::NobodyHere) }
                     </p>
                 }
             };
@@ -411,15 +805,16 @@ impl Component for Model {
         } else {
             let n_success = self.state.past_rounds.iter().filter(|p| p.success).count();
             let n_total = self.state.past_rounds.len();
-            let summary = format!(
-                "Es wurde in {} von {} Runden richtig geraten.",
-                n_success, n_total
-            );
+            let summary = t(Text::Summary {
+                successes: n_success,
+                rounds: n_total,
+            });
             let list_item = |p: &game::PastRound| {
+                let (name, word) = (p.name.clone(), p.word.clone());
                 let verdict = if p.success {
-                    format!("{} hat \"{}\" erraten.", p.name, p.word)
+                    t(Text::Guessed { name, word })
                 } else {
-                    format!("{} hat \"{}\" nicht erraten.", p.name, p.word)
+                    t(Text::NotGuessed { name, word })
                 };
                 html! {
                     <li> { verdict } </li>
@@ -435,7 +830,7 @@ impl Component for Model {
             html! {
                 <>
                     <p>
-                    { "Was bisher geschah: "}
+                    { t(Text::History) }
                     </p>
                     { summary }
                     { list_rounds }
@@ -445,22 +840,13 @@ impl Component for Model {
 
         let prelude = match &self.state.phase {
             game::VisibleGamePhase::GatherPlayers => match self.state.me {
-                Some(_) => html! { { "Das Spiel hat noch nicht angefangen." } },
-                None => html! { { "Noch nicht angemeldet." }},
+                Some(_) => html! { { t(Text::NotStarted) } },
+                None => html! { { t(Text::NotJoined) } },
             },
             game::VisibleGamePhase::HintCollection(game::VisibleHintCollection::Active(
                 hint_collection,
             )) => {
-                let message = if hint_collection.players_done.len() > 1 {
-                    format!(
-                        "Es sind schon {} Hinweise eingegangen.",
-                        hint_collection.players_done.len()
-                    )
-                } else if hint_collection.players_done.len() == 1 {
-                    format!("Es ist schon 1 Hinweis eingegangen.")
-                } else {
-                    format!("Es sind noch keine Hinweise eingegangen.")
-                };
+                let message = t(Text::HintsReceived(hint_collection.players_done.len()));
                 html! {
                     { message }
                 }
@@ -470,27 +856,23 @@ impl Component for Model {
             )) => match &hint_collection.hint {
                 Some(hint) => {
                     html! {
-                        { format!("Du gibst den Hinweis \"{}\" für \"{}\".", hint.content, hint_collection.word) }
+                        { t(Text::YourHint { hint: hint.content.clone(), word: hint_collection.word.clone() }) }
                     }
                 }
                 None => {
-                    html! { { format!(
-                        "Bitte gib {} einen Hinweis für \"{}\".",
-                        hint_collection.active_player,
-                        hint_collection.word)
-                    }}
+                    html! { { t(Text::GiveHint {
+                        active_player: hint_collection.active_player.clone(),
+                        word: hint_collection.word.clone(),
+                    }) } }
                 }
             },
             game::VisibleGamePhase::HintFiltering(game::VisibleHintFiltering::Active(
                 hint_filtering,
-            )) => html! { { format!(
-                "Es werden doppelte Hinweise entfernt, aktuell sind {} übrig.",
-                hint_filtering.players_valid_hints.len()
-            ) } },
+            )) => html! { { t(Text::HintsLeft(hint_filtering.players_valid_hints.len())) } },
             game::VisibleGamePhase::HintFiltering(game::VisibleHintFiltering::Inactive(
                 game::InactiveHintFiltering { hint_filtering, .. },
             )) => {
-                html! { { format!("Welche Hinweise für \"{}\" sind gültig?", hint_filtering.word) } }
+                html! { { t(Text::WhichHintsValid { word: hint_filtering.word.clone() }) } }
             }
             game::VisibleGamePhase::Guessing(game::VisibleGuessing::Active(guessing)) => {
                 let render_hint = |hint: &game::VisibleHint| {
@@ -502,16 +884,16 @@ impl Component for Model {
                 };
                 html! {
                     <>
-                    { "Die Hinweise sind:" }
+                    { t(Text::TheHintsAre) }
                     <ul class="item-list">
                         { for self.state.players.iter().filter_map(|p| guessing.hints.get(&p.name)).map(|h| render_hint(h)  ) }
                     </ul>
-                    { "Welches Wort ist gesucht?" }
+                    { t(Text::WhichWord) }
                     </>
                 }
             }
             game::VisibleGamePhase::Guessing(game::VisibleGuessing::Inactive(j)) => {
-                html! { { format!("Wir warten bis {} geraten hat.", j.active_player) } }
+                html! { { t(Text::WaitingForGuess { active_player: j.active_player.clone() }) } }
             }
             game::VisibleGamePhase::Judging(game::VisibleJudging::Active(judging))
             | game::VisibleGamePhase::Judging(game::VisibleJudging::Inactive(
@@ -519,7 +901,7 @@ impl Component for Model {
             )) => {
                 let word = html! {
                     <p>
-                        {format!("Das gesuchte Wort war \"{}\".", judging.word)}
+                        { t(Text::TheWordWas { word: judging.word.clone() }) }
                     </p>
                 };
 
@@ -545,7 +927,7 @@ impl Component for Model {
                     <table class="allhints">
                         <thead>
                             <tr>
-                                <th colspan="2">{"Hinweise"}</th>
+                                <th colspan="2">{ t(Text::HintsHeading) }</th>
                             </tr>
                         </thead>
                         <tbody>
@@ -562,11 +944,69 @@ impl Component for Model {
             }
         };
 
-        if self.ws.is_none() {
+        let notice = match &self.notice {
+            Some(notice) => html! {
+                <div class="notice">
+                    { t(notice.clone()) }
+                    <button onclick=self.link.callback(|_| Msg::DismissNotice) class="button noticebutton">
+                    { t(Text::Ok) }
+                    </button>
+                </div>
+            },
+            None => html! {},
+        };
+
+        let language_menu = {
+            let option = |language: &Language| {
+                html! {
+                    <option value=language.code() selected=*language == self.language>
+                    { language.name() }
+                    </option>
+                }
+            };
+            let choose = |e: ChangeData| match e {
+                ChangeData::Select(select) => {
+                    Language::from_code(&select.value()).map_or(Msg::Ignore, Msg::SetLanguage)
+                }
+                _ => Msg::Ignore,
+            };
+            html! {
+                <div class="language">
+                    <label for="language">{ t(Text::Language) }{ ": " }</label>
+                    <select id="language" onchange=self.link.callback(choose)>
+                        { for Language::ALL.iter().map(option) }
+                    </select>
+                </div>
+            }
+        };
+
+        let reconnecting = if self.reconnecting {
+            let status = if self.retry.is_some() {
+                t(Text::ReconnectIn {
+                    seconds: self.retry_delay.as_secs_f64().ceil() as u64,
+                })
+            } else {
+                t(Text::Reconnecting)
+            };
+            html! {
+                <div class="notice reconnecting">
+                    { status }
+                    <button onclick=self.link.callback(|_| WsAction::Connect) class="button noticebutton">
+                    { t(Text::ConnectNow) }
+                    </button>
+                </div>
+            }
+        } else {
+            html! {}
+        };
+
+        if self.ws.is_none() && !self.reconnecting {
             html! {
                 <div>
+                { notice }
+                { language_menu }
                 <button onclick=self.link.callback(|_| WsAction::Connect) class="button connectbutton">
-                { "Verbinden" }
+                { t(Text::Connect) }
                 </button>
                 </div>
             }
@@ -575,7 +1015,7 @@ impl Component for Model {
                 .state
                 .actions
                 .iter()
-                .filter(|a| matches!(a, game::Action::Leave(_)))
+                .filter(|a| matches!(a, game::Action::Leave { .. }))
                 .map(|a| to_html(a))
                 .next()
                 .unwrap_or(html! {});
@@ -584,6 +1024,8 @@ impl Component for Model {
                     // <p>
                     //     { format!("{:#?}", state) }
                     // </p>
+                    { notice }
+                    { reconnecting }
                     <div class="prelude">
                         { prelude }
                     </div>
@@ -599,6 +1041,7 @@ impl Component for Model {
                     <div class="leave">
                         { leave }
                     </div>
+                    { language_menu }
                 </div>
             }
         }
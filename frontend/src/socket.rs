@@ -0,0 +1,69 @@
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CloseEvent, Event, MessageEvent, WebSocket};
+use yew::Callback;
+
+/// What happened to a connection.
+pub enum SocketStatus {
+    /// Closed with the close code of RFC 6455, 1006 if the connection just
+    /// went away without a close frame.
+    Closed(u16),
+    Error,
+}
+
+/// A websocket carrying text frames. Unlike yew's `WebSocketTask` it reports
+/// the close code, so we can tell a restarting server from a lost connection.
+/// Closed when dropped.
+pub struct Socket {
+    ws: WebSocket,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+    _on_error: Closure<dyn FnMut(Event)>,
+}
+
+impl Socket {
+    pub fn connect(
+        url: &str,
+        on_text: Callback<String>,
+        on_status: Callback<SocketStatus>,
+    ) -> Result<Self, JsValue> {
+        let ws = WebSocket::new(url)?;
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            if let Some(text) = event.data().as_string() {
+                on_text.emit(text);
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+        let on_closed = on_status.clone();
+        let on_close = Closure::wrap(Box::new(move |event: CloseEvent| {
+            on_closed.emit(SocketStatus::Closed(event.code()));
+        }) as Box<dyn FnMut(CloseEvent)>);
+        let on_error = Closure::wrap(Box::new(move |_: Event| {
+            on_status.emit(SocketStatus::Error);
+        }) as Box<dyn FnMut(Event)>);
+        ws.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        ws.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        ws.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        Ok(Self {
+            ws,
+            _on_message: on_message,
+            _on_close: on_close,
+            _on_error: on_error,
+        })
+    }
+
+    pub fn send(&self, text: &str) {
+        if let Err(e) = self.ws.send_with_str(text) {
+            log::warn!("can't send: {:?}", e);
+        }
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        // the closures go away with us, so nothing may call them afterwards
+        self.ws.set_onmessage(None);
+        self.ws.set_onclose(None);
+        self.ws.set_onerror(None);
+        let _ = self.ws.close();
+    }
+}
@@ -0,0 +1,115 @@
+use common::game::{Action, GamePhase, HintCollection, HintFiltering, Judging, State};
+use std::collections::HashSet;
+
+/// What has to hold after every action, `before` is the game before it.
+/// Views are built for everyone on the way, so a panic in `get_view` shows up
+/// as well.
+pub fn check(before: &State, state: &State) -> Result<(), String> {
+    players(state)?;
+    phase(state)?;
+    if state.past_rounds.len() < before.past_rounds.len() && !state.past_rounds.is_empty() {
+        return Err("past rounds got lost".to_string());
+    }
+    for player in state.players.iter() {
+        if let Some(id) = player.id {
+            view(state, id)?;
+            offered_actions(state, id)?;
+        }
+    }
+    Ok(())
+}
+
+fn players(state: &State) -> Result<(), String> {
+    match state.active_index {
+        None if !state.players.is_empty() => return Err("players but no active player".to_string()),
+        Some(index) if index >= state.players.len() => {
+            return Err(format!(
+                "active index {} with {} players",
+                index,
+                state.players.len()
+            ))
+        }
+        _ => {}
+    }
+    let mut names = HashSet::new();
+    let mut ids = HashSet::new();
+    for player in state.players.iter() {
+        if !names.insert(&player.name) {
+            return Err(format!("{} is seated twice", player.name));
+        }
+        if let Some(id) = player.id {
+            if !ids.insert(id) {
+                return Err(format!("id {} is used twice", id));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn phase(state: &State) -> Result<(), String> {
+    let word = match state.phase.word() {
+        Some(word) => word,
+        None => return Ok(()),
+    };
+    if state.players.len() < 2 {
+        return Err(format!(
+            "{} with {} players",
+            state.phase.name(),
+            state.players.len()
+        ));
+    }
+    if state.dictionary.find(word).is_none() {
+        return Err(format!("{} isn't in the dictionary", word));
+    }
+    if !state.history.contains(word) {
+        return Err(format!("{} isn't in the history", word));
+    }
+    let active = &state.players[state.active_index.unwrap_or(0)].name;
+    let hints = match &state.phase {
+        GamePhase::HintCollection(HintCollection { hints, .. }) => {
+            if hints.len() >= state.players.len() - 1 {
+                return Err(format!("{} hints are still being collected", hints.len()));
+            }
+            hints
+        }
+        GamePhase::HintFiltering(HintFiltering { hints, .. })
+        | GamePhase::Judging(Judging { hints, .. }) => hints,
+        _ => return Ok(()),
+    };
+    for author in hints.keys() {
+        if author == active {
+            return Err(format!("hint from the active player {}", author));
+        }
+        if !state.players.iter().any(|p| &p.name == author) {
+            return Err(format!("hint from {} who isn't playing", author));
+        }
+    }
+    Ok(())
+}
+
+/// Everyone sees themselves and nothing of the current round that their
+/// role hides, see `State::audit_view`.
+fn view(state: &State, id: usize) -> Result<(), String> {
+    let view = state.get_view(id);
+    if view.me.as_ref().and_then(|me| me.id) != Some(id) {
+        return Err(format!("view for {} shows {:?}", id, view.me));
+    }
+    state.audit_view(id)
+}
+
+/// Everything offered to a player is accepted.
+fn offered_actions(state: &State, id: usize) -> Result<(), String> {
+    for action in state.list_actions(id) {
+        if matches!(action, Action::Join { .. }) {
+            return Err(format!("{} is seated and offered {:?}", id, action));
+        }
+        if state.clone().action(&action).is_none() {
+            return Err(format!(
+                "{:?} is offered in {} but rejected",
+                action,
+                state.phase.name()
+            ));
+        }
+    }
+    Ok(())
+}
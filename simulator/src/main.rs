@@ -0,0 +1,325 @@
+//! Plays many complete games against `common::game::State` without a server
+//! or a browser. Players join, leave, lose their connection and come back at
+//! random, the invariants of the game are checked after every action and
+//! panics and stuck games are reported with the seed to replay them.
+
+mod invariants;
+mod players;
+
+use common::game::{Action, Library, State, Word, WordPack};
+use players::{Strategy, Table};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::cell::RefCell;
+use std::panic::AssertUnwindSafe;
+
+const USAGE: &str = "\
+usage: simulator [options]
+
+  --games N            number of games (100)
+  --seed N             seed of the first game, game i uses seed + i (random)
+  --strategy S         scripted (players follow the rules) or random (10% of
+                       the actions are made up and may be invalid) (scripted)
+  --min-players N      players needed before the game is started (2)
+  --max-players N      most players at the table (6)
+  --rounds N           a game is over after this many rounds (20)
+  --max-actions N      a game is stopped after this many actions (2000)
+  --leave-rate P       chance that a player leaves instead of acting (0.01)
+  --disconnect-rate P  chance that a player loses the connection (0.03)
+  --reconnect-rate P   chance that a disconnected player comes back (0.3)
+  --join-rate P        chance that another player joins (0.02)
+";
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub games: u64,
+    pub seed: u64,
+    pub strategy: Strategy,
+    pub min_players: usize,
+    pub max_players: usize,
+    pub rounds: usize,
+    pub max_actions: usize,
+    pub leave_rate: f64,
+    pub disconnect_rate: f64,
+    pub reconnect_rate: f64,
+    pub join_rate: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            seed: rand::random::<u32>() as u64,
+            strategy: Strategy::Scripted,
+            min_players: 2,
+            max_players: 6,
+            rounds: 20,
+            max_actions: 2000,
+            leave_rate: 0.01,
+            disconnect_rate: 0.03,
+            reconnect_rate: 0.3,
+            join_rate: 0.02,
+        }
+    }
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+            let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+            value
+                .parse()
+                .map_err(|_| format!("invalid value for {}: {}", flag, value))
+        }
+        let mut options = Self::default();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--games" => options.games = value(&flag, args.next())?,
+                "--seed" => options.seed = value(&flag, args.next())?,
+                "--strategy" => {
+                    options.strategy = match value::<String>(&flag, args.next())?.as_str() {
+                        "scripted" => Strategy::Scripted,
+                        "random" => Strategy::Random,
+                        other => return Err(format!("unknown strategy {}", other)),
+                    }
+                }
+                "--min-players" => options.min_players = value(&flag, args.next())?,
+                "--max-players" => options.max_players = value(&flag, args.next())?,
+                "--rounds" => options.rounds = value(&flag, args.next())?,
+                "--max-actions" => options.max_actions = value(&flag, args.next())?,
+                "--leave-rate" => options.leave_rate = value(&flag, args.next())?,
+                "--disconnect-rate" => options.disconnect_rate = value(&flag, args.next())?,
+                "--reconnect-rate" => options.reconnect_rate = value(&flag, args.next())?,
+                "--join-rate" => options.join_rate = value(&flag, args.next())?,
+                "--help" | "-h" => return Err(String::new()),
+                other => return Err(format!("unknown option {}", other)),
+            }
+        }
+        if options.min_players < 2 || options.max_players < options.min_players {
+            return Err("need 2 <= min-players <= max-players".to_string());
+        }
+        Ok(options)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub games: u64,
+    pub actions: u64,
+    pub accepted: u64,
+    pub rounds: u64,
+    pub guessed: u64,
+    pub joins: u64,
+    pub leaves: u64,
+    pub disconnects: u64,
+    pub reconnects: u64,
+    pub restarts: u64,
+}
+
+#[derive(Debug)]
+pub enum Failure {
+    Panic(String),
+    Invariant(String),
+    /// Nobody can move the game on, not even after reconnecting.
+    Stuck,
+}
+
+#[derive(Debug)]
+pub struct FailedGame {
+    pub seed: u64,
+    pub actions: usize,
+    pub failure: Failure,
+    /// The last actions before the failure, oldest first.
+    pub recent: Vec<Action>,
+}
+
+/// How many actions a failure report shows.
+const RECENT_ACTIONS: usize = 10;
+
+thread_local! {
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Forty made-up words, so a word showing up in a view can't be a
+/// coincidence.
+fn library() -> Library {
+    let words = (0..40)
+        .map(|n| Word::from(format!("wort{}", n).as_str()))
+        .collect();
+    let more = (0..10)
+        .map(|n| Word::from(format!("begriff{}", n).as_str()))
+        .collect();
+    Library::new(vec![
+        WordPack::new("woerter", "de", words),
+        WordPack::new("begriffe", "de", more),
+    ])
+}
+
+/// Play one game, the result says why it failed.
+pub fn play(options: &Options, seed: u64, stats: &mut Stats) -> Result<(), FailedGame> {
+    let mut state = State::new(library());
+    state.seed = seed;
+    let mut table = Table::new(options, ChaCha8Rng::seed_from_u64(seed));
+    let mut recent: Vec<Action> = vec![];
+    let failed = |actions, failure, recent: &[Action]| FailedGame {
+        seed,
+        actions,
+        failure,
+        recent: recent.to_vec(),
+    };
+    for count in 0..options.max_actions {
+        let action = match table.next_action(&state) {
+            Some(action) => action,
+            None => return Err(failed(count, Failure::Stuck, &recent)),
+        };
+        recent.push(action.clone());
+        if recent.len() > RECENT_ACTIONS {
+            recent.remove(0);
+        }
+        let before = state.clone();
+        let outcome = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let result = state.action(&action);
+            invariants::check(&before, &state).map(|()| result)
+        }));
+        match outcome {
+            Err(_) => {
+                let message = LAST_PANIC.with(|last| last.borrow_mut().take());
+                let message = message.unwrap_or_else(|| "unknown panic".to_string());
+                return Err(failed(count + 1, Failure::Panic(message), &recent));
+            }
+            Ok(Err(violation)) => {
+                return Err(failed(count + 1, Failure::Invariant(violation), &recent))
+            }
+            Ok(Ok(result)) => {
+                stats.actions += 1;
+                if result.is_some() {
+                    stats.accepted += 1;
+                    table.record(&action, &before, &state, stats);
+                }
+            }
+        }
+        if state.past_rounds.len() >= options.rounds {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("{}", message);
+            }
+            eprint!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    std::panic::set_hook(Box::new(|info| {
+        LAST_PANIC.with(|last| *last.borrow_mut() = Some(info.to_string()));
+    }));
+
+    let mut stats = Stats::default();
+    let mut failures = vec![];
+    for game in 0..options.games {
+        let seed = options.seed.wrapping_add(game);
+        stats.games += 1;
+        if let Err(failed) = play(&options, seed, &mut stats) {
+            failures.push(failed);
+        }
+    }
+
+    println!(
+        "{} games with {:?} players from seed {}, {} failed",
+        stats.games,
+        options.strategy,
+        options.seed,
+        failures.len()
+    );
+    println!(
+        "actions: {} applied, {} accepted, {} rejected",
+        stats.actions,
+        stats.accepted,
+        stats.actions - stats.accepted
+    );
+    println!(
+        "rounds: {}, guessed: {} ({:.0}%)",
+        stats.rounds,
+        stats.guessed,
+        100.0 * stats.guessed as f64 / stats.rounds.max(1) as f64
+    );
+    println!(
+        "players: {} joined, {} left, {} disconnected, {} reconnected, {} games back in the lobby",
+        stats.joins, stats.leaves, stats.disconnects, stats.reconnects, stats.restarts
+    );
+    for failed in failures.iter() {
+        println!();
+        match &failed.failure {
+            Failure::Panic(message) => println!(
+                "seed {}: panic after {} actions: {}",
+                failed.seed, failed.actions, message
+            ),
+            Failure::Invariant(violation) => println!(
+                "seed {}: broken invariant after {} actions: {}",
+                failed.seed, failed.actions, violation
+            ),
+            Failure::Stuck => println!(
+                "seed {}: stuck after {} actions",
+                failed.seed, failed.actions
+            ),
+        }
+        for action in failed.recent.iter() {
+            println!("    {:?}", action);
+        }
+    }
+    if !failures.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(strategy: Strategy) -> Stats {
+        let options = Options {
+            games: 10,
+            seed: 1,
+            strategy,
+            ..Options::default()
+        };
+        let mut stats = Stats::default();
+        for game in 0..options.games {
+            if let Err(failed) = play(&options, options.seed + game, &mut stats) {
+                panic!("{:?}", failed);
+            }
+        }
+        stats
+    }
+
+    #[test]
+    fn scripted_games_finish() {
+        let stats = run(Strategy::Scripted);
+        assert!(stats.rounds >= 10 * 10);
+        assert!(stats.guessed > 0 && stats.guessed < stats.rounds);
+        assert!(stats.leaves > 0 && stats.reconnects > 0);
+    }
+
+    #[test]
+    fn random_games_keep_the_invariants() {
+        let stats = run(Strategy::Random);
+        assert!(stats.accepted < stats.actions);
+    }
+
+    #[test]
+    fn options_are_parsed() {
+        let args = ["--games", "3", "--strategy", "random", "--max-players", "4"];
+        let options = Options::parse(args.iter().map(|a| a.to_string())).unwrap();
+        assert_eq!(options.games, 3);
+        assert_eq!(options.strategy, Strategy::Random);
+        assert_eq!(options.max_players, 4);
+        let args = ["--min-players", "1"];
+        assert!(Options::parse(args.iter().map(|a| a.to_string())).is_err());
+    }
+}
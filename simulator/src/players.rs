@@ -0,0 +1,303 @@
+use super::{Options, Stats};
+use common::game::{Action, Difficulty, FlagReason, GamePhase, Player, State};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Players do what the game offers them and nothing else.
+    Scripted,
+    /// Players pick any offered action with random content, and now and then
+    /// one which wasn't offered at all.
+    Random,
+}
+
+/// Share of made-up actions of the random strategy.
+const MADE_UP: f64 = 0.1;
+
+/// Whether the action moves the game on, as opposed to leaving or choosing packs.
+fn progresses(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::Start { .. }
+            | Action::GiveHint { .. }
+            | Action::FilterHint { .. }
+            | Action::FinishHintFiltering { .. }
+            | Action::Guess { .. }
+            | Action::Judge { .. }
+            | Action::FinishJudging { .. }
+    )
+}
+
+/// The people around the table: who joins, leaves, loses the connection and
+/// what everyone plays.
+pub struct Table {
+    options: Options,
+    rng: ChaCha8Rng,
+    next_id: usize,
+    next_name: usize,
+}
+
+impl Table {
+    pub fn new(options: &Options, rng: ChaCha8Rng) -> Self {
+        Self {
+            options: options.clone(),
+            rng,
+            next_id: 1,
+            next_name: 1,
+        }
+    }
+
+    fn new_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        items.choose(&mut self.rng)
+    }
+
+    /// The next thing happening at the table, none if the game is stuck.
+    pub fn next_action(&mut self, state: &State) -> Option<Action> {
+        let people: Vec<&Player> = state
+            .players
+            .iter()
+            .filter(|p| p.bot.is_none() && p.id.is_some())
+            .collect();
+        let away: Vec<&Player> = state.players.iter().filter(|p| p.id.is_none()).collect();
+        let options = self.options.clone();
+
+        let event: f64 = self.rng.gen();
+        if event < options.leave_rate {
+            if let Some(player) = self.pick(&people) {
+                return Some(Action::Leave { id: player.id? });
+            }
+        } else if event < options.leave_rate + options.disconnect_rate {
+            if let Some(player) = self.pick(&people) {
+                return Some(Action::DisconnectPlayer { id: player.id? });
+            }
+        }
+        if !away.is_empty() && self.rng.gen_bool(options.reconnect_rate) {
+            return self.reconnect(&away);
+        }
+        let seated = state.players.len();
+        if seated < options.max_players
+            && (seated < options.min_players || self.rng.gen_bool(options.join_rate))
+        {
+            return Some(self.join());
+        }
+
+        let movers: Vec<(usize, Vec<Action>)> = state
+            .players
+            .iter()
+            .filter_map(|p| p.id)
+            .map(|id| (id, state.list_actions(id)))
+            .filter(|(_, actions)| actions.iter().any(progresses))
+            .collect();
+        if movers.is_empty() {
+            // wait for someone to come back, or for enough players
+            if !away.is_empty() {
+                return self.reconnect(&away);
+            }
+            if seated < options.max_players {
+                return Some(self.join());
+            }
+            return None;
+        }
+        match options.strategy {
+            Strategy::Scripted => {
+                let (_, actions) = self.pick(&movers)?.clone();
+                Some(self.scripted(state, actions))
+            }
+            Strategy::Random => {
+                if self.rng.gen_bool(MADE_UP) {
+                    return Some(self.made_up(state));
+                }
+                // leaving is up to the leave rate
+                let offered: Vec<Action> = state
+                    .players
+                    .iter()
+                    .filter_map(|p| p.id)
+                    .flat_map(|id| state.list_actions(id))
+                    .filter(|a| !matches!(a, Action::Leave { .. }))
+                    .collect();
+                let action = self.pick(&offered)?.clone();
+                Some(self.fill_in(state, action))
+            }
+        }
+    }
+
+    fn join(&mut self) -> Action {
+        let name = format!("p{}", self.next_name);
+        self.next_name += 1;
+        Action::Join {
+            id: self.new_id(),
+            name,
+        }
+    }
+
+    fn reconnect(&mut self, away: &[&Player]) -> Option<Action> {
+        let name = self.pick(away)?.name.clone();
+        Some(Action::Join {
+            id: self.new_id(),
+            name,
+        })
+    }
+
+    /// Start, hint, finish filtering, guess and finish judging in that
+    /// order. Now and then a hint is filtered or a judgement changed first.
+    fn scripted(&mut self, state: &State, actions: Vec<Action>) -> Action {
+        let detour = actions
+            .iter()
+            .find(|a| matches!(a, Action::FilterHint { .. } | Action::Judge { .. }))
+            .cloned();
+        if let Some(detour) = &detour {
+            if self.rng.gen_bool(0.2) {
+                return detour.clone();
+            }
+        }
+        let next = actions
+            .into_iter()
+            .find(|a| {
+                progresses(a) && !matches!(a, Action::FilterHint { .. } | Action::Judge { .. })
+            })
+            .or(detour)
+            .expect("a player who can move");
+        match next {
+            Action::GiveHint { id, .. } => Action::GiveHint {
+                id,
+                hint: Some(format!("hinweis{}", self.rng.gen_range(0..8))),
+            },
+            Action::Guess { id, .. } => {
+                let guess = match state.phase.word() {
+                    Some(word) if self.rng.gen_bool(0.5) => word.to_string(),
+                    _ => "falsch".to_string(),
+                };
+                Action::Guess {
+                    id,
+                    guess: Some(guess),
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// An offered action with random content.
+    fn fill_in(&mut self, state: &State, action: Action) -> Action {
+        match action {
+            Action::GiveHint { id, .. } => Action::GiveHint {
+                id,
+                hint: self.text(state),
+            },
+            Action::Guess { id, .. } => Action::Guess {
+                id,
+                guess: self.text(state),
+            },
+            Action::SelectPacks { id, .. } => {
+                let packs = ["woerter", "begriffe", "fehlt"]
+                    .iter()
+                    .filter(|_| self.rng.gen_bool(0.5))
+                    .map(|name| name.to_string())
+                    .collect();
+                Action::SelectPacks { id, packs }
+            }
+            Action::SubmitWords { id, .. } => {
+                let count = self.rng.gen_range(0..4);
+                let words = (0..count)
+                    .map(|_| format!("eigen{}", self.rng.gen_range(0..6)))
+                    .collect();
+                Action::SubmitWords { id, words }
+            }
+            Action::AddBot { id, .. } => {
+                let difficulties = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+                Action::AddBot {
+                    id,
+                    difficulty: *self.pick(&difficulties).unwrap(),
+                }
+            }
+            Action::FlagWord { id, .. } => Action::FlagWord {
+                id,
+                reason: *self.pick(&FlagReason::ALL).unwrap(),
+            },
+            other => other,
+        }
+    }
+
+    /// Hints and guesses: none, the word, or something else.
+    fn text(&mut self, state: &State) -> Option<String> {
+        match self.rng.gen_range(0..4) {
+            0 => None,
+            1 => state.phase.word().map(str::to_string),
+            _ => Some(format!("text{}", self.rng.gen_range(0..5))),
+        }
+    }
+
+    /// Any action from anyone, offered or not.
+    fn made_up(&mut self, state: &State) -> Action {
+        let id = self.rng.gen_range(0..self.next_id + 2);
+        let name = state
+            .players
+            .choose(&mut self.rng)
+            .map(|p| p.name.clone())
+            .unwrap_or_default();
+        let action = match self.rng.gen_range(0..14) {
+            0 => Action::Join {
+                id,
+                name: name.clone(),
+            },
+            1 => Action::DisconnectPlayer { id },
+            2 => Action::Start { id },
+            3 => Action::GiveHint { id, hint: None },
+            4 => Action::FilterHint {
+                id,
+                hint: format!("text{}", self.rng.gen_range(0..5)),
+                allowed: false,
+            },
+            5 => Action::FinishHintFiltering { id },
+            6 => Action::Guess { id, guess: None },
+            7 => Action::Judge {
+                id,
+                correct: self.rng.gen(),
+            },
+            8 => Action::FinishJudging { id },
+            9 => Action::SelectPacks { id, packs: vec![] },
+            10 => Action::SubmitWords { id, words: vec![] },
+            11 => Action::FlagWord {
+                id,
+                reason: FlagReason::Unknown,
+            },
+            12 => Action::AddBot {
+                id,
+                difficulty: Difficulty::Easy,
+            },
+            _ => Action::RemoveBot { id, name },
+        };
+        self.fill_in(state, action)
+    }
+
+    /// Count what an accepted action did.
+    pub fn record(&mut self, action: &Action, before: &State, after: &State, stats: &mut Stats) {
+        match action {
+            Action::Join { name, .. } => match before.players.iter().find(|p| &p.name == name) {
+                Some(player) if player.id.is_none() => stats.reconnects += 1,
+                Some(_) => {}
+                None => stats.joins += 1,
+            },
+            Action::Leave { .. } => stats.leaves += 1,
+            Action::DisconnectPlayer { .. } => stats.disconnects += 1,
+            _ => {}
+        }
+        if after.past_rounds.len() > before.past_rounds.len() {
+            stats.rounds += 1;
+            if after.past_rounds.last().is_some_and(|round| round.success) {
+                stats.guessed += 1;
+            }
+        }
+        if matches!(after.phase, GamePhase::GatherPlayers)
+            && !matches!(before.phase, GamePhase::GatherPlayers)
+        {
+            stats.restarts += 1;
+        }
+    }
+}
@@ -0,0 +1,932 @@
+//! The client without the terminal: what is shown, what the keys do and what
+//! is sent, so it can be tested without a server or a screen.
+
+use common::game::{
+    Action, Difficulty, FlagReason, InactiveJudging, PlayerView, ServerMessage, VisibleGamePhase,
+    VisibleHintCollection, VisibleJudging, CLOSE_GOING_AWAY, MAX_CUSTOM_WORDS,
+};
+use common::i18n::{Language, Text};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::collections::HashMap;
+use std::mem::discriminant;
+use std::time::{Duration, Instant};
+
+const FIRST_RETRY: Duration = Duration::from_millis(500);
+const MAX_RETRY: Duration = Duration::from_secs(30);
+/// How many connections may be refused the seat before we stop reconnecting
+/// and the player joins under another name.
+const MAX_REFUSED_REJOINS: u32 = 3;
+
+/// Doubles with every failed attempt up to `MAX_RETRY`.
+pub fn retry_delay(attempt: u32) -> Duration {
+    FIRST_RETRY
+        .checked_mul(1 << attempt.min(16))
+        .map_or(MAX_RETRY, |delay| delay.min(MAX_RETRY))
+}
+
+/// Something to type, sent with Enter.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Field {
+    Name(usize),
+    Hint(usize),
+    Guess(usize),
+    /// A new custom word, added to the ones already submitted.
+    CustomWord(usize, Vec<String>),
+}
+
+impl Field {
+    pub fn label(&self) -> Text {
+        match self {
+            Field::Name(_) => Text::MyName,
+            Field::Hint(_) => Text::Hint,
+            Field::Guess(_) => Text::MyGuess,
+            Field::CustomWord(..) => Text::AddCustomWord,
+        }
+    }
+
+    /// The same field for the connection `id`.
+    fn with_id(self, id: usize) -> Self {
+        match self {
+            Field::Name(_) => Field::Name(id),
+            Field::Hint(_) => Field::Hint(id),
+            Field::Guess(_) => Field::Guess(id),
+            Field::CustomWord(_, own) => Field::CustomWord(id, own),
+        }
+    }
+
+    /// The action for what was typed, none if there is nothing to send.
+    /// An empty hint takes back the one given before.
+    fn submit(&self, typed: &str) -> Option<Action> {
+        let typed = typed.trim();
+        let text = if typed.is_empty() {
+            None
+        } else {
+            Some(typed.to_string())
+        };
+        match self {
+            Field::Name(id) => Some(Action::Join {
+                id: *id,
+                name: text?,
+            }),
+            Field::Hint(id) => Some(Action::GiveHint {
+                id: *id,
+                hint: text,
+            }),
+            Field::Guess(id) => Some(Action::Guess {
+                id: *id,
+                guess: Some(text?),
+            }),
+            Field::CustomWord(id, own) => {
+                let mut words = own.clone();
+                words.push(text?);
+                Some(Action::SubmitWords { id: *id, words })
+            }
+        }
+    }
+}
+
+/// A line below the prelude, one for every input, button and checkbox the
+/// frontend shows.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Control {
+    Input(Field),
+    Button(String, Action),
+    /// Switching sends the action, none if it can't be switched.
+    Checkbox {
+        label: String,
+        checked: bool,
+        action: Option<Action>,
+    },
+    Label(String),
+}
+
+impl Control {
+    fn focusable(&self) -> bool {
+        !matches!(self, Control::Label(_))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connection {
+    Connecting,
+    Connected,
+    /// The next attempt is due at the instant.
+    Waiting(Instant),
+}
+
+/// What the main loop has to do after a key.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Effect {
+    Send(Action),
+    /// Try again without waiting for the backoff.
+    Connect,
+    /// Drop the connection and try again after the backoff.
+    Reconnect,
+    Quit,
+}
+
+pub struct App {
+    pub view: PlayerView,
+    pub language: Language,
+    pub notice: Option<Text>,
+    pub connection: Connection,
+    /// The server announced that it is going down, so losing the connection is expected.
+    shutting_down: bool,
+    /// The name we joined with, used to take the seat again after reconnecting.
+    name: Option<String>,
+    /// The connection was lost and we are trying to get back into the game.
+    pub reconnecting: bool,
+    /// Failed attempts since the connection was lost, determines the backoff.
+    attempt: u32,
+    /// Whether the seat was asked for on this connection, see `receive`.
+    rejoined: bool,
+    /// Connections which were refused the seat since the connection was lost.
+    refused_rejoins: u32,
+    /// Text submitted while there was no connection, sent once we have our
+    /// seat back if the game still asks for it.
+    unsent: Vec<Action>,
+    /// Index of the focused control, see `focus`.
+    focus: usize,
+    /// What was typed into every field so far.
+    typed: HashMap<Field, String>,
+}
+
+impl App {
+    pub fn new(language: Language) -> Self {
+        Self {
+            view: PlayerView::default(),
+            language,
+            notice: None,
+            connection: Connection::Connecting,
+            shutting_down: false,
+            name: None,
+            reconnecting: false,
+            attempt: 0,
+            rejoined: false,
+            refused_rejoins: 0,
+            unsent: vec![],
+            focus: 0,
+            typed: HashMap::new(),
+        }
+    }
+
+    pub fn text(&self, text: Text) -> String {
+        self.language.text(text)
+    }
+
+    pub fn typed(&self, field: &Field) -> &str {
+        self.typed.get(field).map_or("", String::as_str)
+    }
+
+    pub fn connected(&mut self) {
+        self.connection = Connection::Connected;
+        self.shutting_down = false;
+        self.rejoined = false;
+    }
+
+    /// The connection is gone or couldn't be made, returns how long to wait
+    /// before the next attempt. `close_code` is the code of the close frame
+    /// if the server sent one.
+    pub fn lost(&mut self, close_code: Option<u16>) -> Duration {
+        if self.reconnecting {
+            self.attempt += 1;
+        } else {
+            self.reconnecting = true;
+            self.attempt = 0;
+        }
+        if self.shutting_down || close_code == Some(CLOSE_GOING_AWAY) {
+            self.notice = Some(Text::Restarting);
+        }
+        let delay = retry_delay(self.attempt);
+        self.connection = Connection::Waiting(Instant::now() + delay);
+        delay
+    }
+
+    /// Takes in a message from the server, returns what has to be done about it.
+    pub fn receive(&mut self, message: ServerMessage) -> Vec<Effect> {
+        match message {
+            ServerMessage::View { view } if self.reconnecting => match (&view.me, &self.name) {
+                (None, Some(name)) if self.rejoined => {
+                    // the seat is still held by the old connection or was
+                    // taken by someone else, asking again would loop
+                    self.refused_rejoins += 1;
+                    if self.refused_rejoins < MAX_REFUSED_REJOINS {
+                        return vec![Effect::Reconnect];
+                    }
+                    // the old connection should be gone by now, so somebody
+                    // else has the seat
+                    self.notice = Some(Text::SeatTaken { name: name.clone() });
+                    self.name = None;
+                    self.unsent.clear();
+                    self.reconnecting = false;
+                    self.attempt = 0;
+                    self.refused_rejoins = 0;
+                    self.show(*view);
+                    return vec![];
+                }
+                (None, Some(name)) => {
+                    // keep showing the old view until we have our seat back
+                    self.rejoined = true;
+                    let join = view.actions.iter().find_map(|a| match a {
+                        Action::Join { id, .. } => Some(*id),
+                        _ => None,
+                    });
+                    return join
+                        .map(|id| {
+                            Effect::Send(Action::Join {
+                                id,
+                                name: name.clone(),
+                            })
+                        })
+                        .into_iter()
+                        .collect();
+                }
+                (me, _) => {
+                    self.reconnecting = false;
+                    self.attempt = 0;
+                    self.refused_rejoins = 0;
+                    let unsent = std::mem::take(&mut self.unsent);
+                    let mut resend = vec![];
+                    if let Some(id) = me.as_ref().and_then(|me| me.id) {
+                        // what was typed stays in the fields of the new seat
+                        self.typed = std::mem::take(&mut self.typed)
+                            .into_iter()
+                            .map(|(field, typed)| (field.with_id(id), typed))
+                            .collect();
+                        resend = unsent
+                            .into_iter()
+                            .filter(|action| is_offered(&view, action))
+                            .map(|action| Effect::Send(action.with_id(id)))
+                            .collect();
+                    }
+                    self.show(*view);
+                    return resend;
+                }
+            },
+            ServerMessage::View { view } => self.show(*view),
+            ServerMessage::Notice { notice } => self.notice = Some(Text::Notice(notice)),
+            ServerMessage::ShuttingDown { grace_seconds } => {
+                self.shutting_down = true;
+                self.notice = Some(Text::ShuttingDown { grace_seconds });
+            }
+            ServerMessage::Rejected { reason } => self.notice = Some(Text::Rejected(reason)),
+        }
+        vec![]
+    }
+
+    /// The focus goes back to the top when the controls change their kind,
+    /// say from the lobby to giving a hint.
+    fn show(&mut self, view: PlayerView) {
+        let screen = |view: &PlayerView| {
+            (
+                discriminant(&view.phase),
+                view.actions.first().map(discriminant),
+            )
+        };
+        if screen(&view) != screen(&self.view) {
+            self.focus = 0;
+        }
+        self.view = view;
+    }
+
+    /// Whether `action` can be sent now or kept until we are back. Clicks
+    /// while there is no connection were meant for a game which may have
+    /// moved on, only text is kept.
+    fn can_act(&self, action: &Action) -> bool {
+        if self.reconnecting {
+            matches!(
+                action,
+                Action::Join { .. }
+                    | Action::GiveHint { .. }
+                    | Action::Guess { .. }
+                    | Action::SubmitWords { .. }
+            )
+        } else {
+            self.connection == Connection::Connected
+        }
+    }
+
+    /// The action to send now, none if it is kept until we are back.
+    fn act(&mut self, action: Action) -> Option<Action> {
+        if self.reconnecting {
+            match action {
+                // taken along with the seat
+                Action::Join { name, .. } => self.name = Some(name),
+                action => {
+                    let kind = discriminant(&action);
+                    self.unsent.retain(|a| discriminant(a) != kind);
+                    self.unsent.push(action);
+                }
+            }
+            return None;
+        }
+        match &action {
+            Action::Join { name, .. } => self.name = Some(name.clone()),
+            Action::Leave { .. } => self.name = None,
+            _ => {}
+        }
+        Some(action)
+    }
+
+    fn guess(&self) -> Option<&String> {
+        match &self.view.phase {
+            VisibleGamePhase::Judging(VisibleJudging::Active(judging))
+            | VisibleGamePhase::Judging(VisibleJudging::Inactive(InactiveJudging {
+                judging,
+                ..
+            })) => judging.guess.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The controls for the offered actions in the frontend's order, leaving
+    /// the game comes last.
+    pub fn controls(&self) -> Vec<Control> {
+        let t = |text: Text| self.text(text);
+        let (leave, actions): (Vec<&Action>, Vec<&Action>) = self
+            .view
+            .actions
+            .iter()
+            .partition(|a| matches!(a, Action::Leave { .. }));
+        let mut controls = vec![];
+        for action in actions.into_iter().chain(leave) {
+            match action {
+                Action::Join { id, .. } => controls.push(Control::Input(Field::Name(*id))),
+                Action::DisconnectPlayer { .. } => {
+                    controls.push(Control::Label(t(Text::ExplicitlyDisconnected)))
+                }
+                Action::Start { .. } => {
+                    controls.push(Control::Button(t(Text::Start), action.clone()))
+                }
+                Action::GiveHint { id, .. } => {
+                    controls.push(Control::Input(Field::Hint(*id)));
+                    let hint_given = matches!(
+                        &self.view.phase,
+                        VisibleGamePhase::HintCollection(VisibleHintCollection::Inactive(collection))
+                            if collection.hint.is_some()
+                    );
+                    if hint_given {
+                        let revoke = Action::GiveHint {
+                            id: *id,
+                            hint: None,
+                        };
+                        controls.push(Control::Button(t(Text::Delete), revoke));
+                    }
+                }
+                Action::FilterHint {
+                    hint,
+                    allowed: change_validity_to,
+                    ..
+                } => {
+                    let valid = !change_validity_to;
+                    let verdict = t(if valid { Text::Valid } else { Text::Invalid });
+                    controls.push(Control::Checkbox {
+                        label: format!("{} ({})", hint, verdict.trim()),
+                        checked: valid,
+                        action: Some(action.clone()),
+                    });
+                }
+                Action::FinishHintFiltering { .. } => {
+                    controls.push(Control::Button(t(Text::FinishFiltering), action.clone()))
+                }
+                Action::Guess { id, .. } => {
+                    controls.push(Control::Input(Field::Guess(*id)));
+                    let no_idea = Action::Guess {
+                        id: *id,
+                        guess: None,
+                    };
+                    controls.push(Control::Button(t(Text::NoIdea), no_idea));
+                }
+                Action::Judge {
+                    correct: change_correct_to,
+                    ..
+                } => {
+                    if let Some(guess) = self.guess() {
+                        let correct = !change_correct_to;
+                        let verdict = t(if correct { Text::Correct } else { Text::Wrong });
+                        controls.push(Control::Checkbox {
+                            label: format!("{} ({})", guess, verdict.trim()),
+                            checked: correct,
+                            action: Some(action.clone()),
+                        });
+                    }
+                }
+                Action::FinishJudging { .. } => {
+                    if self.guess().is_none() {
+                        controls.push(Control::Label(t(Text::NoGuess)));
+                    }
+                    controls.push(Control::Button(t(Text::FinishRound), action.clone()));
+                }
+                Action::SelectPacks {
+                    id,
+                    packs: selected,
+                } => {
+                    controls.push(Control::Label(t(Text::Packs)));
+                    for pack in self.view.packs.iter() {
+                        let checked = selected.contains(&pack.name);
+                        let mut packs = selected.clone();
+                        if checked {
+                            packs.retain(|name| name != &pack.name);
+                        } else {
+                            packs.push(pack.name.clone());
+                        }
+                        // at least one pack has to stay selected
+                        let last = checked && selected.len() == 1;
+                        controls.push(Control::Checkbox {
+                            label: t(Text::Pack(pack.clone())),
+                            checked,
+                            action: Some(Action::SelectPacks { id: *id, packs }).filter(|_| !last),
+                        });
+                    }
+                }
+                Action::SubmitWords { id, words: own } => {
+                    controls.push(Control::Label(t(Text::CustomWords)));
+                    for word in own.iter() {
+                        let mut rest = own.clone();
+                        rest.retain(|w| w != word);
+                        let label = format!("{}: {}", word, t(Text::Delete));
+                        controls.push(Control::Button(
+                            label,
+                            Action::SubmitWords {
+                                id: *id,
+                                words: rest,
+                            },
+                        ));
+                    }
+                    if own.len() < MAX_CUSTOM_WORDS {
+                        controls.push(Control::Input(Field::CustomWord(*id, own.clone())));
+                    }
+                    let in_game = t(Text::CustomWordsInGame(self.view.custom_words));
+                    controls.push(Control::Label(in_game));
+                }
+                Action::FlagWord { id, .. } => {
+                    controls.push(Control::Label(t(Text::FlagWord)));
+                    for reason in FlagReason::ALL.iter() {
+                        let flag = Action::FlagWord {
+                            id: *id,
+                            reason: *reason,
+                        };
+                        controls.push(Control::Button(t(Text::FlagReason(*reason)), flag));
+                    }
+                }
+                Action::AddBot { id, .. } => {
+                    for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+                        let add = Action::AddBot {
+                            id: *id,
+                            difficulty,
+                        };
+                        controls.push(Control::Button(t(Text::AddBot(difficulty)), add));
+                    }
+                }
+                Action::RemoveBot { name, .. } => {
+                    let label = t(Text::RemoveBot(name.clone()));
+                    controls.push(Control::Button(label, action.clone()));
+                }
+                Action::Leave { .. } => {
+                    controls.push(Control::Button(t(Text::Leave), action.clone()))
+                }
+            }
+        }
+        controls
+    }
+
+    /// The focused control: the one at the remembered index, or the next
+    /// focusable one, or the last focusable one if there is none after it.
+    pub fn focus(&self, controls: &[Control]) -> Option<usize> {
+        let focusable = |i: &usize| controls[*i].focusable();
+        (self.focus..controls.len())
+            .find(focusable)
+            .or_else(|| (0..controls.len().min(self.focus)).rev().find(focusable))
+    }
+
+    fn move_focus(&mut self, forward: bool) {
+        let controls = self.controls();
+        let current = match self.focus(&controls) {
+            Some(current) => current,
+            None => return,
+        };
+        let n = controls.len();
+        let next = (1..n)
+            .map(|step| {
+                if forward {
+                    (current + step) % n
+                } else {
+                    (current + n - step) % n
+                }
+            })
+            .find(|i| controls[*i].focusable());
+        self.focus = next.unwrap_or(current);
+    }
+
+    pub fn key(&mut self, key: KeyEvent) -> Option<Effect> {
+        if key.kind == KeyEventKind::Release {
+            return None;
+        }
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') if control => return Some(Effect::Quit),
+            KeyCode::Esc if self.notice.take().is_none() => return Some(Effect::Quit),
+            KeyCode::Esc => return None,
+            KeyCode::F(2) => {
+                let next = Language::ALL
+                    .iter()
+                    .cycle()
+                    .skip_while(|language| **language != self.language)
+                    .nth(1);
+                self.language = *next.unwrap_or(&self.language);
+                return None;
+            }
+            KeyCode::F(5) => return Some(Effect::Connect),
+            KeyCode::Up | KeyCode::BackTab => {
+                self.move_focus(false);
+                return None;
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                self.move_focus(true);
+                return None;
+            }
+            _ => {}
+        }
+        let controls = self.controls();
+        let focus = self.focus(&controls)?;
+        let (action, submitted) = match (&controls[focus], key.code) {
+            (Control::Input(field), KeyCode::Enter) => {
+                (field.submit(self.typed(field)), Some(field))
+            }
+            (Control::Input(field), KeyCode::Char(c)) if !control => {
+                self.typed.entry(field.clone()).or_default().push(c);
+                return None;
+            }
+            (Control::Input(field), KeyCode::Backspace) => {
+                if let Some(typed) = self.typed.get_mut(field) {
+                    typed.pop();
+                }
+                return None;
+            }
+            (Control::Button(_, action), KeyCode::Enter | KeyCode::Char(' '))
+            | (
+                Control::Checkbox {
+                    action: Some(action),
+                    ..
+                },
+                KeyCode::Enter | KeyCode::Char(' '),
+            ) => (Some(action.clone()), None),
+            _ => return None,
+        };
+        // what was typed stays if it can't be sent
+        let action = action.filter(|action| self.can_act(action))?;
+        if let Some(field) = submitted {
+            self.typed.remove(field);
+        }
+        self.act(action).map(Effect::Send)
+    }
+}
+
+/// Whether `view` offers an action of the same kind as `action`.
+fn is_offered(view: &PlayerView, action: &Action) -> bool {
+    view.actions
+        .iter()
+        .any(|offered| discriminant(offered) == discriminant(action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::{Library, State, WordPack};
+
+    fn press(app: &mut App, code: KeyCode) -> Option<Effect> {
+        app.key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn focused(app: &App) -> Control {
+        let controls = app.controls();
+        controls[app.focus(&controls).unwrap()].clone()
+    }
+
+    fn type_in(app: &mut App, text: &str) {
+        for c in text.chars() {
+            assert_eq!(press(app, KeyCode::Char(c)), None);
+        }
+    }
+
+    /// Applies what the app sends and hands it the new view.
+    fn play(state: &mut State, app: &mut App, effect: Option<Effect>) {
+        match effect {
+            Some(Effect::Send(action)) => {
+                state.action(&action).expect("accepted");
+            }
+            other => panic!("expected an action, got {:?}", other),
+        }
+        let id = app.view.me.as_ref().and_then(|me| me.id).unwrap_or(1);
+        app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(id)),
+        });
+    }
+
+    fn game() -> State {
+        State::new(Library::new(vec![WordPack::new(
+            "test",
+            "de",
+            vec!["Geheimnis".into()],
+        )]))
+    }
+
+    #[test]
+    fn a_player_joins_starts_and_guesses_with_the_keyboard() {
+        let mut state = game();
+        let mut app = App::new(Language::English);
+        app.connected();
+        app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(1)),
+        });
+        assert_eq!(app.controls(), vec![Control::Input(Field::Name(1))]);
+        assert_eq!(press(&mut app, KeyCode::Enter), None);
+        type_in(&mut app, "ab");
+        press(&mut app, KeyCode::Backspace);
+        type_in(&mut app, " ");
+        let effect = press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            effect,
+            Some(Effect::Send(Action::Join {
+                id: 1,
+                name: "a".to_string()
+            }))
+        );
+        play(&mut state, &mut app, effect);
+        state
+            .action(&Action::Join {
+                id: 2,
+                name: "b".to_string(),
+            })
+            .unwrap();
+        app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(1)),
+        });
+
+        // start comes right before leaving, at the bottom
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Up);
+        let effect = press(&mut app, KeyCode::Enter);
+        assert_eq!(effect, Some(Effect::Send(Action::Start { id: 1 })));
+        play(&mut state, &mut app, effect);
+        state
+            .action(&Action::GiveHint {
+                id: 2,
+                hint: Some("Versteck".to_string()),
+            })
+            .unwrap();
+        state
+            .action(&Action::FinishHintFiltering { id: 2 })
+            .unwrap();
+        app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(1)),
+        });
+
+        // the focus is back at the top, typing, going down to "no idea" and
+        // back keeps the text
+        type_in(&mut app, "Geheimnis");
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.typed(&Field::Guess(1)), "Geheimnis");
+        let effect = press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            effect,
+            Some(Effect::Send(Action::Guess {
+                id: 1,
+                guess: Some("Geheimnis".to_string())
+            }))
+        );
+        play(&mut state, &mut app, effect);
+        assert_eq!(app.guess().map(String::as_str), Some("Geheimnis"));
+        assert_eq!(app.typed(&Field::Guess(1)), "");
+    }
+
+    #[test]
+    fn labels_are_skipped_and_the_last_pack_stays_selected() {
+        let mut state = State::new(Library::new(vec![
+            WordPack::new("eins", "de", vec!["Geheimnis".into()]),
+            WordPack::new("zwei", "de", vec!["Rätsel".into()]),
+        ]));
+        state
+            .action(&Action::Join {
+                id: 1,
+                name: "a".to_string(),
+            })
+            .unwrap();
+        let mut app = App::new(Language::German);
+        app.connected();
+        app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(1)),
+        });
+        let checkbox = |app: &App| {
+            let controls = app.controls();
+            match &controls[app.focus(&controls).unwrap()] {
+                Control::Checkbox {
+                    checked, action, ..
+                } => (*checked, action.clone()),
+                other => panic!("focus on {:?}", other),
+            }
+        };
+
+        // the "word packs" label at the top is skipped, going up wraps around
+        let unselect_eins = Action::SelectPacks {
+            id: 1,
+            packs: vec!["zwei".to_string()],
+        };
+        assert_eq!(checkbox(&app), (true, Some(unselect_eins.clone())));
+        press(&mut app, KeyCode::Up);
+        assert_eq!(
+            press(&mut app, KeyCode::Enter),
+            Some(Effect::Send(Action::Leave { id: 1 }))
+        );
+        press(&mut app, KeyCode::Down);
+        let effect = press(&mut app, KeyCode::Char(' '));
+        assert_eq!(effect, Some(Effect::Send(unselect_eins)));
+        play(&mut state, &mut app, effect);
+
+        // the only selected pack can't be unticked
+        press(&mut app, KeyCode::Down);
+        assert_eq!(checkbox(&app), (true, None));
+        assert_eq!(press(&mut app, KeyCode::Char(' ')), None);
+
+        press(&mut app, KeyCode::Down);
+        type_in(&mut app, "Wort");
+        let effect = press(&mut app, KeyCode::Enter);
+        let words = Action::SubmitWords {
+            id: 1,
+            words: vec!["Wort".to_string()],
+        };
+        assert_eq!(effect, Some(Effect::Send(words)));
+    }
+
+    #[test]
+    fn the_seat_is_taken_again_after_reconnecting() {
+        let mut state = game();
+        let mut app = App::new(Language::English);
+        app.connected();
+        app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(1)),
+        });
+        type_in(&mut app, "a");
+        let effect = press(&mut app, KeyCode::Enter);
+        play(&mut state, &mut app, effect);
+        state
+            .action(&Action::Join {
+                id: 2,
+                name: "b".to_string(),
+            })
+            .unwrap();
+        app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(1)),
+        });
+
+        state.action(&Action::DisconnectPlayer { id: 1 }).unwrap();
+        assert_eq!(app.lost(None), FIRST_RETRY);
+        assert_eq!(app.lost(None), FIRST_RETRY * 2);
+        assert_eq!(app.notice, None);
+        // a click while there is no connection is dropped, text is kept
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(press(&mut app, KeyCode::Enter), None);
+        while !matches!(focused(&app), Control::Input(Field::CustomWord(..))) {
+            press(&mut app, KeyCode::Down);
+        }
+        type_in(&mut app, "Wort");
+        assert_eq!(press(&mut app, KeyCode::Enter), None);
+        type_in(&mut app, "Rest");
+        app.connected();
+        let join = Action::Join {
+            id: 3,
+            name: "a".to_string(),
+        };
+        let rejoin = app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(3)),
+        });
+        assert_eq!(rejoin, vec![Effect::Send(join.clone())]);
+        state.action(&join).unwrap();
+        let unsent = app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(3)),
+        });
+        let words = Action::SubmitWords {
+            id: 3,
+            words: vec!["Wort".to_string()],
+        };
+        assert_eq!(unsent, vec![Effect::Send(words)]);
+        assert!(!app.reconnecting);
+        assert_eq!(app.typed(&Field::CustomWord(3, vec![])), "Rest");
+    }
+
+    #[test]
+    fn a_seat_still_held_is_asked_for_once_per_connection() {
+        let mut state = game();
+        let mut app = App::new(Language::English);
+        app.connected();
+        app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(1)),
+        });
+        type_in(&mut app, "a");
+        let effect = press(&mut app, KeyCode::Enter);
+        play(&mut state, &mut app, effect);
+
+        // the server hasn't noticed yet that the old connection is gone
+        assert_eq!(app.lost(None), FIRST_RETRY);
+        app.connected();
+        let view = |state: &State, id: usize| ServerMessage::View {
+            view: Box::new(state.get_view(id)),
+        };
+        let join = Action::Join {
+            id: 2,
+            name: "a".to_string(),
+        };
+        assert_eq!(
+            app.receive(view(&state, 2)),
+            vec![Effect::Send(join.clone())]
+        );
+        assert!(state.action(&join).is_none());
+        state
+            .action(&Action::Join {
+                id: 3,
+                name: "b".to_string(),
+            })
+            .unwrap();
+        assert_eq!(app.receive(view(&state, 2)), vec![Effect::Reconnect]);
+        assert_eq!(app.lost(None), FIRST_RETRY * 2);
+
+        state.action(&Action::DisconnectPlayer { id: 1 }).unwrap();
+        app.connected();
+        let join = join.with_id(4);
+        assert_eq!(
+            app.receive(view(&state, 4)),
+            vec![Effect::Send(join.clone())]
+        );
+        state.action(&join).unwrap();
+        assert_eq!(app.receive(view(&state, 4)), vec![]);
+        assert!(!app.reconnecting);
+    }
+
+    #[test]
+    fn a_seat_taken_by_someone_else_is_given_up() {
+        let mut state = game();
+        let mut app = App::new(Language::English);
+        app.connected();
+        app.receive(ServerMessage::View {
+            view: Box::new(state.get_view(1)),
+        });
+        type_in(&mut app, "a");
+        let effect = press(&mut app, KeyCode::Enter);
+        play(&mut state, &mut app, effect);
+        state.action(&Action::DisconnectPlayer { id: 1 }).unwrap();
+        state
+            .action(&Action::Join {
+                id: 2,
+                name: "a".to_string(),
+            })
+            .unwrap();
+
+        app.lost(None);
+        for id in 3..3 + MAX_REFUSED_REJOINS as usize {
+            app.connected();
+            let view = || ServerMessage::View {
+                view: Box::new(state.get_view(id)),
+            };
+            assert_eq!(
+                app.receive(view()),
+                vec![Effect::Send(Action::Join {
+                    id,
+                    name: "a".to_string()
+                })]
+            );
+            if id < 2 + MAX_REFUSED_REJOINS as usize {
+                assert_eq!(app.receive(view()), vec![Effect::Reconnect]);
+                app.lost(None);
+            } else {
+                assert_eq!(app.receive(view()), vec![]);
+            }
+        }
+        assert!(!app.reconnecting);
+        assert_eq!(
+            app.notice,
+            Some(Text::SeatTaken {
+                name: "a".to_string()
+            })
+        );
+        assert_eq!(app.view.me, None);
+    }
+
+    #[test]
+    fn escape_dismisses_the_notice_before_quitting() {
+        let mut app = App::new(Language::German);
+        app.receive(ServerMessage::ShuttingDown { grace_seconds: 30 });
+        app.lost(None);
+        assert_eq!(app.notice, Some(Text::Restarting));
+        app.notice = None;
+        // the announcement was missed, the close code tells as well
+        app.connected();
+        app.lost(Some(CLOSE_GOING_AWAY));
+        assert_eq!(app.notice, Some(Text::Restarting));
+        assert_eq!(press(&mut app, KeyCode::Esc), None);
+        assert_eq!(press(&mut app, KeyCode::F(2)), None);
+        assert_eq!(app.language, Language::English);
+        assert_eq!(press(&mut app, KeyCode::Esc), Some(Effect::Quit));
+    }
+}
@@ -0,0 +1,244 @@
+//! Plays the game in a terminal, for example over SSH. Talks to the same
+//! websocket as the frontend, shows everything it shows and is used with the
+//! keyboard only.
+
+mod app;
+mod ui;
+
+use app::{App, Connection, Effect};
+use common::game::{Action, ServerMessage};
+use common::i18n::Language;
+use futures::{SinkExt, StreamExt};
+use ratatui::crossterm::event::{self, Event};
+use ratatui::DefaultTerminal;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+const USAGE: &str = "\
+usage: terminal [options] [url]
+
+  url              websocket of the backend, ws:// or wss://
+                   (ws://localhost:9001/websocket), add /<room> to play
+                   in a room other than the default one
+  --language L     de or en (from LC_ALL, LC_MESSAGES or LANG, else de)
+
+keys: up and down select, Enter confirms, Space ticks, F2 switches the
+language, F5 connects right away, Esc dismisses a notice or quits
+";
+
+/// How often the countdown to the next attempt is redrawn.
+const TICK: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone)]
+struct Options {
+    url: String,
+    language: Language,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            url: "ws://localhost:9001/websocket".to_string(),
+            language: ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|name| std::env::var(name).ok())
+                .find(|value| !value.is_empty())
+                .and_then(|value| Language::from_code(&value))
+                .unwrap_or(Language::German),
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--language" => {
+                    let code = args.next().ok_or("--language needs a value")?;
+                    options.language = Language::from_code(&code)
+                        .ok_or_else(|| format!("unknown language {}", code))?;
+                }
+                "--help" | "-h" => return Err(String::new()),
+                url if url.starts_with("ws://") || url.starts_with("wss://") => {
+                    options.url = url.to_string()
+                }
+                other => return Err(format!("unknown option {}", other)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// How a connection ended.
+#[derive(Debug, PartialEq, Eq)]
+enum Ending {
+    /// With the code of the close frame, if there was one.
+    Lost(Option<u16>),
+    Quit,
+}
+
+/// What an event from the terminal means, the channel closing means quitting.
+fn effect(app: &mut App, event: Option<Event>) -> Option<Effect> {
+    match event {
+        Some(Event::Key(key)) => app.key(key),
+        Some(_) => None,
+        None => Some(Effect::Quit),
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("{}", message);
+            }
+            eprint!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let mut terminal = ratatui::init();
+    // crossterm blocks while reading, so it gets a thread of its own
+    let (sender, mut events) = unbounded_channel();
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    let result = run(&mut terminal, &options, &mut events).await;
+    ratatui::restore();
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Connects, plays until the connection is lost and tries again until the
+/// player quits.
+async fn run(
+    terminal: &mut DefaultTerminal,
+    options: &Options,
+    events: &mut UnboundedReceiver<Event>,
+) -> io::Result<()> {
+    let mut app = App::new(options.language);
+    loop {
+        app.connection = Connection::Connecting;
+        terminal.draw(|frame| ui::draw(frame, &app))?;
+        let connect = tokio_tungstenite::connect_async(options.url.as_str());
+        tokio::pin!(connect);
+        let connected = loop {
+            tokio::select! {
+                result = &mut connect => break result.ok(),
+                event = events.recv() => {
+                    if effect(&mut app, event) == Some(Effect::Quit) {
+                        return Ok(());
+                    }
+                    terminal.draw(|frame| ui::draw(frame, &app))?;
+                }
+            }
+        };
+        let mut close_code = None;
+        if let Some((socket, _)) = connected {
+            app.connected();
+            match play(terminal, &mut app, socket, events).await? {
+                Ending::Quit => return Ok(()),
+                Ending::Lost(code) => close_code = code,
+            }
+        }
+
+        let retry = tokio::time::sleep(app.lost(close_code));
+        tokio::pin!(retry);
+        let mut tick = tokio::time::interval(TICK);
+        loop {
+            terminal.draw(|frame| ui::draw(frame, &app))?;
+            tokio::select! {
+                _ = &mut retry => break,
+                _ = tick.tick() => {}
+                event = events.recv() => match effect(&mut app, event) {
+                    Some(Effect::Quit) => return Ok(()),
+                    Some(Effect::Connect) => break,
+                    // kept by the app until we are back
+                    Some(Effect::Send(_)) | Some(Effect::Reconnect) | None => {}
+                },
+            }
+        }
+    }
+}
+
+async fn play<S>(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    socket: WebSocketStream<S>,
+    events: &mut UnboundedReceiver<Event>,
+) -> io::Result<Ending>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut sink, mut stream) = socket.split();
+    let send = |action: &Action| Message::text(serde_json::to_string(action).unwrap());
+    loop {
+        terminal.draw(|frame| ui::draw(frame, app))?;
+        tokio::select! {
+            message = stream.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    // messages of a newer server we don't understand are skipped
+                    if let Ok(message) = serde_json::from_str::<ServerMessage>(&text) {
+                        for effect in app.receive(message) {
+                            match effect {
+                                Effect::Send(action) => {
+                                    let _ = sink.send(send(&action)).await;
+                                }
+                                Effect::Reconnect => {
+                                    let _ = sink.close().await;
+                                    return Ok(Ending::Lost(None));
+                                }
+                                Effect::Connect | Effect::Quit => {}
+                            }
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(frame))) => {
+                    return Ok(Ending::Lost(frame.map(|frame| frame.code.into())));
+                }
+                Some(Err(_)) | None => return Ok(Ending::Lost(None)),
+                Some(Ok(_)) => {}
+            },
+            event = events.recv() => match effect(app, event) {
+                Some(Effect::Quit) => {
+                    let _ = sink.close().await;
+                    return Ok(Ending::Quit);
+                }
+                Some(Effect::Send(action)) => {
+                    // a failure shows up on the stream as well
+                    let _ = sink.send(send(&action)).await;
+                }
+                Some(Effect::Connect) | Some(Effect::Reconnect) | None => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options_take_a_url_and_a_language() {
+        let options = parse(&["ws://example.org:9001/websocket", "--language", "en_GB"]).unwrap();
+        assert_eq!(options.url, "ws://example.org:9001/websocket");
+        assert_eq!(options.language, Language::English);
+        assert_eq!(
+            parse(&["--language", "fr"]).unwrap_err(),
+            "unknown language fr"
+        );
+        let options = parse(&["wss://example.org/websocket/stammtisch"]).unwrap();
+        assert_eq!(options.url, "wss://example.org/websocket/stammtisch");
+        assert!(parse(&["http://example.org"]).is_err());
+    }
+}
@@ -0,0 +1,387 @@
+//! Draws the app like the frontend lays out its page: notices on top, then
+//! what the phase is about and the controls, the players and the history
+//! on the side and the keys at the bottom.
+
+use super::app::{App, Connection, Control};
+use common::game::{
+    Hint, InactiveHintFiltering, InactiveJudging, Player, VisibleGamePhase, VisibleGuessing,
+    VisibleHintCollection, VisibleHintFiltering, VisibleJudging,
+};
+use common::i18n::Text;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+use std::time::Instant;
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let t = |text: Text| app.text(text);
+    let status = status(app);
+    let area = frame.area();
+    let status_height = if status.is_empty() {
+        0
+    } else {
+        height(&status, area.width)
+    };
+    let [status_area, main, keys] = Layout::vertical([
+        Constraint::Length(status_height),
+        Constraint::Fill(1),
+        Constraint::Length(1),
+    ])
+    .areas(area);
+    let [left, right] =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(main);
+    let prelude = prelude(app);
+    let [prelude_area, controls_area] = Layout::vertical([
+        Constraint::Max(height(&prelude, left.width)),
+        Constraint::Fill(1),
+    ])
+    .areas(left);
+    let [players_area, history_area] =
+        Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(right);
+
+    if !status.is_empty() {
+        let status = Paragraph::new(status)
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered());
+        frame.render_widget(status, status_area);
+    }
+    let prelude = Paragraph::new(prelude)
+        .wrap(Wrap { trim: false })
+        .block(Block::bordered().title(" BeUnique "));
+    frame.render_widget(prelude, prelude_area);
+
+    let controls = app.controls();
+    let mut state = ListState::default().with_selected(app.focus(&controls));
+    let items: Vec<Line> = controls
+        .iter()
+        .enumerate()
+        .map(|(i, control)| control_line(app, control, state.selected() == Some(i)))
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::new().add_modifier(Modifier::BOLD))
+        .block(Block::bordered());
+    frame.render_stateful_widget(list, controls_area, &mut state);
+
+    let players = Paragraph::new(players(app))
+        .wrap(Wrap { trim: false })
+        .block(Block::bordered().title(format!(" {} ", t(Text::PlayersHeading).trim())));
+    frame.render_widget(players, players_area);
+    let history = Paragraph::new(history(app))
+        .wrap(Wrap { trim: false })
+        .block(Block::bordered().title(format!(" {} ", t(Text::History).trim())));
+    frame.render_widget(history, history_area);
+
+    let help = format!(
+        "{} · {}: {}",
+        t(Text::Keys),
+        t(Text::Language),
+        app.language.name()
+    );
+    frame.render_widget(Line::from(help).dim(), keys);
+}
+
+/// Rows of the lines wrapped inside a border of `width` columns.
+fn height(lines: &[Line], width: u16) -> u16 {
+    let inner = width.saturating_sub(2).max(1) as usize;
+    let rows: usize = lines
+        .iter()
+        .map(|line| line.width().div_ceil(inner).max(1))
+        .sum();
+    rows as u16 + 2
+}
+
+/// The notice and how the connection is doing, empty if there is nothing to say.
+fn status(app: &App) -> Vec<Line<'static>> {
+    let mut lines = vec![];
+    if let Some(notice) = &app.notice {
+        let dismiss = format!("  [Esc: {}]", app.text(Text::Ok));
+        lines.push(Line::from(vec![
+            Span::from(app.text(notice.clone())).bold(),
+            Span::from(dismiss).dim(),
+        ]));
+    }
+    if app.reconnecting {
+        let status = match app.connection {
+            Connection::Waiting(at) => app.text(Text::ReconnectIn {
+                seconds: at
+                    .saturating_duration_since(Instant::now())
+                    .as_secs_f64()
+                    .ceil() as u64,
+            }),
+            _ => app.text(Text::Reconnecting),
+        };
+        lines.push(Line::from(status));
+    }
+    lines
+}
+
+fn control_line<'a>(app: &App, control: &'a Control, focused: bool) -> Line<'a> {
+    match control {
+        Control::Input(field) => {
+            let mut spans = vec![
+                Span::from(app.text(field.label())),
+                Span::from(app.typed(field).to_string()).underlined(),
+            ];
+            if focused {
+                spans.push(Span::from(" ").reversed());
+            }
+            Line::from(spans)
+        }
+        Control::Button(label, _) => Line::from(format!("[ {} ]", label)),
+        Control::Checkbox {
+            label,
+            checked,
+            action,
+        } => {
+            let line = Line::from(format!("[{}] {}", if *checked { 'x' } else { ' ' }, label));
+            if action.is_some() {
+                line
+            } else {
+                line.dim()
+            }
+        }
+        Control::Label(text) => Line::from(text.as_str()).italic(),
+    }
+}
+
+/// What the phase is about, the same texts as above the frontend's controls.
+fn prelude(app: &App) -> Vec<Line<'static>> {
+    let t = |text: Text| app.text(text);
+    match &app.view.phase {
+        VisibleGamePhase::GatherPlayers => match app.view.me {
+            Some(_) => vec![t(Text::NotStarted).into()],
+            None => vec![t(Text::NotJoined).into()],
+        },
+        VisibleGamePhase::HintCollection(VisibleHintCollection::Active(collection)) => {
+            vec![t(Text::HintsReceived(collection.players_done.len())).into()]
+        }
+        VisibleGamePhase::HintCollection(VisibleHintCollection::Inactive(collection)) => {
+            let text = match &collection.hint {
+                Some(hint) => Text::YourHint {
+                    hint: hint.content.clone(),
+                    word: collection.word.clone(),
+                },
+                None => Text::GiveHint {
+                    active_player: collection.active_player.clone(),
+                    word: collection.word.clone(),
+                },
+            };
+            vec![t(text).into()]
+        }
+        VisibleGamePhase::HintFiltering(VisibleHintFiltering::Active(filtering)) => {
+            vec![t(Text::HintsLeft(filtering.players_valid_hints.len())).into()]
+        }
+        VisibleGamePhase::HintFiltering(VisibleHintFiltering::Inactive(
+            InactiveHintFiltering { hint_filtering, .. },
+        )) => vec![t(Text::WhichHintsValid {
+            word: hint_filtering.word.clone(),
+        })
+        .into()],
+        VisibleGamePhase::Guessing(VisibleGuessing::Active(guessing)) => {
+            let mut lines = vec![t(Text::TheHintsAre).into()];
+            lines.extend(
+                app.view
+                    .players
+                    .iter()
+                    .filter_map(|p| guessing.hints.get(&p.name))
+                    .map(|hint| Line::from(format!("  • {}", hint)).bold()),
+            );
+            lines.push(t(Text::WhichWord).into());
+            lines
+        }
+        VisibleGamePhase::Guessing(VisibleGuessing::Inactive(guessing)) => {
+            vec![t(Text::WaitingForGuess {
+                active_player: guessing.active_player.clone(),
+            })
+            .into()]
+        }
+        VisibleGamePhase::Judging(VisibleJudging::Active(judging))
+        | VisibleGamePhase::Judging(VisibleJudging::Inactive(InactiveJudging {
+            judging, ..
+        })) => {
+            let mut lines = vec![
+                t(Text::TheWordWas {
+                    word: judging.word.clone(),
+                })
+                .into(),
+                Line::default(),
+                Line::from(t(Text::HintsHeading)).underlined(),
+            ];
+            let mut hints: Vec<(&String, &Hint)> = judging.hints.iter().collect();
+            hints.sort();
+            for (author, hint) in hints {
+                let content = Span::from(hint.content.clone());
+                lines.push(Line::from(vec![
+                    Span::from(format!("{}: ", author)),
+                    if hint.allowed {
+                        content
+                    } else {
+                        content.crossed_out()
+                    },
+                ]));
+            }
+            lines
+        }
+    }
+}
+
+fn players(app: &App) -> Vec<Line<'static>> {
+    if app.view.players.is_empty() {
+        return vec![app.text(Text::NobodyHere).into()];
+    }
+    let is_me = |player: &Player| matches!(&app.view.me, Some(me) if me.name == player.name);
+    app.view
+        .players
+        .iter()
+        .map(|p| {
+            let mut content = p.name.clone();
+            if is_me(p) {
+                content += &app.text(Text::Me);
+            }
+            if let Some(difficulty) = p.bot {
+                content += &app.text(Text::Bot(difficulty));
+            }
+            if p.id.is_none() {
+                content += &app.text(Text::ConnectionLost);
+            }
+            Line::from(content)
+        })
+        .collect()
+}
+
+fn history(app: &App) -> Vec<Line<'static>> {
+    let rounds = &app.view.past_rounds;
+    if rounds.is_empty() {
+        return vec![];
+    }
+    let summary = app.text(Text::Summary {
+        successes: rounds.iter().filter(|p| p.success).count(),
+        rounds: rounds.len(),
+    });
+    let mut lines = vec![Line::from(summary), Line::default()];
+    lines.extend(rounds.iter().map(|round| {
+        let (name, word) = (round.name.clone(), round.word.clone());
+        if round.success {
+            Line::from(app.text(Text::Guessed { name, word }))
+        } else {
+            Line::from(app.text(Text::NotGuessed { name, word })).dim()
+        }
+    }));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::{Action, Library, ServerMessage, State, WordPack};
+    use common::i18n::Language;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn screen(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn every_phase_is_drawn_for_both_roles() {
+        let mut state = State::new(Library::new(vec![WordPack::new(
+            "test",
+            "de",
+            vec!["Geheimnis".into()],
+        )]));
+        for (id, name) in [(1, "a"), (2, "b"), (3, "c")] {
+            state
+                .action(&Action::Join {
+                    id,
+                    name: name.to_string(),
+                })
+                .unwrap();
+        }
+        let mut active = App::new(Language::English);
+        let mut inactive = App::new(Language::English);
+        let mut seen = vec![];
+        let mut show = |state: &State, seen: &mut Vec<(String, String)>| {
+            active.receive(ServerMessage::View {
+                view: Box::new(state.get_view(1)),
+            });
+            inactive.receive(ServerMessage::View {
+                view: Box::new(state.get_view(2)),
+            });
+            seen.push((screen(&active), screen(&inactive)));
+        };
+        show(&state, &mut seen);
+        state.action(&Action::Start { id: 1 }).unwrap();
+        show(&state, &mut seen);
+        state
+            .action(&Action::GiveHint {
+                id: 2,
+                hint: Some("Versteck".to_string()),
+            })
+            .unwrap();
+        state
+            .action(&Action::GiveHint {
+                id: 3,
+                hint: Some("Rätsel".to_string()),
+            })
+            .unwrap();
+        show(&state, &mut seen);
+        state
+            .action(&Action::FilterHint {
+                id: 2,
+                hint: "Rätsel".to_string(),
+                allowed: false,
+            })
+            .unwrap();
+        state
+            .action(&Action::FinishHintFiltering { id: 2 })
+            .unwrap();
+        show(&state, &mut seen);
+        state
+            .action(&Action::Guess {
+                id: 1,
+                guess: Some("Geheimnis".to_string()),
+            })
+            .unwrap();
+        show(&state, &mut seen);
+        state.action(&Action::FinishJudging { id: 2 }).unwrap();
+        show(&state, &mut seen);
+
+        let expected = [
+            ("The game hasn't started yet", "[x] test"),
+            ("No hints have arrived", "Hint: "),
+            ("are left", "[x] Versteck (valid)"),
+            ("• Versteck", "Waiting for a to guess"),
+            ("Hints", "[x] Geheimnis (correct)"),
+            ("a guessed", "b (me)"),
+        ];
+        for ((active, inactive), (in_active, in_inactive)) in seen.iter().zip(expected) {
+            assert!(
+                active.contains(in_active),
+                "{} not in\n{}",
+                in_active,
+                active
+            );
+            assert!(
+                inactive.contains(in_inactive),
+                "{} not in\n{}",
+                in_inactive,
+                inactive
+            );
+        }
+        // the active player neither sees the word nor the hint which was filtered
+        for (active, _) in seen.iter().take(4) {
+            assert!(!active.contains("Geheimnis") && !active.contains("Rätsel"));
+        }
+    }
+}